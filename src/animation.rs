@@ -0,0 +1,2237 @@
+use crate::convert::{parse_tree, render_tree, ConversionOptions};
+use crate::encode::{self, OutputFormat};
+use crate::icc;
+use crate::outline::name_length;
+use crate::sanitize::attribute_range;
+use crate::svg_writer::{escape, number};
+use crate::video;
+use image::codecs::gif::{GifEncoder, Repeat};
+use rayon::prelude::*;
+use resvg::tiny_skia::Pixmap;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use usvg::roxmltree;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+
+/// SMIL animation elements.
+const ANIMATIONS: [&str; 5] = [
+    "animate",
+    "set",
+    "animateColor",
+    "animateTransform",
+    "animateMotion",
+];
+
+/// Properties that a `style` attribute overrides, so animated values are written there.
+const PRESENTATION: [&str; 47] = [
+    "clip-path",
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-rendering",
+    "display",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "filter",
+    "flood-color",
+    "flood-opacity",
+    "font",
+    "font-family",
+    "font-size",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "image-rendering",
+    "isolation",
+    "letter-spacing",
+    "lighting-color",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "mask",
+    "mix-blend-mode",
+    "opacity",
+    "overflow",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "text-decoration",
+    "text-rendering",
+    "visibility",
+    "word-spacing",
+];
+
+/// Animations aren't followed past this many seconds, e.g. when two restart each other.
+const HORIZON: f64 = 600.0;
+
+/// Longest loop made to fit animations that repeat forever with different durations.
+const MAX_LOOP: f64 = 60.0;
+
+/// How SMIL and CSS animations are sampled into frames.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AnimationOptions {
+    pub fps: f32,
+    /// Seconds to record; `None` runs until every animation has finished or, for ones that
+    /// repeat forever, until they all line up again.
+    pub duration: Option<f32>,
+    /// Video bitrate in kbit/s; `None` leaves it to ffmpeg's quality defaults.
+    pub bitrate: Option<u32>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            fps: 25.0,
+            duration: None,
+            bitrate: None,
+        }
+    }
+}
+
+/// Renders the animation in `svg_data` as `options.format` into `output_path`.
+pub fn save(
+    svg_data: &str,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if matches!(options.format, OutputFormat::Mp4 | OutputFormat::Webm) {
+        return video::save(svg_data, input_path, output_path, options, check_cancel);
+    }
+    std::fs::write(
+        output_path,
+        encode(svg_data, input_path, options, check_cancel)?,
+    )?;
+    Ok(())
+}
+
+/// Writes each frame of the animation in `svg_data` as `frame_0001.png`, `frame_0002.png`
+/// and so on into `dir`, and returns their paths.
+pub fn save_frames(
+    svg_data: &str,
+    input_path: &str,
+    dir: &Path,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+    let png = ConversionOptions {
+        format: OutputFormat::Png,
+        ..options.clone()
+    };
+    let mut written = Vec::new();
+    let rendered = render_frames(svg_data, input_path, options, check_cancel, |frame| {
+        let path = dir
+            .join(format!("frame_{:04}.png", written.len() + 1))
+            .display()
+            .to_string();
+        encode::save(&frame, input_path, &path, &png)?;
+        written.push(path);
+        Ok(())
+    });
+    // A partial sequence would be mistaken for the whole animation.
+    if rendered.is_err() {
+        for path in &written {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    rendered.map(|()| written)
+}
+
+/// The animation encoded as `options.format`, like [`save`] writes it. An SVG without
+/// animations becomes a single frame.
+pub fn encode(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let fps = options.animation.fps;
+    let mut data = Vec::new();
+    match options.format {
+        OutputFormat::Gif => {
+            let mut encoder = GifEncoder::new_with_speed(&mut data, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            render_frames(svg_data, input_path, options, check_cancel, |frame| {
+                encoder.encode_frame(encode::gif_frame(&frame, fps)?)?;
+                Ok(())
+            })?;
+        }
+        OutputFormat::Mp4 | OutputFormat::Webm => {
+            return video::encode(svg_data, input_path, options, check_cancel)
+        }
+        OutputFormat::Apng => {
+            let frames = frame_count(&Timeline::parse(svg_data)?, options);
+            let mut info = png::Info::with_size(0, 0);
+            info.color_type = png::ColorType::Rgba;
+            info.bit_depth = png::BitDepth::Eight;
+            // The header needs the size of the first frame, so the writer is made with it.
+            let mut sink = Some(&mut data);
+            let mut writer = None;
+            render_frames(svg_data, input_path, options, check_cancel, |frame| {
+                if let Some(data) = sink.take() {
+                    (info.width, info.height) = (frame.width(), frame.height());
+                    let mut encoder = encode::png_encoder(data, info.clone(), input_path, options)?;
+                    // Zero plays loops forever.
+                    encoder.set_animated(frames as u32, 0)?;
+                    let (numerator, denominator) = frame_delay(fps);
+                    encoder.set_frame_delay(numerator, denominator)?;
+                    writer = Some(encoder.write_header()?);
+                }
+                let writer = writer.as_mut().unwrap();
+                writer.write_image_data(&encode::unpremultiply(&frame))?;
+                Ok(())
+            })?;
+            writer.ok_or("No frames were rendered")?.finish()?;
+            if let Some(profile) = &options.icc_profile {
+                data = icc::embed_in_png(&data, profile)?;
+            }
+        }
+        format => return Err(format!("{} output can't be animated", format.name()).into()),
+    }
+    Ok(data)
+}
+
+/// Renders each frame at `options.animation.fps`, several at a time, and passes them to
+/// `each` in order. Every frame keeps the size and view box of the first, so cropping to
+/// the content doesn't make the animation jump around.
+pub fn render_frames(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+    mut each: impl FnMut(Pixmap) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fps = options.animation.fps as f64;
+    if fps.is_nan() || fps <= 0.0 {
+        return Err("Frames per second must be above 0".into());
+    }
+    let timeline = Timeline::parse(svg_data)?;
+    let count = frame_count(&timeline, options);
+
+    let first = parse_tree(&timeline.frame(0.0), input_path, options)?;
+    let (size, view_box) = (first.size, first.view_box);
+    let render = |index: usize| -> Result<Pixmap, String> {
+        let markup = timeline.frame(index as f64 / fps);
+        let mut rtree = parse_tree(&markup, input_path, options).map_err(|e| e.to_string())?;
+        rtree.size = size;
+        rtree.view_box = view_box;
+        render_tree(&rtree, options).map_err(|e| e.to_string())
+    };
+
+    let batch = rayon::current_num_threads().max(1);
+    for start in (0..count).step_by(batch) {
+        let frames: Vec<Pixmap> = (start..(start + batch).min(count))
+            .into_par_iter()
+            .map(render)
+            .collect::<Result<_, _>>()?;
+        check_cancel()?;
+        for frame in frames {
+            each(frame)?;
+        }
+    }
+    Ok(())
+}
+
+/// How many frames [`render_frames`] renders: the requested duration, or else the
+/// animation's own, at `options.animation.fps`, and at least one.
+pub fn frame_count(timeline: &Timeline, options: &ConversionOptions) -> usize {
+    let duration = options
+        .animation
+        .duration
+        .map(f64::from)
+        .or_else(|| timeline.duration())
+        .unwrap_or(0.0);
+    ((duration * options.animation.fps as f64).round() as usize).max(1)
+}
+
+/// An APNG frame delay of 1/`fps` seconds, as the fraction of two 16-bit numbers.
+fn frame_delay(fps: f32) -> (u16, u16) {
+    if fps.fract() == 0.0 && fps <= u16::MAX as f32 {
+        (1, fps as u16)
+    } else {
+        (
+            100,
+            (fps * 100.0).round().clamp(1.0, u16::MAX as f32) as u16,
+        )
+    }
+}
+
+/// The SMIL and CSS animations of a document, which rewrite its markup at any given time.
+pub struct Timeline<'a> {
+    svg_data: &'a str,
+    targets: Vec<Target>,
+    smil: Vec<Smil>,
+    css: Vec<CssAnimation>,
+}
+
+/// An animated element, as found in the markup.
+struct Target {
+    /// Position after the element name, where attributes can be added.
+    name_end: usize,
+    /// Local names of the attributes, with their range in the markup and value.
+    attributes: Vec<(String, Range<usize>, String)>,
+    /// Declarations from style sheets and then the `style` attribute, later ones winning.
+    declarations: Vec<(String, String)>,
+}
+
+impl Target {
+    fn new(svg_data: &str, node: roxmltree::Node, sheet: &simplecss::StyleSheet) -> Self {
+        let start = node.range().start;
+        let attributes = node
+            .attributes()
+            .filter_map(|attribute| {
+                let range = attribute_range(svg_data, attribute.position())?;
+                Some((
+                    attribute.name().to_string(),
+                    range,
+                    attribute.value().to_string(),
+                ))
+            })
+            .collect();
+        Self {
+            name_end: start + name_length(&svg_data[start..]),
+            attributes,
+            declarations: declarations(node, sheet),
+        }
+    }
+
+    /// The value an animation starts from when it doesn't say.
+    fn base(&self, name: &str) -> Option<&str> {
+        let declared = self
+            .declarations
+            .iter()
+            .rev()
+            .find(|(property, _)| property == name && PRESENTATION.contains(&name))
+            .map(|(_, value)| value.as_str());
+        declared.or_else(|| self.attribute(name))
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _, _)| attribute == name)
+            .map(|(_, _, value)| value.as_str())
+    }
+}
+
+impl<'a> Timeline<'a> {
+    pub fn parse(svg_data: &'a str) -> Result<Timeline<'a>, roxmltree::Error> {
+        let document = roxmltree::Document::parse_with_options(
+            svg_data,
+            roxmltree::ParsingOptions {
+                allow_dtd: true,
+                ..Default::default()
+            },
+        )?;
+        let mut timeline = Timeline {
+            svg_data,
+            targets: Vec::new(),
+            smil: Vec::new(),
+            css: Vec::new(),
+        };
+
+        let mut sheet = simplecss::StyleSheet::new();
+        let style_texts: Vec<&str> = document
+            .descendants()
+            .filter(|node| node.has_tag_name((SVG_NS, "style")))
+            .filter(|node| node.attribute("type").is_none_or(|kind| kind == "text/css"))
+            .filter_map(|node| node.text())
+            .collect();
+        for text in &style_texts {
+            sheet.parse_more(text);
+        }
+
+        let by_id: HashMap<&str, roxmltree::Node> = document
+            .descendants()
+            .filter_map(|node| Some((node.attribute("id")?, node)))
+            .collect();
+        let mut target_index: HashMap<roxmltree::NodeId, usize> = HashMap::new();
+        let mut target_of = |timeline: &mut Timeline, node: roxmltree::Node| {
+            *target_index.entry(node.id()).or_insert_with(|| {
+                timeline.targets.push(Target::new(svg_data, node, &sheet));
+                timeline.targets.len() - 1
+            })
+        };
+
+        // SMIL, timed once all of them are known, since they can start when others do.
+        let mut timings = Vec::new();
+        let mut smil_ids = HashMap::new();
+        for node in document.descendants() {
+            if node.tag_name().namespace() != Some(SVG_NS)
+                || !ANIMATIONS.contains(&node.tag_name().name())
+            {
+                continue;
+            }
+            let target = match href(node) {
+                Some(href) => by_id.get(href.trim_start_matches('#')).copied(),
+                None => node.parent_element(),
+            };
+            let Some(target) = target else {
+                continue;
+            };
+            let index = target_of(&mut timeline, target);
+            let Some(smil) = Smil::parse(node, &timeline.targets[index], &by_id, index) else {
+                continue;
+            };
+            if let Some(id) = node.attribute("id") {
+                smil_ids.insert(id.to_string(), timeline.smil.len());
+            }
+            timings.push(TimingSpec::parse(node));
+            timeline.smil.push(smil);
+        }
+        resolve_timings(&mut timeline.smil, &timings, &smil_ids);
+
+        let keyframes: HashMap<String, Vec<Keyframe>> = style_texts
+            .iter()
+            .flat_map(|text| keyframes(text))
+            .collect();
+        if !keyframes.is_empty() {
+            let view_box = view_box(document.root_element());
+            for node in document.descendants().filter(|node| node.is_element()) {
+                let found = declarations(node, &sheet);
+                let specs = animation_specs(&found);
+                if specs.is_empty() {
+                    continue;
+                }
+                let mut index = None;
+                for spec in specs {
+                    let Some(frames) = keyframes.get(&spec.name) else {
+                        continue;
+                    };
+                    let target = *index.get_or_insert_with(|| target_of(&mut timeline, node));
+                    let origin = frames
+                        .iter()
+                        .any(|frame| frame.value("transform").is_some())
+                        .then(|| transform_origin(node, &found, view_box))
+                        .flatten();
+                    timeline.css.push(CssAnimation {
+                        target,
+                        keyframes: frames.clone(),
+                        spec,
+                        origin,
+                    });
+                }
+            }
+        }
+        Ok(timeline)
+    }
+
+    /// Seconds until every animation has finished, or until the ones that repeat forever
+    /// line up again; `None` when nothing moves.
+    pub fn duration(&self) -> Option<f64> {
+        let mut end: f64 = 0.0;
+        let mut periods = Vec::new();
+        for smil in &self.smil {
+            match (smil.period, smil.intervals.last()) {
+                (Some(period), _) => periods.push(period),
+                (None, Some(&(begin, interval_end))) => {
+                    end = end.max(interval_end.unwrap_or(begin))
+                }
+                (None, None) => {}
+            }
+        }
+        for css in &self.css {
+            let spec = &css.spec;
+            if spec.iterations.is_infinite() {
+                let alternates = matches!(
+                    spec.direction,
+                    Direction::Alternate | Direction::AlternateReverse
+                );
+                periods.push(spec.duration * if alternates { 2.0 } else { 1.0 });
+            } else {
+                end = end.max(spec.delay + spec.duration * spec.iterations);
+            }
+        }
+        periods.retain(|&period| period > 0.0);
+        if !periods.is_empty() {
+            let period = common_period(&periods);
+            end = (end / period).ceil().max(1.0) * period;
+        }
+        (end > 0.0).then_some(end)
+    }
+
+    /// The markup with every animated value set to what it is `time` seconds in.
+    pub fn frame(&self, time: f64) -> String {
+        if self.smil.is_empty() && self.css.is_empty() {
+            return self.svg_data.to_string();
+        }
+        let mut values: Vec<Vec<(String, String)>> = vec![Vec::new(); self.targets.len()];
+        let mut motions: Vec<Option<String>> = vec![None; self.targets.len()];
+
+        // SMIL comes last, since it overrides style sheets, animated ones included.
+        for css in &self.css {
+            let target = &self.targets[css.target];
+            for (name, value) in css.sample(time, target) {
+                set_value(&mut values[css.target], name, value);
+            }
+        }
+        for smil in &self.smil {
+            let Some((progress, iteration)) = smil.progress(time) else {
+                continue;
+            };
+            let current = values[smil.target]
+                .iter()
+                .find(|(name, _)| *name == smil.attribute)
+                .map(|(_, value)| value.as_str())
+                .or_else(|| self.targets[smil.target].base(&smil.attribute));
+            match smil.sample(progress, iteration, current) {
+                Sampled::Value(value) => {
+                    let name = smil.attribute.clone();
+                    set_value(&mut values[smil.target], name, value);
+                }
+                Sampled::Motion(motion) => {
+                    let previous = motions[smil.target].take();
+                    motions[smil.target] = Some(match (previous, smil.additive) {
+                        (Some(previous), true) => format!("{} {}", previous, motion),
+                        _ => motion,
+                    });
+                }
+            }
+        }
+        for (index, motion) in motions.into_iter().enumerate() {
+            let Some(motion) = motion else {
+                continue;
+            };
+            let transform = values[index]
+                .iter()
+                .find(|(name, _)| name == "transform")
+                .map(|(_, value)| value.as_str())
+                .or_else(|| self.targets[index].attribute("transform"))
+                .unwrap_or("");
+            let transform = format!("{} {}", motion, transform).trim_end().to_string();
+            set_value(&mut values[index], String::from("transform"), transform);
+        }
+
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for (target, values) in self.targets.iter().zip(&values) {
+            let mut added = String::new();
+            let mut style = String::new();
+            for (name, value) in values {
+                if PRESENTATION.contains(&name.as_str()) {
+                    style.push_str(&format!("{}:{};", name, value));
+                } else if let Some((_, range, _)) = target.attributes.iter().find(|a| a.0 == *name)
+                {
+                    edits.push((range.clone(), format!("{}=\"{}\"", name, escape(value))));
+                } else {
+                    added.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+                }
+            }
+            if !style.is_empty() {
+                match target.attributes.iter().find(|a| a.0 == "style") {
+                    Some((_, range, old)) => edits.push((
+                        range.clone(),
+                        format!("style=\"{};{}\"", escape(old), escape(&style)),
+                    )),
+                    None => added.push_str(&format!(" style=\"{}\"", escape(&style))),
+                }
+            }
+            if !added.is_empty() {
+                edits.push((target.name_end..target.name_end, added));
+            }
+        }
+
+        edits.sort_by_key(|(range, _)| range.start);
+        let mut markup = self.svg_data.to_string();
+        for (range, replacement) in edits.into_iter().rev() {
+            markup.replace_range(range, &replacement);
+        }
+        markup
+    }
+}
+
+fn set_value(values: &mut Vec<(String, String)>, name: String, value: String) {
+    match values.iter_mut().find(|(existing, _)| *existing == name) {
+        Some(existing) => existing.1 = value,
+        None => values.push((name, value)),
+    }
+}
+
+/// Style sheet declarations that apply to `node`, followed by its `style` attribute.
+fn declarations(node: roxmltree::Node, sheet: &simplecss::StyleSheet) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = sheet
+        .rules
+        .iter()
+        .filter(|rule| rule.selector.matches(&XmlNode(node)))
+        .flat_map(|rule| &rule.declarations)
+        .map(|declaration| (declaration.name.to_string(), declaration.value.to_string()))
+        .collect();
+    if let Some(style) = node.attribute("style") {
+        found.extend(
+            simplecss::DeclarationTokenizer::from(style)
+                .map(|declaration| (declaration.name.to_string(), declaration.value.to_string())),
+        );
+    }
+    found
+}
+
+fn href<'a>(node: roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attributes()
+        .find(|attribute| attribute.name() == "href")
+        .map(|attribute| attribute.value())
+}
+
+struct XmlNode<'a, 'input>(roxmltree::Node<'a, 'input>);
+
+impl simplecss::Element for XmlNode<'_, '_> {
+    fn parent_element(&self) -> Option<Self> {
+        self.0.parent_element().map(XmlNode)
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.0.prev_sibling_element().map(XmlNode)
+    }
+
+    fn has_local_name(&self, local_name: &str) -> bool {
+        self.0.tag_name().name() == local_name
+    }
+
+    fn attribute_matches(&self, local_name: &str, operator: simplecss::AttributeOperator) -> bool {
+        self.0
+            .attribute(local_name)
+            .is_some_and(|value| operator.matches(value))
+    }
+
+    fn pseudo_class_matches(&self, class: simplecss::PseudoClass) -> bool {
+        match class {
+            simplecss::PseudoClass::FirstChild => self.0.prev_sibling_element().is_none(),
+            _ => false,
+        }
+    }
+}
+
+/// One SMIL animation element.
+struct Smil {
+    target: usize,
+    /// The animated attribute; `transform` for `<animateTransform>` and `<animateMotion>`.
+    attribute: String,
+    kind: SmilKind,
+    values: Vec<String>,
+    key_times: Option<Vec<f64>>,
+    key_splines: Vec<Easing>,
+    discrete: bool,
+    additive: bool,
+    accumulate: bool,
+    /// Seconds of one simple duration; `None` when indefinite.
+    dur: Option<f64>,
+    freeze: bool,
+    /// Start and end of each time the animation runs, `None` ending never.
+    intervals: Vec<(f64, Option<f64>)>,
+    /// How often the animation starts over forever after, if it does.
+    period: Option<f64>,
+}
+
+enum SmilKind {
+    Value,
+    /// `<animateTransform>` of this type, e.g. `rotate`.
+    Transform(String),
+    Motion(Motion),
+}
+
+enum Sampled {
+    Value(String),
+    Motion(String),
+}
+
+impl Smil {
+    fn parse(
+        node: roxmltree::Node,
+        target: &Target,
+        by_id: &HashMap<&str, roxmltree::Node>,
+        target_index: usize,
+    ) -> Option<Smil> {
+        let element = node.tag_name().name();
+        let attribute = match element {
+            "animateTransform" | "animateMotion" => String::from("transform"),
+            _ => {
+                let name = node.attribute("attributeName")?;
+                name.rsplit(':').next().unwrap_or(name).to_string()
+            }
+        };
+        let kind = match element {
+            "animateTransform" => {
+                SmilKind::Transform(node.attribute("type").unwrap_or("translate").to_string())
+            }
+            "animateMotion" => SmilKind::Motion(Motion::parse(node, by_id)?),
+            _ => SmilKind::Value,
+        };
+
+        let additive = node.attribute("additive") == Some("sum");
+        let accumulate = node.attribute("accumulate") == Some("sum");
+        let base = match &kind {
+            SmilKind::Value => target.base(&attribute).map(String::from),
+            _ => None,
+        };
+        let values: Vec<String> = if element == "set" {
+            vec![node.attribute("to")?.trim().to_string()]
+        } else if let Some(values) = node.attribute("values") {
+            values
+                .split(';')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+                .collect()
+        } else {
+            match (
+                node.attribute("from"),
+                node.attribute("to"),
+                node.attribute("by"),
+            ) {
+                (Some(from), Some(to), _) => vec![from.trim().to_string(), to.trim().to_string()],
+                (Some(from), None, Some(by)) => {
+                    vec![from.trim().to_string(), add(from.trim(), by.trim())]
+                }
+                (None, Some(to), _) => {
+                    let from = base.unwrap_or_else(|| to.trim().to_string());
+                    vec![from, to.trim().to_string()]
+                }
+                (None, None, Some(by)) => vec![scale(by.trim(), 0.0), by.trim().to_string()],
+                _ => Vec::new(),
+            }
+        };
+        let by_only = node.attribute("values").is_none()
+            && node.attribute("from").is_none()
+            && node.attribute("to").is_none()
+            && node.attribute("by").is_some();
+        if values.is_empty() && !matches!(kind, SmilKind::Motion(_)) {
+            return None;
+        }
+
+        let interpolable = values
+            .windows(2)
+            .all(|pair| interpolate(&pair[0], &pair[1], 0.5).is_some());
+        let calc_mode = node.attribute("calcMode").unwrap_or("linear");
+        let discrete = element == "set" || calc_mode == "discrete" || !interpolable;
+        let key_times = node
+            .attribute("keyTimes")
+            .map(|text| {
+                text.split(';')
+                    .filter_map(|time| time.trim().parse().ok())
+                    .collect::<Vec<f64>>()
+            })
+            .filter(|times| {
+                let count = if matches!(kind, SmilKind::Motion(_)) {
+                    times.len()
+                } else {
+                    values.len()
+                };
+                times.len() == count && times.first() == Some(&0.0)
+            });
+        let key_splines = match (calc_mode, node.attribute("keySplines")) {
+            ("spline", Some(splines)) => splines
+                .split(';')
+                .filter_map(|spline| {
+                    let numbers: Vec<f64> = spline
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter_map(|number| number.parse().ok())
+                        .collect();
+                    match numbers[..] {
+                        [x1, y1, x2, y2] => Some(Easing::CubicBezier(x1, y1, x2, y2)),
+                        _ => None,
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Some(Smil {
+            target: target_index,
+            attribute,
+            kind,
+            values,
+            key_times,
+            key_splines,
+            discrete,
+            additive: additive || by_only,
+            accumulate,
+            dur: None,
+            freeze: node.attribute("fill") == Some("freeze"),
+            intervals: Vec::new(),
+            period: None,
+        })
+    }
+
+    /// Progress through the simple duration at `time`, and how many times it repeated.
+    fn progress(&self, time: f64) -> Option<(f64, f64)> {
+        let index = self.intervals.partition_point(|&(begin, _)| begin <= time);
+        let (begin, end) = *self.intervals.get(index.checked_sub(1)?)?;
+        let (local, frozen) = match end {
+            Some(end) if time >= end => {
+                if !self.freeze {
+                    return None;
+                }
+                (end - begin, true)
+            }
+            _ => (time - begin, false),
+        };
+        let Some(dur) = self.dur else {
+            return Some((0.0, 0.0));
+        };
+        let repeats = local / dur;
+        let mut iteration = repeats.floor();
+        let mut fraction = repeats - iteration;
+        if frozen && fraction < 1e-9 && iteration > 0.0 {
+            iteration -= 1.0;
+            fraction = 1.0;
+        }
+        Some((fraction, iteration))
+    }
+
+    fn sample(&self, progress: f64, iteration: f64, current: Option<&str>) -> Sampled {
+        if let SmilKind::Motion(motion) = &self.kind {
+            return Sampled::Motion(motion.transform(self.motion_distance(progress, motion)));
+        }
+
+        let mut value = self.value_at(progress);
+        if self.accumulate && iteration > 0.0 && !self.discrete {
+            let last = self.value_at(1.0);
+            value = add(&value, &scale(&last, iteration));
+        }
+        match &self.kind {
+            SmilKind::Transform(kind) => {
+                let transform = format!("{}({})", kind, value);
+                match current.filter(|_| self.additive) {
+                    Some(current) => Sampled::Value(format!("{} {}", current, transform)),
+                    None => Sampled::Value(transform),
+                }
+            }
+            _ => match current.filter(|_| self.additive) {
+                Some(current) => Sampled::Value(add(current, &value)),
+                None => Sampled::Value(value),
+            },
+        }
+    }
+
+    fn value_at(&self, progress: f64) -> String {
+        let count = self.values.len();
+        if count == 1 {
+            return self.values[0].clone();
+        }
+        if self.discrete {
+            let index = match &self.key_times {
+                Some(times) => times
+                    .iter()
+                    .rposition(|&time| time <= progress)
+                    .unwrap_or(0),
+                None => (progress * count as f64).floor() as usize,
+            };
+            return self.values[index.min(count - 1)].clone();
+        }
+        let (segment, local) = self.segment(progress, count - 1);
+        interpolate(&self.values[segment], &self.values[segment + 1], local)
+            .unwrap_or_else(|| self.values[segment].clone())
+    }
+
+    /// The segment of `segments` evenly spread ones, or ones between `keyTimes`, that
+    /// `progress` is in, and how far through it, eased by `keySplines`.
+    fn segment(&self, progress: f64, segments: usize) -> (usize, f64) {
+        let (segment, local) = match &self.key_times {
+            Some(times) if times.len() == segments + 1 => {
+                let segment = times[1..]
+                    .iter()
+                    .position(|&time| progress < time)
+                    .unwrap_or(segments - 1);
+                let (start, end) = (times[segment], times[segment + 1]);
+                let local = if end > start {
+                    (progress - start) / (end - start)
+                } else {
+                    1.0
+                };
+                (segment, local.clamp(0.0, 1.0))
+            }
+            _ => {
+                let position = progress * segments as f64;
+                let segment = (position.floor() as usize).min(segments - 1);
+                (segment, position - segment as f64)
+            }
+        };
+        let local = match self.key_splines.get(segment) {
+            Some(easing) => easing.apply(local),
+            None => local,
+        };
+        (segment, local)
+    }
+
+    /// Fraction of the motion path travelled at `progress`.
+    fn motion_distance(&self, progress: f64, motion: &Motion) -> f64 {
+        if let (Some(points), Some(times)) = (&motion.key_points, &self.key_times) {
+            if points.len() == times.len() && points.len() > 1 {
+                let (segment, local) = self.segment(progress, points.len() - 1);
+                return points[segment] + (points[segment + 1] - points[segment]) * local;
+            }
+        }
+        match &motion.vertices {
+            // Without `calcMode="paced"`, listed points are reached at even or given times.
+            Some(vertices) if !motion.paced && vertices.len() > 1 => {
+                let (segment, local) = self.segment(progress, vertices.len() - 1);
+                let start = vertices[segment];
+                start + (vertices[segment + 1] - start) * local
+            }
+            _ => progress,
+        }
+    }
+}
+
+/// When a SMIL animation starts and stops, before resolving references to other ones.
+struct TimingSpec {
+    begin: Vec<TimeSpec>,
+    end: Vec<TimeSpec>,
+    dur: Option<f64>,
+    repeat_count: Option<f64>,
+    repeat_dur: Option<f64>,
+}
+
+enum TimeSpec {
+    Offset(f64),
+    /// Relative to the begin or end of another animation, e.g. `fade.end+1s`.
+    Sync {
+        id: String,
+        end: bool,
+        offset: f64,
+    },
+}
+
+impl TimingSpec {
+    fn parse(node: roxmltree::Node) -> Self {
+        let indefinite = |value: Option<&str>| match value.map(str::trim) {
+            Some("indefinite") => Some(f64::INFINITY),
+            Some(value) => clock_value(value),
+            None => None,
+        };
+        let begin = match node.attribute("begin") {
+            Some(begin) => time_specs(begin),
+            None => vec![TimeSpec::Offset(0.0)],
+        };
+        Self {
+            begin,
+            end: node.attribute("end").map(time_specs).unwrap_or_default(),
+            dur: node
+                .attribute("dur")
+                .and_then(clock_value)
+                .filter(|&dur| dur > 0.0),
+            repeat_count: match node.attribute("repeatCount").map(str::trim) {
+                Some("indefinite") => Some(f64::INFINITY),
+                Some(count) => count.parse().ok().filter(|&count: &f64| count > 0.0),
+                None => None,
+            },
+            repeat_dur: indefinite(node.attribute("repeatDur")),
+        }
+    }
+
+    /// How long each run lasts, before `end` cuts it short; `None` when indefinite.
+    fn active_duration(&self) -> Option<f64> {
+        let active = match (self.repeat_count, self.repeat_dur) {
+            (None, None) => self.dur,
+            (count, repeat_dur) => {
+                let by_count = count.map(|count| self.dur.map_or(f64::INFINITY, |dur| dur * count));
+                let limits = [by_count, repeat_dur];
+                limits.into_iter().flatten().reduce(f64::min)
+            }
+        };
+        active.filter(|active| active.is_finite())
+    }
+}
+
+fn time_specs(text: &str) -> Vec<TimeSpec> {
+    text.split(';')
+        .filter_map(|item| {
+            let item: String = item.split_whitespace().collect();
+            if let Some(offset) = clock_value(&item) {
+                return Some(TimeSpec::Offset(offset));
+            }
+            for (event, end) in [(".begin", false), (".end", true)] {
+                if let Some(position) = item.find(event) {
+                    let rest = &item[position + event.len()..];
+                    let offset = if rest.is_empty() {
+                        Some(0.0)
+                    } else {
+                        clock_value(rest)
+                    };
+                    return Some(TimeSpec::Sync {
+                        id: item[..position].to_string(),
+                        end,
+                        offset: offset?,
+                    });
+                }
+            }
+            // Clicks and other events never happen here.
+            None
+        })
+        .collect()
+}
+
+/// Seconds in a SMIL clock value such as `2s`, `500ms`, `1.5`, `+1s` or `00:01.5`.
+fn clock_value(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.contains(':') {
+        let parts: Vec<f64> = text
+            .split(':')
+            .map(|part| part.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        return match parts[..] {
+            [minutes, seconds] => Some(minutes * 60.0 + seconds),
+            [hours, minutes, seconds] => Some(hours * 3600.0 + minutes * 60.0 + seconds),
+            _ => None,
+        };
+    }
+    let (number, unit) = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)]
+        .into_iter()
+        .find_map(|(suffix, unit)| Some((text.strip_suffix(suffix)?, unit)))
+        .unwrap_or((text, 1.0));
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .map(|value| value * unit)
+}
+
+/// Works out when each animation runs, following `begin` and `end` references between them
+/// until nothing changes or they pass [`HORIZON`].
+fn resolve_timings(smil: &mut [Smil], timings: &[TimingSpec], ids: &HashMap<String, usize>) {
+    for (animation, timing) in smil.iter_mut().zip(timings) {
+        animation.dur = timing.dur;
+    }
+    let instance_times = |specs: &[TimeSpec], smil: &[Smil]| -> Vec<f64> {
+        let mut times = Vec::new();
+        for spec in specs {
+            match spec {
+                TimeSpec::Offset(offset) => times.push(*offset),
+                TimeSpec::Sync { id, end, offset } => {
+                    let Some(&other) = ids.get(id) else {
+                        continue;
+                    };
+                    for &(begin, interval_end) in &smil[other].intervals {
+                        let time = if *end { interval_end } else { Some(begin) };
+                        times.extend(time.map(|time| time + offset));
+                    }
+                }
+            }
+        }
+        times.retain(|&time| time < HORIZON);
+        times.sort_by(f64::total_cmp);
+        times.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+        times
+    };
+
+    for _ in 0..10_000 {
+        let mut changed = false;
+        for index in 0..smil.len() {
+            let timing = &timings[index];
+            let begins = instance_times(&timing.begin, smil);
+            let ends = instance_times(&timing.end, smil);
+            let active = timing.active_duration();
+            let intervals: Vec<(f64, Option<f64>)> = begins
+                .iter()
+                .enumerate()
+                .map(|(i, &begin)| {
+                    let limits = [
+                        active.map(|active| begin + active),
+                        ends.iter().copied().find(|&end| end >= begin),
+                        // A later begin restarts the animation.
+                        begins.get(i + 1).copied(),
+                    ];
+                    (begin, limits.into_iter().flatten().reduce(f64::min))
+                })
+                .collect();
+            let animation = &mut smil[index];
+            if intervals != animation.intervals {
+                animation.intervals = intervals;
+                changed = true;
+            }
+            let repeats_forever = timing.repeat_count == Some(f64::INFINITY)
+                || timing.repeat_dur == Some(f64::INFINITY);
+            // Restarts that carry on until the horizon, e.g. `begin="0s;other.end"`, loop.
+            let gap = match begins[..] {
+                [.., previous, last] if last + 2.0 * (last - previous) >= HORIZON => {
+                    Some(last - previous)
+                }
+                _ => None,
+            };
+            animation.period = if gap.is_some() {
+                gap
+            } else if repeats_forever
+                && animation
+                    .intervals
+                    .last()
+                    .is_some_and(|last| last.1.is_none())
+            {
+                animation.dur
+            } else {
+                None
+            };
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// The shortest time after which all `periods` have repeated a whole number of times, up to
+/// [`MAX_LOOP`]; beyond that, the longest of them.
+fn common_period(periods: &[f64]) -> f64 {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+    let longest = periods.iter().copied().fold(0.0, f64::max);
+    let mut lcm: u64 = 1;
+    for &period in periods {
+        let millis = ((period * 1000.0).round() as u64).max(1);
+        lcm = lcm / gcd(lcm, millis) * millis;
+        if lcm as f64 / 1000.0 > MAX_LOOP {
+            return longest;
+        }
+    }
+    lcm as f64 / 1000.0
+}
+
+/// The path of an `<animateMotion>`, flattened into line segments.
+struct Motion {
+    points: Vec<(f64, f64)>,
+    /// Length along the path up to each point; jumps from `M` commands don't count.
+    lengths: Vec<f64>,
+    /// Fractions of the length at each of the listed `values`, when there are any.
+    vertices: Option<Vec<f64>>,
+    key_points: Option<Vec<f64>>,
+    paced: bool,
+    rotate: Rotate,
+}
+
+enum Rotate {
+    Fixed(f64),
+    Auto,
+    AutoReverse,
+}
+
+impl Motion {
+    fn parse(node: roxmltree::Node, by_id: &HashMap<&str, roxmltree::Node>) -> Option<Motion> {
+        let mpath = node
+            .children()
+            .find(|child| child.has_tag_name((SVG_NS, "mpath")))
+            .and_then(href)
+            .and_then(|href| by_id.get(href.trim_start_matches('#')))
+            .and_then(|path| path.attribute("d"));
+        let (segments, vertices) = match node.attribute("path").or(mpath) {
+            Some(d) => (path_points(d), None),
+            None => {
+                let points = |text: &str| -> Vec<(f64, f64)> {
+                    text.split(';')
+                        .filter_map(|point| {
+                            let numbers: Vec<f64> = point
+                                .split(|c: char| c == ',' || c.is_whitespace())
+                                .filter_map(|number| number.parse().ok())
+                                .collect();
+                            match numbers[..] {
+                                [x, y] => Some((x, y)),
+                                [x] => Some((x, 0.0)),
+                                _ => None,
+                            }
+                        })
+                        .collect()
+                };
+                let listed = match (
+                    node.attribute("values"),
+                    node.attribute("from"),
+                    node.attribute("to"),
+                    node.attribute("by"),
+                ) {
+                    (Some(values), ..) => points(values),
+                    (None, Some(from), Some(to), _) => [points(from), points(to)].concat(),
+                    (None, Some(from), None, Some(by)) => {
+                        let (from, by) = (points(from), points(by));
+                        let (&(x, y), &(dx, dy)) = (from.first()?, by.first()?);
+                        vec![(x, y), (x + dx, y + dy)]
+                    }
+                    (None, None, Some(to), _) => [vec![(0.0, 0.0)], points(to)].concat(),
+                    (None, None, None, Some(by)) => [vec![(0.0, 0.0)], points(by)].concat(),
+                    _ => Vec::new(),
+                };
+                let segments = listed.iter().map(|&point| (point, false)).collect();
+                (segments, Some(listed.len()))
+            }
+        };
+        if segments.is_empty() {
+            return None;
+        }
+
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        let mut lengths = Vec::new();
+        let mut length = 0.0;
+        for ((x, y), jump) in segments {
+            if let Some(&(px, py)) = points.last() {
+                if !jump {
+                    length += (x - px).hypot(y - py);
+                }
+            }
+            points.push((x, y));
+            lengths.push(length);
+        }
+        let vertices = vertices.map(|_| {
+            lengths
+                .iter()
+                .map(|&at| if length > 0.0 { at / length } else { 0.0 })
+                .collect()
+        });
+        let key_points = node.attribute("keyPoints").map(|text| {
+            text.split(';')
+                .filter_map(|point| point.trim().parse().ok())
+                .collect()
+        });
+        let rotate = match node.attribute("rotate").map(str::trim) {
+            Some("auto") => Rotate::Auto,
+            Some("auto-reverse") => Rotate::AutoReverse,
+            Some(angle) => Rotate::Fixed(angle.parse().unwrap_or(0.0)),
+            None => Rotate::Fixed(0.0),
+        };
+        Some(Motion {
+            points,
+            lengths,
+            vertices,
+            key_points,
+            paced: node
+                .attribute("calcMode")
+                .is_none_or(|mode| mode == "paced"),
+            rotate,
+        })
+    }
+
+    /// `translate` and `rotate` placing the element `fraction` of the way along the path.
+    fn transform(&self, fraction: f64) -> String {
+        let total = *self.lengths.last().unwrap_or(&0.0);
+        let distance = fraction.clamp(0.0, 1.0) * total;
+        let (x, y, angle) = if self.points.len() == 1 || total == 0.0 {
+            (self.points[0].0, self.points[0].1, 0.0)
+        } else {
+            let index = self
+                .lengths
+                .partition_point(|&length| length < distance)
+                .clamp(1, self.points.len() - 1);
+            let (start, end) = (self.points[index - 1], self.points[index]);
+            let span = self.lengths[index] - self.lengths[index - 1];
+            let local = if span > 0.0 {
+                (distance - self.lengths[index - 1]) / span
+            } else {
+                1.0
+            };
+            let angle = (end.1 - start.1).atan2(end.0 - start.0).to_degrees();
+            (
+                start.0 + (end.0 - start.0) * local,
+                start.1 + (end.1 - start.1) * local,
+                angle,
+            )
+        };
+        let rotation = match self.rotate {
+            Rotate::Fixed(angle) => angle,
+            Rotate::Auto => angle,
+            Rotate::AutoReverse => angle + 180.0,
+        };
+        let translate = format!("translate({} {})", number(x), number(y));
+        if rotation == 0.0 {
+            translate
+        } else {
+            format!("{} rotate({})", translate, number(rotation))
+        }
+    }
+}
+
+/// Points along path data, curves split into short lines, each marked when it starts a
+/// new subpath.
+fn path_points(d: &str) -> Vec<((f64, f64), bool)> {
+    const STEPS: usize = 16;
+    let mut points: Vec<((f64, f64), bool)> = Vec::new();
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    for segment in svgtypes::SimplifyingPathParser::from(d).flatten() {
+        match segment {
+            svgtypes::SimplePathSegment::MoveTo { x, y } => {
+                current = (x, y);
+                start = current;
+                points.push((current, true));
+            }
+            svgtypes::SimplePathSegment::LineTo { x, y } => {
+                current = (x, y);
+                points.push((current, false));
+            }
+            svgtypes::SimplePathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (x0, y0) = current;
+                for step in 1..=STEPS {
+                    let t = step as f64 / STEPS as f64;
+                    let u = 1.0 - t;
+                    let point = (
+                        u * u * u * x0
+                            + 3.0 * u * u * t * x1
+                            + 3.0 * u * t * t * x2
+                            + t * t * t * x,
+                        u * u * u * y0
+                            + 3.0 * u * u * t * y1
+                            + 3.0 * u * t * t * y2
+                            + t * t * t * y,
+                    );
+                    points.push((point, false));
+                }
+                current = (x, y);
+            }
+            svgtypes::SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                let (x0, y0) = current;
+                for step in 1..=STEPS {
+                    let t = step as f64 / STEPS as f64;
+                    let u = 1.0 - t;
+                    let point = (
+                        u * u * x0 + 2.0 * u * t * x1 + t * t * x,
+                        u * u * y0 + 2.0 * u * t * y1 + t * t * y,
+                    );
+                    points.push((point, false));
+                }
+                current = (x, y);
+            }
+            svgtypes::SimplePathSegment::ClosePath => {
+                current = start;
+                points.push((current, false));
+            }
+        }
+    }
+    points
+}
+
+/// One CSS animation of one element.
+struct CssAnimation {
+    target: usize,
+    keyframes: Vec<Keyframe>,
+    spec: AnimationSpec,
+    /// `transform-origin`, when it isn't `0 0`.
+    origin: Option<(f64, f64)>,
+}
+
+#[derive(Clone)]
+struct Keyframe {
+    offset: f64,
+    declarations: Vec<(String, String)>,
+    easing: Option<Easing>,
+}
+
+impl Keyframe {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.declarations
+            .iter()
+            .rev()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The `animation-*` properties of one animation.
+struct AnimationSpec {
+    name: String,
+    duration: f64,
+    delay: f64,
+    easing: Easing,
+    iterations: f64,
+    direction: Direction,
+    fill_backwards: bool,
+    fill_forwards: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+impl CssAnimation {
+    /// Progress through the keyframes at `time`, or `None` outside the animation.
+    fn progress(&self, time: f64) -> Option<f64> {
+        let spec = &self.spec;
+        let local = time - spec.delay;
+        let active = if spec.duration > 0.0 {
+            spec.duration * spec.iterations
+        } else {
+            0.0
+        };
+        let (iteration, fraction) = if local < 0.0 {
+            if !spec.fill_backwards {
+                return None;
+            }
+            (0.0, 0.0)
+        } else if local >= active {
+            if !spec.fill_forwards {
+                return None;
+            }
+            let iterations = if spec.iterations.is_finite() {
+                spec.iterations
+            } else {
+                1.0
+            };
+            let iteration = (iterations.ceil() - 1.0).max(0.0);
+            (iteration, (iterations - iteration).min(1.0))
+        } else {
+            let repeats = local / spec.duration;
+            (repeats.floor(), repeats.fract())
+        };
+        let odd = iteration % 2.0 == 1.0;
+        let reverse = match spec.direction {
+            Direction::Normal => false,
+            Direction::Reverse => true,
+            Direction::Alternate => odd,
+            Direction::AlternateReverse => !odd,
+        };
+        Some(if reverse { 1.0 - fraction } else { fraction })
+    }
+
+    fn sample(&self, time: f64, target: &Target) -> Vec<(String, String)> {
+        let Some(progress) = self.progress(time) else {
+            return Vec::new();
+        };
+        let mut names: Vec<&str> = Vec::new();
+        for keyframe in &self.keyframes {
+            for (name, _) in &keyframe.declarations {
+                if !names.contains(&name.as_str()) && !name.starts_with("animation") {
+                    names.push(name);
+                }
+            }
+        }
+
+        let mut sampled = Vec::new();
+        for name in names {
+            let transform = name == "transform";
+            let convert = |value: &str| -> Option<String> {
+                if transform {
+                    css_transform(value)
+                } else {
+                    Some(value.to_string())
+                }
+            };
+            let mut frames: Vec<(f64, String, Option<Easing>)> = self
+                .keyframes
+                .iter()
+                .filter_map(|keyframe| {
+                    Some((
+                        keyframe.offset,
+                        convert(keyframe.value(name)?)?,
+                        keyframe.easing,
+                    ))
+                })
+                .collect();
+            let Some(first) = frames.first() else {
+                continue;
+            };
+            // Missing ends animate from and to the element's own value.
+            let base = if transform {
+                Some(target.attribute("transform").unwrap_or("").to_string())
+            } else {
+                target.base(name).map(String::from)
+            };
+            if first.0 > 0.0 {
+                let value = base.clone().unwrap_or_else(|| first.1.clone());
+                frames.insert(0, (0.0, value, None));
+            }
+            if frames.last().is_some_and(|last| last.0 < 1.0) {
+                let value = base.unwrap_or_else(|| frames[frames.len() - 1].1.clone());
+                frames.push((1.0, value, None));
+            }
+
+            let next = frames
+                .iter()
+                .position(|frame| frame.0 > progress)
+                .unwrap_or(frames.len() - 1)
+                .max(1);
+            let (start, end) = (&frames[next - 1], &frames[next]);
+            let local = if end.0 > start.0 {
+                ((progress - start.0) / (end.0 - start.0)).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            let eased = start.2.unwrap_or(self.spec.easing).apply(local);
+            let value = if transform {
+                interpolate_transforms(&start.1, &end.1, eased)
+            } else {
+                interpolate(&start.1, &end.1, eased)
+            };
+            let mut value = value.unwrap_or_else(|| {
+                if eased < 0.5 {
+                    start.1.clone()
+                } else {
+                    end.1.clone()
+                }
+            });
+            if transform {
+                if let Some((x, y)) = self.origin.filter(|_| !value.is_empty()) {
+                    value = format!(
+                        "translate({} {}) {} translate({} {})",
+                        number(x),
+                        number(y),
+                        value,
+                        number(-x),
+                        number(-y)
+                    );
+                }
+            }
+            sampled.push((name.to_string(), value));
+        }
+        sampled
+    }
+}
+
+/// `@keyframes` rules in a style sheet, by name.
+fn keyframes(css: &str) -> Vec<(String, Vec<Keyframe>)> {
+    let css = strip_comments(css);
+    let mut found = Vec::new();
+    let mut rest = css.as_str();
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let keyword_end = rest
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(rest.len());
+        let keyword = &rest[..keyword_end];
+        if !keyword.ends_with("keyframes") {
+            continue;
+        }
+        let Some(open) = rest.find('{') else {
+            break;
+        };
+        let name = rest[keyword_end..open]
+            .trim()
+            .trim_matches(['"', '\''])
+            .to_string();
+        let Some(close) = matching_brace(rest, open) else {
+            break;
+        };
+        let body = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+
+        let mut frames: Vec<Keyframe> = Vec::new();
+        let mut block = body;
+        while let Some(open) = block.find('{') {
+            let selectors = &block[..open];
+            let Some(close) = block[open..].find('}').map(|close| open + close) else {
+                break;
+            };
+            let declarations: Vec<(String, String)> =
+                simplecss::DeclarationTokenizer::from(&block[open + 1..close])
+                    .map(|declaration| {
+                        (declaration.name.to_string(), declaration.value.to_string())
+                    })
+                    .collect();
+            let easing = declarations
+                .iter()
+                .rev()
+                .find(|(name, _)| name.ends_with("animation-timing-function"))
+                .and_then(|(_, value)| Easing::parse(value));
+            for selector in selectors.split(',') {
+                let offset = match selector.trim() {
+                    "from" => Some(0.0),
+                    "to" => Some(1.0),
+                    percentage => percentage
+                        .strip_suffix('%')
+                        .and_then(|value| value.trim().parse::<f64>().ok())
+                        .map(|value| value / 100.0),
+                };
+                let Some(offset) = offset.filter(|offset| (0.0..=1.0).contains(offset)) else {
+                    continue;
+                };
+                match frames.iter_mut().find(|frame| frame.offset == offset) {
+                    Some(frame) => {
+                        frame.declarations.extend(declarations.iter().cloned());
+                        frame.easing = easing.or(frame.easing);
+                    }
+                    None => frames.push(Keyframe {
+                        offset,
+                        declarations: declarations.clone(),
+                        easing,
+                    }),
+                }
+            }
+            block = &block[close + 1..];
+        }
+        frames.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        found.push((name, frames));
+    }
+    found
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Position of the `}` closing the `{` at `open`.
+fn matching_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The animations named by `animation` and `animation-*` declarations, later ones winning.
+fn animation_specs(declarations: &[(String, String)]) -> Vec<AnimationSpec> {
+    let mut names: Vec<String> = Vec::new();
+    let mut durations: Vec<String> = Vec::new();
+    let mut delays: Vec<String> = Vec::new();
+    let mut easings: Vec<String> = Vec::new();
+    let mut iterations: Vec<String> = Vec::new();
+    let mut directions: Vec<String> = Vec::new();
+    let mut fills: Vec<String> = Vec::new();
+    for (name, value) in declarations {
+        let name = name.trim_start_matches("-webkit-");
+        let items: Vec<String> = split_top_level(value, ',')
+            .iter()
+            .map(|item| item.trim().to_string())
+            .collect();
+        match name {
+            "animation" => {
+                let lists = [
+                    &mut names,
+                    &mut durations,
+                    &mut delays,
+                    &mut easings,
+                    &mut iterations,
+                    &mut directions,
+                    &mut fills,
+                ];
+                for list in lists {
+                    list.clear();
+                }
+                for item in &items {
+                    let mut times = Vec::new();
+                    let (mut name, mut easing, mut count, mut direction, mut fill) =
+                        (None, None, None, None, None);
+                    for token in split_top_level(item, ' ') {
+                        if css_time(token).is_some() {
+                            times.push(token.to_string());
+                        } else if Easing::parse(token).is_some() && easing.is_none() {
+                            easing = Some(token.to_string());
+                        } else if token == "infinite" || token.parse::<f64>().is_ok() {
+                            count = Some(token.to_string());
+                        } else if ["normal", "reverse", "alternate", "alternate-reverse"]
+                            .contains(&token)
+                        {
+                            direction = Some(token.to_string());
+                        } else if ["none", "forwards", "backwards", "both"].contains(&token)
+                            && fill.is_none()
+                        {
+                            fill = Some(token.to_string());
+                        } else if !["running", "paused"].contains(&token) {
+                            name = Some(token.to_string());
+                        }
+                    }
+                    names.push(name.unwrap_or_else(|| String::from("none")));
+                    durations.push(times.first().cloned().unwrap_or_else(|| String::from("0s")));
+                    delays.push(times.get(1).cloned().unwrap_or_else(|| String::from("0s")));
+                    easings.push(easing.unwrap_or_else(|| String::from("ease")));
+                    iterations.push(count.unwrap_or_else(|| String::from("1")));
+                    directions.push(direction.unwrap_or_else(|| String::from("normal")));
+                    fills.push(fill.unwrap_or_else(|| String::from("none")));
+                }
+            }
+            "animation-name" => names = items,
+            "animation-duration" => durations = items,
+            "animation-delay" => delays = items,
+            "animation-timing-function" => easings = items,
+            "animation-iteration-count" => iterations = items,
+            "animation-direction" => directions = items,
+            "animation-fill-mode" => fills = items,
+            _ => {}
+        }
+    }
+
+    let pick = |list: &[String], index: usize| -> Option<String> {
+        (!list.is_empty()).then(|| list[index % list.len()].clone())
+    };
+    names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.as_str() != "none")
+        .map(|(index, name)| {
+            let fill = pick(&fills, index).unwrap_or_default();
+            AnimationSpec {
+                name: name.trim_matches(['"', '\'']).to_string(),
+                duration: pick(&durations, index)
+                    .and_then(|time| css_time(&time))
+                    .unwrap_or(0.0),
+                delay: pick(&delays, index)
+                    .and_then(|time| css_time(&time))
+                    .unwrap_or(0.0),
+                easing: pick(&easings, index)
+                    .and_then(|easing| Easing::parse(&easing))
+                    .unwrap_or(Easing::EASE),
+                iterations: match pick(&iterations, index).as_deref() {
+                    Some("infinite") => f64::INFINITY,
+                    Some(count) => count.parse().unwrap_or(1.0),
+                    None => 1.0,
+                },
+                direction: match pick(&directions, index).as_deref() {
+                    Some("reverse") => Direction::Reverse,
+                    Some("alternate") => Direction::Alternate,
+                    Some("alternate-reverse") => Direction::AlternateReverse,
+                    _ => Direction::Normal,
+                },
+                fill_backwards: fill == "backwards" || fill == "both",
+                fill_forwards: fill == "forwards" || fill == "both",
+            }
+        })
+        .collect()
+}
+
+/// Pieces of `text` split at `separator` outside parentheses; spaces also split at any
+/// whitespace.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 && (c == separator || (separator == ' ' && c.is_whitespace())) => {
+                pieces.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&text[start..]);
+    pieces.retain(|piece| !piece.trim().is_empty());
+    pieces
+}
+
+/// Seconds in a CSS time like `2s` or `250ms`.
+fn css_time(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (number, unit) = match text.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => (text.strip_suffix('s')?, 1.0),
+    };
+    number.parse::<f64>().ok().map(|value| value * unit)
+}
+
+/// A timing function, from `keySplines` or CSS.
+#[derive(Clone, Copy)]
+enum Easing {
+    CubicBezier(f64, f64, f64, f64),
+    /// Jumps between `steps` levels, at the start of each step when `start` is set.
+    Steps {
+        steps: u32,
+        start: bool,
+    },
+}
+
+impl Easing {
+    const EASE: Easing = Easing::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+    fn parse(text: &str) -> Option<Easing> {
+        let text = text.trim();
+        let easing = match text {
+            "linear" => Easing::CubicBezier(0.0, 0.0, 1.0, 1.0),
+            "ease" => Easing::EASE,
+            "ease-in" => Easing::CubicBezier(0.42, 0.0, 1.0, 1.0),
+            "ease-out" => Easing::CubicBezier(0.0, 0.0, 0.58, 1.0),
+            "ease-in-out" => Easing::CubicBezier(0.42, 0.0, 0.58, 1.0),
+            "step-start" => Easing::Steps {
+                steps: 1,
+                start: true,
+            },
+            "step-end" => Easing::Steps {
+                steps: 1,
+                start: false,
+            },
+            _ => {
+                let (function, arguments) = text.strip_suffix(')')?.split_once('(')?;
+                let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+                match (function.trim(), &arguments[..]) {
+                    ("cubic-bezier", [x1, y1, x2, y2]) => Easing::CubicBezier(
+                        x1.parse().ok()?,
+                        y1.parse().ok()?,
+                        x2.parse().ok()?,
+                        y2.parse().ok()?,
+                    ),
+                    ("steps", [steps, position @ ..]) => Easing::Steps {
+                        steps: steps.parse().ok().filter(|&steps| steps > 0)?,
+                        start: matches!(position.first(), Some(&"start" | &"jump-start")),
+                    },
+                    _ => return None,
+                }
+            }
+        };
+        Some(easing)
+    }
+
+    fn apply(self, progress: f64) -> f64 {
+        match self {
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let bezier = |t: f64, p1: f64, p2: f64| {
+                    let u = 1.0 - t;
+                    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+                };
+                // Find where the curve's x is `progress` by bisection; x only ever grows.
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..40 {
+                    let middle = (low + high) / 2.0;
+                    if bezier(middle, x1, x2) < progress {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                bezier((low + high) / 2.0, y1, y2)
+            }
+            Easing::Steps { steps, start } => {
+                let steps = steps as f64;
+                let step = (progress * steps).floor() + if start { 1.0 } else { 0.0 };
+                (step / steps).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// A CSS `transform` value as an SVG `transform` attribute, dropping units.
+fn css_transform(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value == "none" {
+        return Some(String::new());
+    }
+    let mut functions = Vec::new();
+    for function in split_top_level(value, ' ') {
+        let (name, arguments) = function.strip_suffix(')')?.split_once('(')?;
+        let numbers: Vec<f64> = arguments
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .map(css_number)
+            .collect::<Option<_>>()?;
+        let joined = |numbers: &[f64]| -> String {
+            numbers
+                .iter()
+                .map(|&n| number(n))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let converted = match (name.trim(), &numbers[..]) {
+            ("translate" | "translate3d", [x, rest @ ..]) => {
+                format!(
+                    "translate({})",
+                    joined(&[*x, rest.first().copied().unwrap_or(0.0)])
+                )
+            }
+            ("translateX", [x]) => format!("translate({})", joined(&[*x, 0.0])),
+            ("translateY", [y]) => format!("translate({})", joined(&[0.0, *y])),
+            ("scale" | "scale3d", [x, rest @ ..]) => {
+                format!(
+                    "scale({})",
+                    joined(&[*x, rest.first().copied().unwrap_or(*x)])
+                )
+            }
+            ("scaleX", [x]) => format!("scale({})", joined(&[*x, 1.0])),
+            ("scaleY", [y]) => format!("scale({})", joined(&[1.0, *y])),
+            ("rotate" | "rotateZ", [angle]) => format!("rotate({})", number(*angle)),
+            ("skew", [x, rest @ ..]) => format!(
+                "skewX({}) skewY({})",
+                number(*x),
+                number(rest.first().copied().unwrap_or(0.0))
+            ),
+            ("skewX", [x]) => format!("skewX({})", number(*x)),
+            ("skewY", [y]) => format!("skewY({})", number(*y)),
+            ("matrix", [_, _, _, _, _, _]) => format!("matrix({})", joined(&numbers)),
+            _ => return None,
+        };
+        functions.push(converted);
+    }
+    Some(functions.join(" "))
+}
+
+/// A CSS length or angle in user units or degrees.
+fn css_number(text: &str) -> Option<f64> {
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(text.len());
+    let value: f64 = text[..split].parse().ok()?;
+    let scale = match &text[split..] {
+        "" | "px" | "deg" | "%" => 1.0,
+        "rad" => 180.0 / std::f64::consts::PI,
+        "grad" => 0.9,
+        "turn" => 360.0,
+        _ => return None,
+    };
+    Some(value * scale)
+}
+
+/// Transforms interpolated function by function; `none` on one side acts as the identity
+/// version of the other's functions.
+fn interpolate_transforms(from: &str, to: &str, progress: f64) -> Option<String> {
+    let from = if from.is_empty() {
+        identity(to)
+    } else {
+        from.to_string()
+    };
+    let to = if to.is_empty() {
+        identity(&from)
+    } else {
+        to.to_string()
+    };
+    interpolate(&from, &to, progress)
+}
+
+fn identity(transform: &str) -> String {
+    split_top_level(transform, ' ')
+        .iter()
+        .filter_map(|function| {
+            let name = function.split('(').next()?;
+            Some(match name {
+                "translate" => String::from("translate(0 0)"),
+                "scale" => String::from("scale(1 1)"),
+                "matrix" => String::from("matrix(1 0 0 1 0 0)"),
+                _ => format!("{}(0)", name),
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `transform-origin` in user units, from keywords, percentages of the reference box or
+/// lengths; `None` for the SVG default of `0 0`.
+fn transform_origin(
+    node: roxmltree::Node,
+    declarations: &[(String, String)],
+    view_box: (f64, f64, f64, f64),
+) -> Option<(f64, f64)> {
+    let find = |name: &str| {
+        declarations
+            .iter()
+            .rev()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.trim())
+    };
+    let origin = find("transform-origin")?;
+    let (x, y, width, height) = match find("transform-box") {
+        Some("fill-box") => fill_box(node).unwrap_or(view_box),
+        _ => view_box,
+    };
+    let mut tokens: Vec<&str> = origin.split_whitespace().take(2).collect();
+    if tokens.len() == 1 {
+        tokens.push("center");
+    }
+    if ["top", "bottom"].contains(&tokens[0]) || ["left", "right"].contains(&tokens[1]) {
+        tokens.swap(0, 1);
+    }
+    let resolve = |token: &str, start: f64, size: f64| -> Option<f64> {
+        let fraction = match token {
+            "left" | "top" => Some(0.0),
+            "center" => Some(0.5),
+            "right" | "bottom" => Some(1.0),
+            _ => token
+                .strip_suffix('%')
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(|value| value / 100.0),
+        };
+        match fraction {
+            Some(fraction) => Some(start + size * fraction),
+            None => css_number(token),
+        }
+    };
+    let origin = (
+        resolve(tokens[0], x, width)?,
+        resolve(tokens[1], y, height)?,
+    );
+    (origin != (0.0, 0.0)).then_some(origin)
+}
+
+/// The root's view box as `(x, y, width, height)`.
+fn view_box(root: roxmltree::Node) -> (f64, f64, f64, f64) {
+    if let Some(numbers) = root.attribute("viewBox").map(numbers_in) {
+        if let [x, y, width, height] = numbers[..] {
+            return (x, y, width, height);
+        }
+    }
+    let length = |name: &str| root.attribute(name).and_then(css_number).unwrap_or(0.0);
+    (0.0, 0.0, length("width"), length("height"))
+}
+
+/// The bounding box of an element's geometry, without stroke, in its own user units.
+fn fill_box(node: roxmltree::Node) -> Option<(f64, f64, f64, f64)> {
+    let length = |name: &str| node.attribute(name).and_then(css_number).unwrap_or(0.0);
+    let points: Vec<(f64, f64)> = match node.tag_name().name() {
+        "rect" | "image" | "use" => {
+            let (x, y) = (length("x"), length("y"));
+            vec![(x, y), (x + length("width"), y + length("height"))]
+        }
+        "circle" => {
+            let (cx, cy, r) = (length("cx"), length("cy"), length("r"));
+            vec![(cx - r, cy - r), (cx + r, cy + r)]
+        }
+        "ellipse" => {
+            let (cx, cy, rx, ry) = (length("cx"), length("cy"), length("rx"), length("ry"));
+            vec![(cx - rx, cy - ry), (cx + rx, cy + ry)]
+        }
+        "line" => vec![(length("x1"), length("y1")), (length("x2"), length("y2"))],
+        "polyline" | "polygon" => numbers_in(node.attribute("points")?)
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect(),
+        "path" => path_points(node.attribute("d")?)
+            .into_iter()
+            .map(|(point, _)| point)
+            .collect(),
+        _ => node
+            .children()
+            .filter(|child| child.is_element())
+            .filter_map(|child| {
+                let (x, y, width, height) = fill_box(child)?;
+                let transform = child
+                    .attribute("transform")
+                    .and_then(|transform| svgtypes::Transform::from_str(transform).ok())
+                    .unwrap_or_default();
+                let corners = [
+                    (x, y),
+                    (x + width, y),
+                    (x, y + height),
+                    (x + width, y + height),
+                ];
+                Some(corners.map(|(x, y)| {
+                    (
+                        transform.a * x + transform.c * y + transform.e,
+                        transform.b * x + transform.d * y + transform.f,
+                    )
+                }))
+            })
+            .flatten()
+            .collect(),
+    };
+    let (first, rest) = points.split_first()?;
+    let (mut min, mut max) = (*first, *first);
+    for &(x, y) in rest {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    Some((min.0, min.1, max.0 - min.0, max.1 - min.1))
+}
+
+fn numbers_in(text: &str) -> Vec<f64> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+/// A value split into the numbers in it and the text around them, so values of the same
+/// shape, like `0 0 10 10` and `5 5 20 20` or two paths with the same commands, can be
+/// worked on number by number.
+struct Shape<'a> {
+    text: Vec<&'a str>,
+    numbers: Vec<f64>,
+}
+
+impl<'a> Shape<'a> {
+    fn parse(value: &'a str) -> Shape<'a> {
+        let bytes = value.as_bytes();
+        let digit = |at: usize| bytes.get(at).is_some_and(u8::is_ascii_digit);
+        let mut text = Vec::new();
+        let mut numbers = Vec::new();
+        let (mut copied, mut i) = (0, 0);
+        while i < bytes.len() {
+            let sign = usize::from(matches!(bytes[i], b'-' | b'+'));
+            let starts =
+                digit(i + sign) || (bytes.get(i + sign) == Some(&b'.') && digit(i + sign + 1));
+            if !starts || !(i == copied && !numbers.is_empty() || number_boundary(bytes, i)) {
+                i += 1;
+                continue;
+            }
+            let mut end = i + sign;
+            while digit(end) {
+                end += 1;
+            }
+            if bytes.get(end) == Some(&b'.') && digit(end + 1) {
+                end += 1;
+                while digit(end) {
+                    end += 1;
+                }
+            }
+            if matches!(bytes.get(end), Some(b'e' | b'E')) {
+                let exponent =
+                    end + 1 + usize::from(matches!(bytes.get(end + 1), Some(b'-' | b'+')));
+                if digit(exponent) {
+                    end = exponent;
+                    while digit(end) {
+                        end += 1;
+                    }
+                }
+            }
+            text.push(&value[copied..i]);
+            numbers.push(value[i..end].parse().unwrap_or(0.0));
+            copied = end;
+            i = end;
+        }
+        text.push(&value[copied..]);
+        Shape { text, numbers }
+    }
+
+    fn matches(&self, other: &Shape) -> bool {
+        let normalize = |text: &str| {
+            text.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|piece| !piece.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        self.numbers.len() == other.numbers.len()
+            && self
+                .text
+                .iter()
+                .zip(&other.text)
+                .all(|(a, b)| normalize(a) == normalize(b))
+    }
+
+    fn with_numbers(&self, numbers: impl Iterator<Item = f64>) -> String {
+        let mut value = String::from(self.text[0]);
+        for (number_value, text) in numbers.zip(&self.text[1..]) {
+            value.push_str(&number(number_value));
+            value.push_str(text);
+        }
+        value
+    }
+}
+
+fn color(value: &str) -> Option<svgtypes::Color> {
+    svgtypes::Color::from_str(value.trim()).ok()
+}
+
+fn color_markup(channels: [f64; 4]) -> String {
+    let [red, green, blue, alpha] = channels.map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+    if alpha == 255 {
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    } else {
+        format!(
+            "rgba({},{},{},{})",
+            red,
+            green,
+            blue,
+            number(alpha as f64 / 255.0)
+        )
+    }
+}
+
+fn channels(color: svgtypes::Color) -> [f64; 4] {
+    [color.red, color.green, color.blue, color.alpha].map(f64::from)
+}
+
+/// The value `progress` of the way from `from` to `to`, if they can be blended: colors,
+/// or numbers in text of the same shape.
+fn interpolate(from: &str, to: &str, progress: f64) -> Option<String> {
+    if from == to {
+        return Some(from.to_string());
+    }
+    if let (Some(from), Some(to)) = (color(from), color(to)) {
+        let (from, to) = (channels(from), channels(to));
+        return Some(color_markup(std::array::from_fn(|i| {
+            from[i] + (to[i] - from[i]) * progress
+        })));
+    }
+    let (from_shape, to_shape) = (Shape::parse(from), Shape::parse(to));
+    if from_shape.numbers.is_empty() || !from_shape.matches(&to_shape) {
+        return None;
+    }
+    Some(
+        from_shape.with_numbers(
+            from_shape
+                .numbers
+                .iter()
+                .zip(&to_shape.numbers)
+                .map(|(a, b)| a + (b - a) * progress),
+        ),
+    )
+}
+
+/// Whether a number starting at `at` stands on its own rather than ending a name, like the
+/// `1` of `url(#a1)`. A single letter before it is a path command, like `M10`.
+fn number_boundary(bytes: &[u8], at: usize) -> bool {
+    let letters = bytes[..at]
+        .iter()
+        .rev()
+        .take_while(|byte| byte.is_ascii_alphabetic() || **byte == b'_')
+        .count();
+    let before = at.checked_sub(letters + 1).map(|index| bytes[index]);
+    match letters {
+        0 => !before.is_some_and(|byte| byte.is_ascii_digit()),
+        1 => !before.is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'#'),
+        _ => false,
+    }
+}
+
+/// `value` added to `base`, number by number or channel by channel; just `value` when they
+/// can't be added.
+fn add(base: &str, value: &str) -> String {
+    if let (Some(base), Some(value)) = (color(base), color(value)) {
+        let (base, value) = (channels(base), channels(value));
+        return color_markup(std::array::from_fn(|i| {
+            if i == 3 {
+                base[i]
+            } else {
+                base[i] + value[i]
+            }
+        }));
+    }
+    let (base_shape, value_shape) = (Shape::parse(base), Shape::parse(value));
+    if !base_shape.matches(&value_shape) {
+        return value.to_string();
+    }
+    value_shape.with_numbers(
+        base_shape
+            .numbers
+            .iter()
+            .zip(&value_shape.numbers)
+            .map(|(a, b)| a + b),
+    )
+}
+
+/// `value` with every number, or color channel, multiplied by `factor`.
+fn scale(value: &str, factor: f64) -> String {
+    if let Some(color) = color(value) {
+        let mut channels = channels(color).map(|channel| channel * factor);
+        channels[3] = color.alpha as f64;
+        return color_markup(channels);
+    }
+    let shape = Shape::parse(value);
+    shape.with_numbers(shape.numbers.iter().map(|number| number * factor))
+}
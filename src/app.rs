@@ -0,0 +1,2966 @@
+use crate::cli;
+use crate::editor::SourceEditor;
+use crate::logging;
+use crate::platform;
+use crate::preset::{self, Preset};
+use crate::trace_tab::TraceTab;
+use crate::watch::FileWatcher;
+use crate::worker::{Worker, WorkerEvent};
+use eframe::egui;
+use resvg::tiny_skia::Pixmap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use svg_to_png::atlas::{AtlasOptions, SpriteData};
+use svg_to_png::canvas::PaddingUnit;
+use svg_to_png::convert::{
+    self, batch_output_template, check_pixmap_size, fetch_svg, find_svgs, format_bytes,
+    is_svg_path, is_url, load_tree, parse_tree, pixmap_bytes, read_svg, render_tree, BatchJob,
+    ConversionOptions, LengthUnit, ParseError, StageTimings, CANCELLED, LARGE_PIXMAP_BYTES,
+    SVG_EXTENSIONS,
+};
+use svg_to_png::effects::{Corner, LumaWeights};
+use svg_to_png::encode::{self, OutputFormat, PngCompression, PngFilter};
+use svg_to_png::icc::IccProfile;
+use svg_to_png::icon::ICO_SIZES;
+use svg_to_png::info::{self, SvgInfo};
+use svg_to_png::large_output;
+use svg_to_png::lint::{self, Issue, Severity};
+use svg_to_png::package::Package;
+use svg_to_png::parsing::{ImageRendering, ShapeRendering, TextRendering};
+use svg_to_png::snippet::Snippet;
+use svg_to_png::template;
+use svg_to_png::trace::is_raster_path;
+use svg_to_png::Converter;
+use usvg::Tree;
+
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+enum Tab {
+    #[default]
+    Render,
+    /// Tracing raster images into SVGs.
+    Trace,
+}
+
+enum ItemStatus {
+    Pending,
+    Converting,
+    Done,
+    Failed(String),
+}
+
+impl ItemStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            ItemStatus::Pending => "pending",
+            ItemStatus::Converting => "rendering…",
+            ItemStatus::Done => "done",
+            ItemStatus::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Settings changed for one queued file; everything else follows the shared options.
+#[derive(Default, PartialEq)]
+struct ItemOverrides {
+    scale: Option<f32>,
+    background: Option<[u8; 3]>,
+    /// Output file name, which may contain tokens; empty keeps the usual name.
+    output_name: String,
+}
+
+impl ItemOverrides {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&self, options: &ConversionOptions) -> ConversionOptions {
+        let mut options = options.clone();
+        if let Some(scale) = self.scale {
+            // A scale only counts without a fixed size.
+            options.scale = scale;
+            options.width = None;
+            options.height = None;
+            options.print_width = None;
+            options.print_height = None;
+        }
+        if let Some(background) = self.background {
+            options.background = Some(background);
+        }
+        options
+    }
+}
+
+struct QueueItem {
+    path: String,
+    /// Subfolder mirrored under the output folder, for files added from a folder.
+    relative_dir: PathBuf,
+    /// The SVG's own size, if it could be read, to show the output size.
+    svg_size: Option<(f32, f32)>,
+    overrides: ItemOverrides,
+    selected: bool,
+    status: ItemStatus,
+}
+
+enum ConversionKind {
+    /// The single input file, opened in the default viewer when done.
+    Single { open: bool },
+    /// Every item in the queue, in order.
+    Queue,
+}
+
+struct Conversion {
+    worker: Worker,
+    kind: ConversionKind,
+    /// Added to the history once the conversion finishes, unless it was cancelled.
+    record: Option<HistoryEntry>,
+    inputs: Vec<String>,
+    /// Indices of the items currently being converted, oldest first.
+    active: Vec<usize>,
+    finished: usize,
+    /// Stage timings summed over the finished items.
+    timings: StageTimings,
+    started: Instant,
+}
+
+/// What the input was parsed from, to tell when it has to be parsed again: the file, the
+/// markup or an option used while parsing changed.
+#[derive(Clone)]
+struct InputKey {
+    input_path: String,
+    modified: Option<SystemTime>,
+    svg_source: Option<String>,
+    options: ConversionOptions,
+}
+
+impl InputKey {
+    fn of(app: &SvgConverterApp, modified: Option<SystemTime>) -> Self {
+        Self {
+            input_path: app.input_path.clone(),
+            modified,
+            svg_source: app.svg_source.clone(),
+            options: app.options.clone(),
+        }
+    }
+
+    fn is_current(&self, app: &SvgConverterApp, modified: Option<SystemTime>) -> bool {
+        self.input_path == app.input_path
+            && self.modified == modified
+            && self.svg_source == app.svg_source
+            && self.options.parse == app.options.parse
+            && self.options.fonts == app.options.fonts
+            && self.options.colors == app.options.colors
+            && self.options.canvas == app.options.canvas
+    }
+
+    /// Whether both keys parse to the same tree.
+    fn same_input(&self, other: &InputKey) -> bool {
+        self.input_path == other.input_path
+            && self.modified == other.modified
+            && self.svg_source == other.svg_source
+            && self.options.parse == other.options.parse
+            && self.options.fonts == other.options.fonts
+            && self.options.colors == other.options.colors
+            && self.options.canvas == other.options.canvas
+    }
+}
+
+/// The input as last parsed, kept so frames only parse it again once it changes.
+struct ParsedInput {
+    key: InputKey,
+    tree: Result<Rc<Tree>, String>,
+}
+
+/// The thread that parses, inspects and renders the input for the preview, so a large file
+/// doesn't stall the UI. It keeps the last tree it parsed, so options that don't change
+/// parsing only render it again, and lint and info only run when the input changes.
+struct PreviewThread {
+    requests: Sender<PreviewRequest>,
+    events: Receiver<PreviewEvent>,
+}
+
+struct PreviewRequest {
+    /// The input, and the options to render it with.
+    key: InputKey,
+    /// Parse again even if the input looks unchanged.
+    reparse: bool,
+}
+
+enum PreviewEvent {
+    /// The input was parsed again; what it contains is known even if safe mode refused it.
+    Parsed {
+        size: Option<(f32, f32)>,
+        svg_info: Option<Box<SvgInfo>>,
+        issues: Vec<Issue>,
+    },
+    Rendered(Option<Pixmap>),
+}
+
+impl PreviewThread {
+    fn spawn(ctx: &egui::Context) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<PreviewRequest>();
+        let (event_sender, events) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let mut parsed: Option<(InputKey, Result<Tree, String>)> = None;
+            while let Ok(mut request) = request_receiver.recv() {
+                // Only the newest request is answered, parsing again if any asked to.
+                while let Ok(newer) = request_receiver.try_recv() {
+                    let reparse = request.reparse || newer.reparse;
+                    request = PreviewRequest { reparse, ..newer };
+                }
+                let PreviewRequest { key, reparse } = request;
+                let stale = reparse
+                    || !parsed
+                        .as_ref()
+                        .is_some_and(|(parsed_key, _)| parsed_key.same_input(&key));
+                if stale {
+                    let (tree, svg_info, issues) = parse_input(&key);
+                    let size = tree
+                        .as_ref()
+                        .ok()
+                        .map(|rtree| (rtree.size.width() as f32, rtree.size.height() as f32));
+                    let _ = event_sender.send(PreviewEvent::Parsed {
+                        size,
+                        svg_info: svg_info.map(Box::new),
+                        issues,
+                    });
+                    ctx.request_repaint();
+                    parsed = Some((key.clone(), tree));
+                }
+
+                let pixmap = match &parsed {
+                    Some((_, Ok(rtree))) => render_preview(rtree, &key.options).ok(),
+                    _ => None,
+                };
+                if event_sender.send(PreviewEvent::Rendered(pixmap)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { requests, events }
+    }
+}
+
+/// Measures files added to the queue on a thread of its own, so adding a folder of large
+/// files doesn't stall the UI.
+struct SizeProbe {
+    requests: Sender<(String, ConversionOptions)>,
+    sizes: Receiver<(String, (f32, f32))>,
+}
+
+impl SizeProbe {
+    fn spawn(ctx: &egui::Context) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<(String, ConversionOptions)>();
+        let (size_sender, sizes) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for (path, options) in request_receiver {
+                let Ok(info) =
+                    read_svg(&path).and_then(|svg_data| info::inspect(&svg_data, &path, &options))
+                else {
+                    continue;
+                };
+                let size = (info.width as f32, info.height as f32);
+                if size_sender.send((path, size)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { requests, sizes }
+    }
+}
+
+/// A past conversion, kept so it can be run again from the history panel or Recent menu.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct HistoryEntry {
+    /// The input file, or every queued file for a queue run.
+    inputs: Vec<String>,
+    /// The output path, or the output folder for a queue run.
+    output: String,
+    queue: bool,
+    options: ConversionOptions,
+    /// Seconds since the Unix epoch when the conversion started.
+    started_at: u64,
+    duration: Duration,
+    /// How many of the inputs failed to convert.
+    failed: usize,
+}
+
+impl HistoryEntry {
+    fn label(&self) -> String {
+        match self.inputs.as_slice() {
+            [input] if !self.queue => input.clone(),
+            inputs => format!("{} files", inputs.len()),
+        }
+    }
+}
+
+pub const APP_NAME: &str = "SVG to PNG Converter";
+
+/// Stands in for the input file name when converting markup pasted from the clipboard.
+const PASTED_NAME: &str = "pasted.svg";
+
+/// How long typing in the input path has to pause before the input is loaded.
+const PATH_DEBOUNCE: Duration = Duration::from_millis(400);
+
+const MAX_RECENT_FILES: usize = 10;
+
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+pub const MAX_PREVIEW_SIDE: u32 = 1024;
+
+const DENSITY_VARIANTS: [f32; 5] = [1.0, 1.5, 2.0, 3.0, 4.0];
+
+/// Only the output settings are remembered between launches; the rest starts fresh.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SvgConverterApp {
+    tab: Tab,
+    trace_tab: TraceTab,
+    #[serde(skip)]
+    input_path: String,
+    /// Edited or pasted SVG markup, used instead of reading `input_path` when set.
+    #[serde(skip)]
+    svg_source: Option<String>,
+    #[serde(skip)]
+    editor: Option<SourceEditor>,
+    /// URL whose download is in flight or stored in `svg_source`.
+    #[serde(skip)]
+    downloaded_url: String,
+    #[serde(skip)]
+    download: Option<Receiver<Result<String, String>>>,
+    #[serde(skip)]
+    queue: Vec<QueueItem>,
+    /// Index of the queue item whose overrides are being edited.
+    #[serde(skip)]
+    editing_item: Option<usize>,
+    /// Last item clicked without Shift, where a Shift-click range starts.
+    #[serde(skip)]
+    selection_anchor: Option<usize>,
+    /// Index of the queue item being dragged to a new position.
+    #[serde(skip)]
+    dragged_item: Option<usize>,
+    recursive: bool,
+    /// Worker threads for batch conversion; 0 uses every core.
+    jobs: usize,
+    output_path: String,
+    output_dir: String,
+    /// Pack the queue into one sprite sheet at `sprite_sheet_path` instead of converting
+    /// each file.
+    sprite_sheet: bool,
+    sprite_sheet_path: String,
+    atlas: AtlasOptions,
+    options: ConversionOptions,
+    /// Past conversions, newest first.
+    history: Vec<HistoryEntry>,
+    #[serde(skip)]
+    show_history: bool,
+    /// Loaded from `presets.toml` rather than the app storage.
+    #[serde(skip)]
+    presets: Vec<Preset>,
+    #[serde(skip)]
+    preset_name: String,
+    #[serde(skip)]
+    status_message: String,
+    /// The first file written by the last single conversion, to open or reveal.
+    #[serde(skip)]
+    last_output: Option<String>,
+    #[serde(skip)]
+    parsed: Option<ParsedInput>,
+    #[serde(skip)]
+    preview_thread: Option<PreviewThread>,
+    #[serde(skip)]
+    size_probe: Option<SizeProbe>,
+    /// When the input path was last typed in.
+    #[serde(skip)]
+    path_edited: Option<Instant>,
+    /// The input's size as last parsed by the preview thread, kept until the new one is
+    /// known.
+    #[serde(skip)]
+    input_size: Option<(f32, f32)>,
+    #[serde(skip)]
+    original_dimensions: Option<(u32, u32)>,
+    #[serde(skip)]
+    scaled_dimensions: Option<(u32, u32)>,
+    #[serde(skip)]
+    preview: Option<egui::TextureHandle>,
+    /// What the preview thread was last asked to show; `None` has it parse the input again.
+    #[serde(skip)]
+    preview_key: Option<InputKey>,
+    /// Problems found in the input, refreshed along with the preview.
+    #[serde(skip)]
+    issues: Vec<Issue>,
+    /// What the input contains, refreshed along with the preview.
+    #[serde(skip)]
+    svg_info: Option<SvgInfo>,
+    #[serde(skip)]
+    watch: bool,
+    #[serde(skip)]
+    watcher: Option<FileWatcher>,
+    #[serde(skip)]
+    conversion: Option<Conversion>,
+    /// Kept alive because on Linux the copied image is only served while it exists.
+    #[serde(skip)]
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl Default for SvgConverterApp {
+    fn default() -> Self {
+        Self {
+            tab: Tab::Render,
+            trace_tab: TraceTab::default(),
+            input_path: String::new(),
+            svg_source: None,
+            editor: None,
+            downloaded_url: String::new(),
+            download: None,
+            queue: Vec::new(),
+            editing_item: None,
+            selection_anchor: None,
+            dragged_item: None,
+            recursive: true,
+            jobs: 0,
+            output_path: String::from("output.png"),
+            output_dir: String::new(),
+            sprite_sheet: false,
+            sprite_sheet_path: String::from("sprites.png"),
+            atlas: AtlasOptions::default(),
+            options: ConversionOptions::default(),
+            history: Vec::new(),
+            show_history: false,
+            presets: Vec::new(),
+            preset_name: String::new(),
+            status_message: String::new(),
+            last_output: None,
+            parsed: None,
+            preview_thread: None,
+            size_probe: None,
+            path_edited: None,
+            input_size: None,
+            original_dimensions: None,
+            scaled_dimensions: None,
+            preview: None,
+            preview_key: None,
+            issues: Vec::new(),
+            svg_info: None,
+            watch: false,
+            watcher: None,
+            conversion: None,
+            clipboard: None,
+        }
+    }
+}
+
+impl SvgConverterApp {
+    /// Restores the settings saved by the previous launch, or on the first launch starts
+    /// from the config file shared with the CLI.
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let saved = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY));
+        let mut app: Self = saved.unwrap_or_else(|| {
+            let mut app = Self::default();
+            match cli::config_preset() {
+                Ok(Some(preset)) => {
+                    app.options = preset.options;
+                    if !preset.output.is_empty() {
+                        app.output_path = preset.output;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => app.status_message = format!("Error reading config: {}", e),
+            }
+            app
+        });
+        match preset::load_presets() {
+            Ok(presets) => app.presets = presets,
+            Err(e) => app.status_message = format!("Error loading presets: {}", e),
+        }
+        app
+    }
+
+    /// The output path in single-file mode, or the output folder when a queue is set up.
+    fn output_mut(&mut self) -> &mut String {
+        if self.queue.is_empty() {
+            &mut self.output_path
+        } else {
+            &mut self.output_dir
+        }
+    }
+
+    fn apply_preset(&mut self, preset: Preset) {
+        self.options = preset.options;
+        *self.output_mut() = preset.output;
+        self.preset_name = preset.name;
+    }
+
+    fn save_preset(&mut self) {
+        let preset = Preset {
+            name: self.preset_name.trim().to_string(),
+            output: self.output_mut().clone(),
+            options: self.options.clone(),
+        };
+        if preset.name.is_empty() {
+            self.status_message = String::from("Enter a name for the preset");
+            return;
+        }
+
+        let name = preset.name.clone();
+        match self
+            .presets
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        self.status_message = match preset::save_presets(&self.presets) {
+            Ok(()) => format!("Saved preset {}", name),
+            Err(e) => format!("Error saving presets: {}", e),
+        };
+    }
+
+    fn delete_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        self.presets.retain(|preset| preset.name != name);
+        self.status_message = match preset::save_presets(&self.presets) {
+            Ok(()) => format!("Deleted preset {}", name),
+            Err(e) => format!("Error saving presets: {}", e),
+        };
+    }
+
+    /// Parses the edited or pasted markup, or else the input file.
+    fn load_input(&mut self) -> Result<Rc<Tree>, Box<dyn std::error::Error>> {
+        if self.svg_source.is_none() && is_url(&self.input_path) {
+            // URLs are downloaded once by `update_download`, not on every frame.
+            return Err("The SVG hasn't been downloaded yet".into());
+        }
+        let modified = self.input_modified();
+        if let Some(parsed) = &self.parsed {
+            if parsed.key.is_current(self, modified) {
+                return parsed.tree.clone().map_err(Into::into);
+            }
+        }
+
+        let tree = match &self.svg_source {
+            Some(svg_data) => parse_tree(svg_data, &self.input_path, &self.options),
+            None => load_tree(&self.input_path, &self.options),
+        };
+        let tree = tree.map(Rc::new).map_err(|e| e.to_string());
+        self.parsed = Some(ParsedInput {
+            key: InputKey::of(self, modified),
+            tree: tree.clone(),
+        });
+        tree.map_err(Into::into)
+    }
+
+    /// When the input file was last changed; markup held in memory has no time.
+    fn input_modified(&self) -> Option<SystemTime> {
+        match &self.svg_source {
+            Some(_) => None,
+            None => std::fs::metadata(&self.input_path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+
+    /// Whether typing in the input path has paused, scheduling a repaint to check again until
+    /// then, so a half-typed path isn't loaded.
+    fn path_settled(&self, ctx: &egui::Context) -> bool {
+        let Some(elapsed) = self.path_edited.map(|edited| edited.elapsed()) else {
+            return true;
+        };
+        if elapsed < PATH_DEBOUNCE {
+            ctx.request_repaint_after(PATH_DEBOUNCE - elapsed);
+            return false;
+        }
+        true
+    }
+
+    /// The input as shown in status messages.
+    fn input_label(&self) -> &str {
+        if self.input_path.is_empty() && self.svg_source.is_some() {
+            "pasted SVG"
+        } else {
+            &self.input_path
+        }
+    }
+
+    /// The SVG source as the editor should show it.
+    fn source_text(&self) -> String {
+        match &self.svg_source {
+            Some(svg_data) => svg_data.clone(),
+            None => read_svg(&self.input_path)
+                .map(|svg_data| svg_data.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn open_editor(&mut self) {
+        self.editor = Some(SourceEditor::new(
+            self.source_text(),
+            self.input_path.clone(),
+        ));
+    }
+
+    /// Downloads a URL input once it has been typed in, keeping the markup in memory.
+    fn update_download(&mut self, ctx: &egui::Context) {
+        let typing = ctx.memory(|memory| memory.has_focus(egui::Id::new("input_path")));
+        if is_url(&self.input_path) && self.downloaded_url != self.input_path && !typing {
+            self.downloaded_url = self.input_path.clone();
+            let url = self.input_path.clone();
+            let (sender, download) = mpsc::channel();
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                let _ = sender.send(fetch_svg(&url).map_err(|e| e.to_string()));
+                ctx.request_repaint();
+            });
+            self.download = Some(download);
+            self.status_message = format!("Downloading {}…", self.input_path);
+        }
+
+        let Some(result) = self.download.as_ref().and_then(|d| d.try_recv().ok()) else {
+            return;
+        };
+        self.download = None;
+        if self.downloaded_url != self.input_path {
+            // The input changed while downloading.
+            return;
+        }
+        match result {
+            Ok(svg_data) => {
+                self.svg_source = Some(svg_data);
+                self.preview_key = None;
+                self.status_message = format!("Downloaded {}", self.input_path);
+                if self.editor.is_some() {
+                    self.open_editor();
+                }
+            }
+            Err(e) => self.status_message = format!("Error downloading: {}", e),
+        }
+    }
+
+    /// Reloads the editor when the input changes and applies its edits once typing pauses.
+    fn update_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        if editor.loaded_from() != self.input_path {
+            self.open_editor();
+            return;
+        }
+        if let Some(text) = editor.settled(ctx) {
+            self.svg_source = Some(text.to_string());
+            self.preview_key = None;
+        }
+    }
+
+    /// Writes the edited source back to the input file.
+    fn save_source(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let editor = self.editor.as_ref().ok_or("The source editor isn't open")?;
+        std::fs::write(&self.input_path, editor.text())?;
+        self.svg_source = None;
+        self.preview_key = None;
+        Ok(())
+    }
+
+    fn show_editor(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Source");
+            let has_file = !self.input_path.is_empty();
+            if ui
+                .add_enabled(has_file, egui::Button::new("Save"))
+                .clicked()
+            {
+                self.status_message = match self.save_source() {
+                    Ok(()) => format!("Saved {}", self.input_path),
+                    Err(e) => format!("Error: {}", e),
+                };
+            }
+            if ui
+                .add_enabled(has_file, egui::Button::new("Revert"))
+                .clicked()
+            {
+                self.svg_source = None;
+                self.preview_key = None;
+                self.open_editor();
+            }
+        });
+        ui.add_space(5.0);
+        if let Some(editor) = &mut self.editor {
+            editor.show(ui);
+        }
+    }
+
+    /// Uses `text` as the input if it looks like SVG markup.
+    fn paste_svg(&mut self, text: &str) -> bool {
+        let text = text.trim();
+        if !(text.starts_with('<') && text.contains("<svg")) {
+            return false;
+        }
+        self.svg_source = Some(text.to_string());
+        self.input_path.clear();
+        self.preview_key = None;
+        if self.editor.is_some() {
+            self.open_editor();
+        }
+        self.status_message = String::from("Pasted SVG markup from the clipboard");
+        true
+    }
+
+    fn paste_from_clipboard(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        let text = clipboard.get_text()?;
+        if !self.paste_svg(&text) {
+            return Err("The clipboard doesn't contain SVG markup".into());
+        }
+        Ok(())
+    }
+
+    /// Ctrl+V outside a text field pastes SVG markup as the input.
+    fn handle_paste(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|memory| memory.focus().is_some()) {
+            return;
+        }
+        let pasted: Vec<String> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Paste(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+        for text in pasted {
+            self.paste_svg(&text);
+        }
+    }
+
+    /// Asks the preview thread for a new preview once the input or options change, and takes
+    /// in what it sent back.
+    fn update_preview(&mut self, ctx: &egui::Context) {
+        if let Some(thread) = &self.preview_thread {
+            while let Ok(event) = thread.events.try_recv() {
+                match event {
+                    PreviewEvent::Parsed {
+                        size,
+                        svg_info,
+                        issues,
+                    } => {
+                        self.input_size = size;
+                        self.svg_info = svg_info.map(|svg_info| *svg_info);
+                        self.issues = issues;
+                    }
+                    PreviewEvent::Rendered(pixmap) => {
+                        self.preview = pixmap.map(|pixmap| {
+                            let image = egui::ColorImage::from_rgba_premultiplied(
+                                [pixmap.width() as usize, pixmap.height() as usize],
+                                pixmap.data(),
+                            );
+                            ctx.load_texture("preview", image, egui::TextureOptions::LINEAR)
+                        });
+                    }
+                }
+            }
+        }
+
+        self.original_dimensions = self.input_size.map(|(w, h)| (w as u32, h as u32));
+        self.scaled_dimensions = self.input_size.and_then(|(w, h)| {
+            let options = &self.options;
+            options.canvas.tiled_size(options.output_size(w, h)).ok()
+        });
+
+        // URLs are shown once downloaded, rather than fetched again here.
+        if self.svg_source.is_none() && (self.input_path.is_empty() || is_url(&self.input_path)) {
+            self.preview_key = None;
+            self.input_size = None;
+            self.original_dimensions = None;
+            self.scaled_dimensions = None;
+            self.svg_info = None;
+            self.issues.clear();
+            self.preview = None;
+            return;
+        }
+        if !self.path_settled(ctx) {
+            return;
+        }
+        let modified = self.input_modified();
+        if let Some(key) = &self.preview_key {
+            if key.is_current(self, modified) && key.options == self.options {
+                return;
+            }
+        }
+
+        let key = InputKey::of(self, modified);
+        let request = PreviewRequest {
+            key: key.clone(),
+            reparse: self.preview_key.is_none(),
+        };
+        self.preview_key = Some(key);
+        let thread = self
+            .preview_thread
+            .get_or_insert_with(|| PreviewThread::spawn(ctx));
+        if thread.requests.send(request).is_err() {
+            // The thread panicked; a new one starts on the next frame.
+            self.preview_thread = None;
+            self.preview_key = None;
+        }
+    }
+
+    fn update_watcher(&mut self, ctx: &egui::Context) {
+        if !self.watch || self.input_path.is_empty() || is_url(&self.input_path) {
+            self.watcher = None;
+            return;
+        }
+
+        if self.watcher.as_ref().map(FileWatcher::path) != Some(self.input_path.as_str()) {
+            let ctx = ctx.clone();
+            match FileWatcher::new(&self.input_path, move || ctx.request_repaint()) {
+                Ok(watcher) => self.watcher = Some(watcher),
+                Err(e) => {
+                    self.watch = false;
+                    self.watcher = None;
+                    self.status_message = format!("Error: could not watch input: {}", e);
+                }
+            }
+            return;
+        }
+
+        // Changes stay queued in the watcher until the running conversion finishes.
+        if self.conversion.is_none() && self.watcher.as_ref().is_some_and(FileWatcher::changed) {
+            self.preview_key = None;
+            self.convert_single(ctx, false);
+        }
+    }
+
+    fn add_to_queue(&mut self, path: String) {
+        self.add_to_queue_in(path, PathBuf::new());
+    }
+
+    fn add_to_queue_in(&mut self, path: String, relative_dir: PathBuf) {
+        if !path.is_empty() && !self.queue.iter().any(|item| item.path == path) {
+            // URLs would be downloaded just to be measured, so their size stays unknown.
+            if let Some(probe) = self.size_probe.as_ref().filter(|_| !is_url(&path)) {
+                let _ = probe.requests.send((path.clone(), self.options.clone()));
+            }
+            self.queue.push(QueueItem {
+                path,
+                relative_dir,
+                svg_size: None,
+                overrides: ItemOverrides::default(),
+                selected: false,
+                status: ItemStatus::Pending,
+            });
+
+            // Files added during a batch join it, with the settings as they are now.
+            let index = self.queue.len() - 1;
+            if let Some(Conversion {
+                kind: ConversionKind::Queue,
+                ..
+            }) = self.conversion
+            {
+                let job = self.batch_job(&self.queue[index]);
+                let input = job.input.clone();
+                let conversion = self.conversion.as_mut().unwrap();
+                if conversion.worker.add_job(index, job) {
+                    conversion.inputs.push(input);
+                }
+            }
+        }
+    }
+
+    /// Takes in the sizes the size probe measured, starting it on the first frame.
+    fn update_queue_sizes(&mut self, ctx: &egui::Context) {
+        let probe = self.size_probe.get_or_insert_with(|| SizeProbe::spawn(ctx));
+        while let Ok((path, size)) = probe.sizes.try_recv() {
+            if let Some(item) = self.queue.iter_mut().find(|item| item.path == path) {
+                item.svg_size = Some(size);
+            }
+        }
+    }
+
+    /// Moves a queue item, keeping the item whose overrides are open in the window.
+    fn move_item(&mut self, from: usize, to: usize) {
+        let item = self.queue.remove(from);
+        self.queue.insert(to, item);
+        self.editing_item = self.editing_item.map(|editing| match editing {
+            editing if editing == from => to,
+            editing if from < editing && editing <= to => editing - 1,
+            editing if to <= editing && editing < from => editing + 1,
+            editing => editing,
+        });
+        self.selection_anchor = None;
+    }
+
+    /// A plain click selects just the item, Ctrl/Cmd-click toggles it and Shift-click
+    /// selects everything from the last item clicked.
+    fn select_item(&mut self, index: usize, modifiers: egui::Modifiers) {
+        match self.selection_anchor {
+            Some(anchor) if modifiers.shift => {
+                let range = anchor.min(index)..=anchor.max(index);
+                for (i, item) in self.queue.iter_mut().enumerate() {
+                    item.selected = range.contains(&i) || (modifiers.command && item.selected);
+                }
+                return;
+            }
+            _ if modifiers.command => self.queue[index].selected = !self.queue[index].selected,
+            _ => {
+                for (i, item) in self.queue.iter_mut().enumerate() {
+                    item.selected = i == index;
+                }
+            }
+        }
+        self.selection_anchor = Some(index);
+    }
+
+    fn remove_selected(&mut self) {
+        self.queue.retain(|item| !item.selected);
+        self.editing_item = None;
+        self.selection_anchor = None;
+    }
+
+    fn add_folder(&mut self, dir: &Path) {
+        match find_svgs(dir, self.recursive) {
+            Ok(paths) => {
+                for path in paths {
+                    let relative_dir = path
+                        .parent()
+                        .and_then(|parent| parent.strip_prefix(dir).ok())
+                        .unwrap_or(Path::new(""))
+                        .to_path_buf();
+                    self.add_to_queue_in(path.display().to_string(), relative_dir);
+                }
+            }
+            Err(e) => self.status_message = format!("Error: {}", e),
+        }
+    }
+
+    /// Table of queued files with their output size and state; failed items expand to
+    /// show their error. Until the queue runs, rows can be selected, dragged by their handle
+    /// to reorder them and double-clicked to edit their overrides.
+    fn show_queue(&mut self, ui: &mut egui::Ui) {
+        let idle = self.conversion.is_none();
+        let mut clicked = None;
+        let mut moved = None;
+        egui::ScrollArea::vertical()
+            .max_height(140.0)
+            .show(ui, |ui| {
+                egui::Grid::new("queue")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.strong("File");
+                        ui.strong("Size");
+                        ui.strong("State");
+                        ui.end_row();
+
+                        for (index, item) in self.queue.iter().enumerate() {
+                            let handle = ui
+                                .add_enabled(idle, egui::Label::new("☰").sense(egui::Sense::drag()))
+                                .on_hover_text("Drag to reorder");
+                            if handle.drag_started() {
+                                self.dragged_item = Some(index);
+                            }
+                            let name = Path::new(&item.path)
+                                .file_name()
+                                .map_or(item.path.clone(), |name| {
+                                    name.to_string_lossy().into_owned()
+                                });
+                            let name = if item.overrides.is_empty() {
+                                name
+                            } else {
+                                format!("{} *", name)
+                            };
+                            let size = item.svg_size.and_then(|(width, height)| {
+                                let options = item.overrides.apply(&self.options);
+                                options
+                                    .canvas
+                                    .tiled_size(options.output_size(width, height))
+                                    .ok()
+                            });
+                            let size = match size {
+                                Some((width, height)) => format!("{}×{}", width, height),
+                                None => String::from("?"),
+                            };
+                            let name_cell = ui
+                                .add(egui::SelectableLabel::new(item.selected, name))
+                                .on_hover_text(format!(
+                                    "{}\nDouble-click to override its settings",
+                                    item.path
+                                ));
+                            let size_cell =
+                                ui.add(egui::Label::new(size).sense(egui::Sense::click()));
+                            if name_cell.clicked() && idle {
+                                clicked = Some(index);
+                            }
+                            if (name_cell.double_clicked() || size_cell.double_clicked()) && idle {
+                                self.editing_item = Some(index);
+                            }
+                            match &item.status {
+                                ItemStatus::Failed(e) => {
+                                    let error_color = ui.visuals().error_fg_color;
+                                    egui::CollapsingHeader::new(
+                                        egui::RichText::new(item.status.name()).color(error_color),
+                                    )
+                                    .id_source(("queue_error", index))
+                                    .show(ui, |ui| {
+                                        ui.set_max_width(320.0);
+                                        ui.add(egui::Label::new(e.as_str()).wrap(true));
+                                    });
+                                }
+                                status => {
+                                    ui.label(status.name());
+                                }
+                            }
+
+                            // The row under the pointer makes way for the dragged item.
+                            let pointer = ui.ctx().pointer_interact_pos();
+                            if let (Some(dragged), Some(pointer)) = (self.dragged_item, pointer) {
+                                let row = handle.rect.union(name_cell.rect);
+                                if dragged != index
+                                    && (row.top()..row.bottom()).contains(&pointer.y)
+                                {
+                                    moved = Some((dragged, index));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some((from, to)) = moved {
+            self.move_item(from, to);
+            self.dragged_item = Some(to);
+        }
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.dragged_item = None;
+        }
+        if let Some(index) = clicked {
+            self.select_item(index, ui.input(|i| i.modifiers));
+        }
+        let delete = ui.input(|i| i.key_pressed(egui::Key::Delete));
+        if idle && delete && ui.memory(|memory| memory.focus().is_none()) {
+            self.remove_selected();
+        }
+    }
+
+    /// Problems found in the input, under a collapsed summary.
+    fn show_issues(&self, ui: &mut egui::Ui) {
+        let errors = self
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        let warnings = self.issues.len() - errors;
+        let count =
+            |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+        let summary = match (errors, warnings) {
+            (0, warnings) => count(warnings, "warning"),
+            (errors, 0) => count(errors, "problem"),
+            (errors, warnings) => format!(
+                "{}, {}",
+                count(errors, "problem"),
+                count(warnings, "warning")
+            ),
+        };
+        egui::CollapsingHeader::new(format!("Checks: {}", summary))
+            .id_source("issues")
+            .show(ui, |ui| {
+                for issue in &self.issues {
+                    ui.horizontal_wrapped(|ui| {
+                        match issue.severity {
+                            Severity::Error => ui.colored_label(ui.visuals().error_fg_color, "❌"),
+                            Severity::Warning => ui.colored_label(ui.visuals().warn_fg_color, "⚠"),
+                        };
+                        ui.label(&issue.message);
+                        if let Some(line) = issue.line {
+                            ui.weak(format!("line {}", line));
+                        }
+                    });
+                }
+            });
+    }
+
+    /// Past conversions, newest first, each of which can be run again as it was or loaded
+    /// to adjust before converting.
+    fn show_history(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("History");
+            if ui
+                .add_enabled(!self.history.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.history.clear();
+            }
+            if ui
+                .button("Save log…")
+                .on_hover_text(
+                    "Save the log of conversions, their settings, warnings and timings, \
+                     e.g. to attach to a bug report",
+                )
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("rust-svg-converter.log")
+                    .save_file()
+                {
+                    self.status_message = match logging::export(&path) {
+                        Ok(()) => format!("Saved the log to {}", path.display()),
+                        Err(e) => format!("Error saving log: {}", e),
+                    };
+                }
+            }
+        });
+        if self.history.is_empty() {
+            ui.label("Conversions show up here once they finish.");
+            return;
+        }
+
+        let idle = self.conversion.is_none();
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("history")
+                .num_columns(6)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("When");
+                    ui.strong("Input");
+                    ui.strong("Output");
+                    ui.strong("Took");
+                    ui.strong("Result");
+                    ui.end_row();
+
+                    for (index, entry) in self.history.iter().enumerate() {
+                        ui.label(time_ago(entry.started_at));
+                        ui.label(entry.label())
+                            .on_hover_text(entry.inputs.join("\n"));
+                        ui.label(&entry.output);
+                        ui.label(format!("{:.1}s", entry.duration.as_secs_f32()));
+                        if entry.failed == 0 {
+                            ui.label("done");
+                        } else {
+                            let error_color = ui.visuals().error_fg_color;
+                            ui.colored_label(error_color, format!("{} failed", entry.failed));
+                        }
+                        ui.add_enabled_ui(idle, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("Run again")
+                                    .on_hover_text(format!(
+                                        "{} at {}x as {}",
+                                        entry.label(),
+                                        entry.options.scale,
+                                        entry.options.format.name()
+                                    ))
+                                    .clicked()
+                                {
+                                    action = Some((index, true));
+                                }
+                                if ui
+                                    .button("Load")
+                                    .on_hover_text("Restore its files and settings to adjust them")
+                                    .clicked()
+                                {
+                                    action = Some((index, false));
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some((index, run)) = action {
+            let entry = self.history[index].clone();
+            self.load_history(&entry);
+            if run {
+                if entry.queue {
+                    self.convert_queue(ui.ctx());
+                } else {
+                    self.convert_single(ui.ctx(), false);
+                }
+            }
+        }
+    }
+
+    /// Window editing the overrides of the queue item being edited.
+    fn show_overrides(&mut self, ctx: &egui::Context) {
+        let Some(item) = self
+            .editing_item
+            .and_then(|index| self.queue.get_mut(index))
+        else {
+            self.editing_item = None;
+            return;
+        };
+        let mut open = true;
+        let name = Path::new(&item.path)
+            .file_name()
+            .map_or(item.path.clone(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+        egui::Window::new(format!("Overrides for {}", name))
+            .id(egui::Id::new("queue_overrides"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let overrides = &mut item.overrides;
+                ui.horizontal(|ui| {
+                    let mut enabled = overrides.scale.is_some();
+                    let mut scale = overrides.scale.unwrap_or(self.options.scale);
+                    ui.checkbox(&mut enabled, "Scale:");
+                    ui.add_enabled(
+                        enabled,
+                        egui::Slider::new(&mut scale, 0.1..=64.0)
+                            .logarithmic(true)
+                            .max_decimals(2)
+                            .suffix("x"),
+                    );
+                    overrides.scale = enabled.then_some(scale);
+                });
+                ui.horizontal(|ui| {
+                    let mut enabled = overrides.background.is_some();
+                    let mut color = overrides
+                        .background
+                        .or(self.options.background)
+                        .unwrap_or([255, 255, 255]);
+                    ui.checkbox(&mut enabled, "Background:");
+                    ui.add_enabled_ui(enabled, |ui| ui.color_edit_button_srgb(&mut color));
+                    overrides.background = enabled.then_some(color);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Output name:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut overrides.output_name).hint_text("{name}"),
+                    )
+                    .on_hover_text(format!(
+                        "Written to the output folder; the extension is added if left out. \
+                         Tokens: {}",
+                        template::TOKENS
+                    ));
+                });
+                ui.add_enabled_ui(!overrides.is_empty(), |ui| {
+                    if ui.button("Use shared settings").clicked() {
+                        *overrides = ItemOverrides::default();
+                    }
+                });
+            });
+        if !open {
+            self.editing_item = None;
+        }
+    }
+
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        if self.tab == Tab::Trace {
+            let image = ctx.input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|file| file.path.clone())
+                    .find(|path| is_raster_path(path))
+            });
+            if let Some(path) = image {
+                self.trace_tab.set_input(&path);
+            }
+            return;
+        }
+
+        let dropped: Vec<String> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.as_ref())
+                .filter(|path| is_svg_path(path))
+                .map(|path| path.display().to_string())
+                .collect()
+        });
+
+        if dropped.len() == 1 && self.queue.is_empty() {
+            self.input_path = dropped.into_iter().next().unwrap();
+            self.svg_source = None;
+        } else {
+            for path in dropped {
+                self.add_to_queue(path);
+            }
+        }
+    }
+
+    fn set_format(&mut self, format: OutputFormat) {
+        self.options.format = format;
+        self.output_path = Path::new(&self.output_path)
+            .with_extension(format.extension())
+            .display()
+            .to_string();
+    }
+
+    fn clipboard(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+
+    fn copy_to_clipboard(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let rtree = self.load_input()?;
+        let pixmap = render_tree(&rtree, &self.options)?;
+        let image = arboard::ImageData {
+            width: pixmap.width() as usize,
+            height: pixmap.height() as usize,
+            bytes: encode::unpremultiply(&pixmap).into(),
+        };
+        self.clipboard()?.set_image(image)?;
+        Ok(())
+    }
+
+    /// Copies the input, converted to the selected format, as a `data:` URI.
+    fn copy_data_uri(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let converter = Converter::new(self.options.clone());
+        let data = converter.convert(&self.source_text(), &self.input_path)?;
+        let uri = encode::data_uri(&data, self.options.format);
+        self.clipboard()?.set_text(uri)?;
+        Ok(())
+    }
+
+    fn start_conversion(
+        &mut self,
+        worker: Worker,
+        inputs: Vec<String>,
+        kind: ConversionKind,
+        record: Option<HistoryEntry>,
+    ) {
+        self.conversion = Some(Conversion {
+            worker,
+            inputs,
+            kind,
+            record,
+            active: Vec::new(),
+            finished: 0,
+            timings: StageTimings::default(),
+            started: Instant::now(),
+        });
+        self.status_message.clear();
+        self.last_output = None;
+    }
+
+    fn convert_single(&mut self, ctx: &egui::Context, open: bool) {
+        let worker = match &self.svg_source {
+            Some(svg_data) => Worker::spawn_markup(
+                svg_data.clone(),
+                if self.input_path.is_empty() {
+                    PASTED_NAME.to_string()
+                } else {
+                    self.input_path.clone()
+                },
+                self.output_path.clone(),
+                self.options.clone(),
+                ctx.clone(),
+            ),
+            None => Worker::spawn(
+                vec![BatchJob {
+                    input: self.input_path.clone(),
+                    output_template: self.output_path.clone(),
+                    options: self.options.clone(),
+                }],
+                self.jobs,
+                ctx.clone(),
+            ),
+        };
+        let record = (!self.input_path.is_empty())
+            .then(|| self.history_entry(vec![self.input_path.clone()], false));
+        let inputs = vec![self.input_label().to_string()];
+        self.start_conversion(worker, inputs, ConversionKind::Single { open }, record);
+    }
+
+    fn convert_queue(&mut self, ctx: &egui::Context) {
+        if self.sprite_sheet {
+            let inputs: Vec<String> = self.queue.iter().map(|item| item.path.clone()).collect();
+            let label = format!("{} files", inputs.len());
+            let worker = Worker::spawn_atlas(
+                inputs,
+                self.sprite_sheet_path.clone(),
+                self.options.clone(),
+                self.atlas.clone(),
+                ctx.clone(),
+            );
+            let kind = ConversionKind::Single { open: false };
+            self.start_conversion(worker, vec![label], kind, None);
+            return;
+        }
+
+        let jobs: Vec<BatchJob> = self.queue.iter().map(|item| self.batch_job(item)).collect();
+
+        for item in &mut self.queue {
+            item.status = ItemStatus::Pending;
+        }
+        let inputs: Vec<String> = jobs.iter().map(|job| job.input.clone()).collect();
+        let record = self.history_entry(inputs.clone(), true);
+        log::info!(
+            "batch started files={} jobs={} output={:?}",
+            inputs.len(),
+            self.jobs,
+            self.output_dir
+        );
+        let worker = Worker::spawn(jobs, self.jobs, ctx.clone());
+        self.start_conversion(worker, inputs, ConversionKind::Queue, Some(record));
+    }
+
+    /// A history entry for a conversion starting now with the current settings.
+    fn history_entry(&self, inputs: Vec<String>, queue: bool) -> HistoryEntry {
+        HistoryEntry {
+            inputs,
+            output: if queue {
+                self.output_dir.clone()
+            } else {
+                self.output_path.clone()
+            },
+            queue,
+            options: self.options.clone(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            duration: Duration::ZERO,
+            failed: 0,
+        }
+    }
+
+    /// Restores the inputs, output and settings of a past conversion, so it can be run again
+    /// as it was or after changing them. Per-file overrides of a queue aren't kept.
+    fn load_history(&mut self, entry: &HistoryEntry) {
+        self.options = entry.options.clone();
+        self.svg_source = None;
+        if entry.queue {
+            self.queue.clear();
+            self.editing_item = None;
+            self.selection_anchor = None;
+            for input in &entry.inputs {
+                self.add_to_queue(input.clone());
+            }
+            self.output_dir = entry.output.clone();
+            self.sprite_sheet = false;
+        } else {
+            self.queue.clear();
+            self.input_path = entry.inputs.first().cloned().unwrap_or_default();
+            self.output_path = entry.output.clone();
+        }
+    }
+
+    /// The job converting `item` with the current settings and its overrides.
+    fn batch_job(&self, item: &QueueItem) -> BatchJob {
+        let mut output_template = if template::has_tokens(&self.output_dir) {
+            self.output_dir.clone()
+        } else if self.output_dir.is_empty() {
+            let input_dir = Path::new(&item.path).parent().unwrap_or(Path::new(""));
+            batch_output_template(input_dir, self.options.format)
+        } else {
+            let output_dir = Path::new(&self.output_dir).join(&item.relative_dir);
+            batch_output_template(&output_dir, self.options.format)
+        };
+        let name = &item.overrides.output_name;
+        if !name.is_empty() {
+            let file_name = if Path::new(name).extension().is_some() {
+                name.clone()
+            } else {
+                format!("{}.{}", name, self.options.format.extension())
+            };
+            output_template = Path::new(&output_template)
+                .with_file_name(file_name)
+                .display()
+                .to_string();
+        }
+        BatchJob {
+            input: item.path.clone(),
+            output_template,
+            options: item.overrides.apply(&self.options),
+        }
+    }
+
+    fn poll_conversion(&mut self) {
+        let Some(conversion) = &mut self.conversion else {
+            return;
+        };
+
+        for event in conversion.worker.poll() {
+            match event {
+                WorkerEvent::ItemStarted(index) => {
+                    conversion.active.push(index);
+                    if let ConversionKind::Queue = conversion.kind {
+                        self.queue[index].status = ItemStatus::Converting;
+                    }
+                }
+                WorkerEvent::ItemDone(index, result, timings) => {
+                    let result = result.map_err(|e| e.message);
+                    conversion.active.retain(|&active| active != index);
+                    conversion.finished += 1;
+                    conversion.timings += timings;
+                    match &conversion.kind {
+                        ConversionKind::Single { open } => {
+                            if let Some(mut record) = conversion.record.take() {
+                                if !matches!(&result, Err(e) if e == CANCELLED) {
+                                    record.failed = usize::from(result.is_err());
+                                    record.duration = conversion.started.elapsed();
+                                    remember(&mut self.history, record);
+                                }
+                            }
+                            self.status_message = match result.and_then(|written| {
+                                if *open {
+                                    platform::open(&written[0]).map_err(|e| e.to_string())?;
+                                }
+                                Ok(written)
+                            }) {
+                                Ok(written) => {
+                                    self.last_output = Some(written[0].clone());
+                                    format!(
+                                        "Successfully converted {} to {} ({})",
+                                        conversion.inputs[index],
+                                        written.join(", "),
+                                        timings
+                                    )
+                                }
+                                Err(e) if e == CANCELLED => String::from("Conversion cancelled"),
+                                Err(e) => format!("Error: {}", e),
+                            };
+                        }
+                        ConversionKind::Queue => {
+                            self.queue[index].status = match result {
+                                Ok(_) => ItemStatus::Done,
+                                Err(e) if e == CANCELLED => ItemStatus::Pending,
+                                Err(e) => ItemStatus::Failed(e),
+                            };
+                        }
+                    }
+                }
+                WorkerEvent::Finished(result) => {
+                    if let Err(e) = result {
+                        self.status_message = format!("Error: {}", e);
+                    } else if let ConversionKind::Queue = conversion.kind {
+                        let done = self
+                            .queue
+                            .iter()
+                            .filter(|item| matches!(item.status, ItemStatus::Done))
+                            .count();
+                        log::info!(
+                            "batch finished files={} done={} cancelled={} took={:.3}s",
+                            conversion.inputs.len(),
+                            done,
+                            conversion.worker.is_cancelled(),
+                            conversion.started.elapsed().as_secs_f64()
+                        );
+                        if let Some(mut record) = conversion.record.take() {
+                            if !conversion.worker.is_cancelled() {
+                                // Files added while the queue ran were part of it too.
+                                record.inputs = conversion.inputs.clone();
+                                record.failed = conversion.inputs.len() - done;
+                                record.duration = conversion.started.elapsed();
+                                remember(&mut self.history, record);
+                            }
+                        }
+                        self.status_message = format!(
+                            "{} {} of {} files in {:.1}s ({} over all files)",
+                            if conversion.worker.is_cancelled() {
+                                "Cancelled after converting"
+                            } else {
+                                "Converted"
+                            },
+                            done,
+                            conversion.inputs.len(),
+                            conversion.started.elapsed().as_secs_f32(),
+                            conversion.timings
+                        );
+                    }
+                    self.conversion = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Parses the input once for everything the preview shows: the tree to render, what the
+/// input contains and its lint issues.
+fn parse_input(key: &InputKey) -> (Result<Tree, String>, Option<SvgInfo>, Vec<Issue>) {
+    let mut svg_info = None;
+    let mut issues = Vec::new();
+    let tree = parse_and_inspect(key, &mut svg_info, &mut issues).map_err(|e| e.to_string());
+    (tree, svg_info, issues)
+}
+
+/// [`convert::parse_tree`], describing and linting the document along the way.
+fn parse_and_inspect(
+    key: &InputKey,
+    svg_info: &mut Option<SvgInfo>,
+    issues: &mut Vec<Issue>,
+) -> Result<Tree, Box<dyn std::error::Error>> {
+    let (input_path, options) = (&key.input_path, &key.options);
+    let read;
+    let svg_data = match &key.svg_source {
+        Some(svg_data) => svg_data.as_str(),
+        None => {
+            read = read_svg(input_path)?;
+            &*read
+        }
+    };
+    let svg_data = options
+        .colors
+        .prepare(svg_data)
+        .map_err(|e| ParseError::new(&e, svg_data))?;
+    let (mut rtree, blocked) = {
+        let document = convert::parse_xml(&svg_data)?;
+        let (rtree, blocked) = convert::document_tree(&document, input_path, options)?;
+        let info = info::describe(&document, &rtree, input_path);
+        *issues = lint::check(&document, &info, input_path, options).unwrap_or_default();
+        *svg_info = Some(info);
+        (rtree, blocked)
+    };
+    convert::check_blocked(&blocked)?;
+    convert::finish_tree(&mut rtree, options)?;
+    Ok(rtree)
+}
+
+/// Renders the preview, only up to a sensible texture size for large outputs.
+fn render_preview(
+    rtree: &Tree,
+    options: &ConversionOptions,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let mut preview_options = options.clone();
+    let (w, h) = options
+        .canvas
+        .tiled_size(options.output_size(rtree.size.width() as f32, rtree.size.height() as f32))?;
+    let fit = MAX_PREVIEW_SIDE as f32 / w.max(h) as f32;
+    if fit < 1.0 {
+        preview_options.width = Some(((w as f32 * fit).round() as u32).max(1));
+        preview_options.height = Some(((h as f32 * fit).round() as u32).max(1));
+    }
+    render_tree(rtree, &preview_options)
+}
+
+/// Counts of what the input draws, under a collapsed header.
+fn show_statistics(ui: &mut egui::Ui, svg_info: &SvgInfo) {
+    let stats = &svg_info.stats;
+    egui::CollapsingHeader::new("Statistics")
+        .id_source("statistics")
+        .show(ui, |ui| {
+            egui::Grid::new("statistics_grid")
+                .num_columns(4)
+                .show(ui, |ui| {
+                    let elements: usize = svg_info.elements.values().sum();
+                    let rows = [
+                        ("File size", format_bytes(svg_info.file_size)),
+                        ("Elements", elements.to_string()),
+                        ("Paths", stats.paths.to_string()),
+                        ("Groups", stats.groups.to_string()),
+                        ("Text", stats.texts.to_string()),
+                        ("Images", stats.images.to_string()),
+                        ("Gradients", stats.gradients.to_string()),
+                        ("Patterns", stats.patterns.to_string()),
+                        ("Clip paths", stats.clip_paths.to_string()),
+                        ("Masks", stats.masks.to_string()),
+                        ("Filters", stats.filters.to_string()),
+                    ];
+                    for (index, (label, value)) in rows.iter().enumerate() {
+                        ui.label(*label);
+                        ui.strong(value);
+                        if index % 2 == 1 {
+                            ui.end_row();
+                        }
+                    }
+                });
+        })
+        .header_response
+        .on_hover_text("Counted after parsing, without hidden or unused elements");
+}
+
+/// Memory the output needs while rendering, flagged when it's too large to render or
+/// likely to exhaust memory unless it can be rendered in strips.
+fn show_memory_estimate(ui: &mut egui::Ui, size: (u32, u32), options: &ConversionOptions) {
+    let bytes = pixmap_bytes(size);
+    let estimate = format!("Memory: about {}", format_bytes(bytes));
+    if large_output::is_needed(size) && large_output::unsupported(options).is_none() {
+        ui.label(format!(
+            "{} at once, so it will be rendered in strips",
+            estimate
+        ));
+    } else if let Err(e) = check_pixmap_size(size) {
+        ui.colored_label(ui.visuals().error_fg_color, format!("{}. {}", estimate, e));
+    } else if bytes > LARGE_PIXMAP_BYTES {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("{}; lower the scale if the conversion fails", estimate),
+        );
+    } else {
+        ui.label(estimate);
+    }
+}
+
+/// Adds `entry` to the top of the history, dropping the oldest entries.
+fn remember(history: &mut Vec<HistoryEntry>, entry: HistoryEntry) {
+    history.insert(0, entry);
+    history.truncate(MAX_HISTORY_ENTRIES);
+}
+
+/// How long ago a Unix timestamp was, e.g. `5 min ago`.
+fn time_ago(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    match now.saturating_sub(timestamp) {
+        seconds if seconds < 60 => String::from("just now"),
+        seconds if seconds < 3600 => format!("{} min ago", seconds / 60),
+        seconds if seconds < 86_400 => format!("{} h ago", seconds / 3600),
+        seconds => format!("{} d ago", seconds / 86_400),
+    }
+}
+
+/// Progress bar with the file being converted, files remaining and elapsed time.
+fn show_progress(ui: &mut egui::Ui, conversion: &Conversion) {
+    let total = conversion.inputs.len();
+    ui.add(
+        egui::ProgressBar::new(conversion.finished as f32 / total as f32)
+            .text(format!("{} / {}", conversion.finished, total)),
+    );
+
+    let current = conversion
+        .active
+        .first()
+        .map(|&index| {
+            Path::new(&conversion.inputs[index])
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .unwrap_or_default();
+    ui.label(format!(
+        "Converting {} · {} remaining · {}s elapsed",
+        current,
+        total - conversion.finished,
+        conversion.started.elapsed().as_secs()
+    ));
+    // Keep the elapsed time ticking while no worker events arrive.
+    ui.ctx().request_repaint_after(Duration::from_secs(1));
+}
+
+/// Text field where an empty value means `None`.
+fn optional_text_edit(ui: &mut egui::Ui, value: &mut Option<String>, hint: &str) {
+    let mut text = value.clone().unwrap_or_default();
+    if ui
+        .add(egui::TextEdit::singleline(&mut text).hint_text(hint))
+        .changed()
+    {
+        *value = (!text.trim().is_empty()).then_some(text);
+    }
+}
+
+/// Size field where 0 (shown as "auto") means the dimension is derived.
+fn optional_number_edit<N: egui::emath::Numeric>(ui: &mut egui::Ui, value: &mut Option<N>, max: N) {
+    let zero = N::from_f64(0.0);
+    let mut number = value.unwrap_or(zero);
+    ui.add(
+        egui::DragValue::new(&mut number)
+            .clamp_range(zero..=max)
+            .custom_formatter(|n, _| {
+                if n == 0.0 {
+                    String::from("auto")
+                } else {
+                    n.to_string()
+                }
+            })
+            .custom_parser(|text| {
+                if text.trim().eq_ignore_ascii_case("auto") {
+                    Some(0.0)
+                } else {
+                    text.trim().parse().ok()
+                }
+            }),
+    );
+    *value = (number.to_f64() > 0.0).then_some(number);
+}
+
+/// Draws the preview scaled down to fit, over a checkerboard showing transparency.
+pub fn show_preview(ui: &mut egui::Ui, preview: Option<&egui::TextureHandle>) {
+    let Some(texture) = preview else {
+        ui.label("No preview");
+        return;
+    };
+
+    let available = ui.available_size();
+    let size = texture.size_vec2();
+    let fit = (available.x / size.x).min(available.y / size.y).min(1.0);
+    let (rect, _) = ui.allocate_exact_size(size * fit, egui::Sense::hover());
+
+    paint_checkerboard(ui.painter(), rect);
+    ui.painter().image(
+        texture.id(),
+        rect,
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        egui::Color32::WHITE,
+    );
+}
+
+fn paint_checkerboard(painter: &egui::Painter, rect: egui::Rect) {
+    const CELL: f32 = 8.0;
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(255));
+    let columns = (rect.width() / CELL).ceil() as usize;
+    let rows = (rect.height() / CELL).ceil() as usize;
+    for row in 0..rows {
+        for column in (row % 2..columns).step_by(2) {
+            let min = rect.min + egui::vec2(column as f32 * CELL, row as f32 * CELL);
+            let cell = egui::Rect::from_min_size(min, egui::vec2(CELL, CELL)).intersect(rect);
+            painter.rect_filled(cell, 0.0, egui::Color32::from_gray(204));
+        }
+    }
+}
+
+impl eframe::App for SvgConverterApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_queue_sizes(ctx);
+        self.handle_dropped_files(ctx);
+        self.handle_paste(ctx);
+        self.poll_conversion();
+        self.update_watcher(ctx);
+        self.update_download(ctx);
+        self.update_editor(ctx);
+        self.update_preview(ctx);
+        if self.tab == Tab::Trace {
+            self.trace_tab.update(ctx, &mut self.status_message);
+        }
+
+        egui::SidePanel::right("preview")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.heading("Preview");
+                ui.add_space(5.0);
+                ui.centered_and_justified(|ui| match self.tab {
+                    Tab::Render => show_preview(ui, self.preview.as_ref()),
+                    Tab::Trace => self.trace_tab.show_preview(ui),
+                });
+            });
+
+        if self.show_history && self.tab == Tab::Render {
+            egui::TopBottomPanel::bottom("history")
+                .resizable(true)
+                .default_height(180.0)
+                .show(ctx, |ui| self.show_history(ui));
+        }
+
+        if self.editor.is_some() && self.tab == Tab::Render {
+            egui::SidePanel::left("source")
+                .resizable(true)
+                .default_width(320.0)
+                .show(ctx, |ui| self.show_editor(ui));
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tab, Tab::Render, "SVG to image");
+                ui.selectable_value(&mut self.tab, Tab::Trace, "Image to SVG");
+            });
+            if self.tab == Tab::Trace {
+                self.trace_tab.show(ui, &mut self.status_message);
+                return;
+            }
+
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.heading("SVG to PNG Converter");
+                ui.add_space(10.0);
+
+                let mut input_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Input SVG:");
+                    let hint = if self.svg_source.is_some() {
+                        "Pasted SVG"
+                    } else {
+                        ""
+                    };
+                    if ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.input_path)
+                                .id(egui::Id::new("input_path"))
+                                .hint_text(hint),
+                        )
+                        .on_hover_text("A file path or an http(s) URL")
+                        .changed()
+                    {
+                        self.path_edited = Some(Instant::now());
+                        input_changed = true;
+                    }
+                    if ui.button("Browse").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("SVG files", &SVG_EXTENSIONS)
+                            .pick_file()
+                        {
+                            self.input_path = path.display().to_string();
+                            input_changed = true;
+                        }
+                    }
+                    if ui
+                        .button("Paste")
+                        .on_hover_text("Use SVG markup from the clipboard as the input")
+                        .clicked()
+                    {
+                        if let Err(e) = self.paste_from_clipboard() {
+                            self.status_message = format!("Error: {}", e);
+                        }
+                    }
+                    if ui.button("Add to queue").clicked() {
+                        self.add_to_queue(self.input_path.clone());
+                    }
+                    let mut editing = self.editor.is_some();
+                    if ui
+                        .checkbox(&mut editing, "Edit source")
+                        .on_hover_text("Tweak the SVG markup with a live preview")
+                        .changed()
+                    {
+                        if editing {
+                            self.open_editor();
+                        } else {
+                            self.editor = None;
+                        }
+                    }
+                    // The latest successful conversion of each recently converted file.
+                    let mut recent: Vec<HistoryEntry> = Vec::new();
+                    for entry in &self.history {
+                        if !entry.queue
+                            && entry.failed == 0
+                            && recent.len() < MAX_RECENT_FILES
+                            && !recent.iter().any(|seen| seen.inputs == entry.inputs)
+                        {
+                            recent.push(entry.clone());
+                        }
+                    }
+                    let can_rerun = !recent.is_empty() && self.conversion.is_none();
+                    ui.add_enabled_ui(can_rerun, |ui| {
+                        ui.menu_button("Recent", |ui| {
+                            let mut rerun = None;
+                            for entry in recent {
+                                if ui
+                                    .button(entry.label())
+                                    .on_hover_text(format!("Convert again to {}", entry.output))
+                                    .clicked()
+                                {
+                                    rerun = Some(entry);
+                                    ui.close_menu();
+                                }
+                            }
+                            if let Some(entry) = rerun {
+                                self.load_history(&entry);
+                                self.convert_single(ctx, true);
+                            }
+                        });
+                    });
+                    ui.toggle_value(&mut self.show_history, "History")
+                        .on_hover_text("Past conversions, to run again or adjust");
+                });
+                if input_changed {
+                    self.svg_source = None;
+                }
+                if !self.issues.is_empty() {
+                    self.show_issues(ui);
+                }
+                if let Some(svg_info) = &self.svg_info {
+                    show_statistics(ui, svg_info);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add files…").clicked() {
+                        if let Some(paths) = rfd::FileDialog::new()
+                            .add_filter("SVG files", &SVG_EXTENSIONS)
+                            .pick_files()
+                        {
+                            for path in paths {
+                                self.add_to_queue(path.display().to_string());
+                            }
+                        }
+                    }
+                    if ui.button("Add folder…").clicked() {
+                        if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                            self.add_folder(&dir);
+                        }
+                    }
+                    ui.checkbox(&mut self.recursive, "Recursive");
+                    ui.label("Jobs:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.jobs)
+                            .clamp_range(0..=256)
+                            .custom_formatter(|n, _| {
+                                if n == 0.0 {
+                                    String::from("all")
+                                } else {
+                                    n.to_string()
+                                }
+                            }),
+                    )
+                    .on_hover_text("Files converted in parallel; 0 uses every core");
+                    if !self.queue.is_empty() && self.conversion.is_none() {
+                        if ui.button("Clear queue").clicked() {
+                            self.queue.clear();
+                            self.editing_item = None;
+                            self.selection_anchor = None;
+                        }
+                        let any_selected = self.queue.iter().any(|item| item.selected);
+                        if ui
+                            .add_enabled(any_selected, egui::Button::new("Remove selected"))
+                            .on_hover_text(
+                                "Removes the files selected in the list; Delete does the same",
+                            )
+                            .clicked()
+                        {
+                            self.remove_selected();
+                        }
+                    }
+                });
+
+                if !self.queue.is_empty() {
+                    self.show_queue(ui);
+                    self.show_overrides(ui.ctx());
+                }
+
+                ui.add_space(5.0);
+
+                if !self.queue.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.sprite_sheet, "Pack into sprite sheet")
+                            .on_hover_text(
+                                "Render every file into one image, with a JSON or CSS file \
+                                 giving each sprite's position",
+                            );
+                        ui.add_enabled_ui(self.sprite_sheet, |ui| {
+                            egui::ComboBox::from_id_source("sprite_data")
+                                .selected_text(self.atlas.data.name())
+                                .show_ui(ui, |ui| {
+                                    for data in SpriteData::ALL {
+                                        ui.selectable_value(
+                                            &mut self.atlas.data,
+                                            data,
+                                            data.name(),
+                                        );
+                                    }
+                                });
+                            ui.label("Gap:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.atlas.gap)
+                                    .clamp_range(0..=256)
+                                    .suffix(" px"),
+                            );
+                        });
+                    });
+                }
+
+                if !self.queue.is_empty() && self.sprite_sheet {
+                    ui.horizontal(|ui| {
+                        ui.label("Sprite sheet:");
+                        ui.text_edit_singleline(&mut self.sprite_sheet_path);
+                        if ui.button("Browse").clicked() {
+                            let format = self.options.format;
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter(
+                                    &format!("{} files", format.name()),
+                                    &[format.extension()],
+                                )
+                                .save_file()
+                            {
+                                self.sprite_sheet_path = path.display().to_string();
+                            }
+                        }
+                    });
+                } else if !self.queue.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label("Output folder:");
+                        ui.text_edit_singleline(&mut self.output_dir)
+                            .on_hover_text(format!(
+                                "Leave empty to write next to each input. Tokens: {}",
+                                template::TOKENS
+                            ));
+                        if ui.button("Browse").clicked() {
+                            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                self.output_dir = dir.display().to_string();
+                            }
+                        }
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label("Output:");
+                        let mut output_changed = ui
+                            .text_edit_singleline(&mut self.output_path)
+                            .on_hover_text(format!("Tokens: {}", template::TOKENS))
+                            .changed();
+                        if ui.button("Browse").clicked() {
+                            let format = self.options.format;
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter(
+                                    &format!("{} files", format.name()),
+                                    &[format.extension()],
+                                )
+                                .save_file()
+                            {
+                                self.output_path = path.display().to_string();
+                                output_changed = true;
+                            }
+                        }
+                        if output_changed {
+                            if let Some(format) = OutputFormat::from_path(&self.output_path) {
+                                self.options.format = format;
+                            }
+                        }
+                    });
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Preset:");
+                    let mut selected = None;
+                    egui::ComboBox::from_id_source("preset")
+                        .selected_text("Apply…")
+                        .show_ui(ui, |ui| {
+                            for preset in &self.presets {
+                                if ui.selectable_label(false, &preset.name).clicked() {
+                                    selected = Some(preset.clone());
+                                }
+                            }
+                        });
+                    if let Some(preset) = selected {
+                        self.apply_preset(preset);
+                    }
+                    ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("Name"));
+                    if ui.button("Save preset").clicked() {
+                        self.save_preset();
+                    }
+                    let exists = self
+                        .presets
+                        .iter()
+                        .any(|preset| preset.name == self.preset_name.trim());
+                    if ui
+                        .add_enabled(exists, egui::Button::new("Delete"))
+                        .clicked()
+                    {
+                        self.delete_preset();
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    let mut format = self.options.format;
+                    egui::ComboBox::from_id_source("format")
+                        .selected_text(format.name())
+                        .show_ui(ui, |ui| {
+                            for option in OutputFormat::ALL {
+                                ui.selectable_value(&mut format, option, option.name());
+                            }
+                        });
+                    if format != self.options.format {
+                        self.set_format(format);
+                    }
+
+                    ui.label("Package:").on_hover_text(
+                        "Write a folder of platform assets, named after the output file",
+                    );
+                    let package = &mut self.options.package;
+                    egui::ComboBox::from_id_source("package")
+                        .selected_text(package.map_or("None", Package::name))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(package, None, "None");
+                            for option in Package::ALL {
+                                ui.selectable_value(package, Some(option), option.name());
+                            }
+                        });
+                });
+
+                if let Some(package @ (Package::Android | Package::Ios)) = self.options.package {
+                    let package_options = &mut self.options.package_options;
+                    ui.horizontal(|ui| {
+                        ui.label("Base size:").on_hover_text(
+                            "Longer side of the 1x image; empty uses the scale or size set below",
+                        );
+                        optional_number_edit(ui, &mut package_options.base_size, 10_000);
+                        if package == Package::Android {
+                            ui.label("dp");
+                            ui.checkbox(
+                                &mut package_options.android_nodpi,
+                                "Also write drawable-nodpi",
+                            );
+                        } else {
+                            ui.label("pt");
+                        }
+                    });
+                }
+
+                match self.options.format {
+                    OutputFormat::Png => {
+                        ui.horizontal(|ui| {
+                            ui.label("Compression:");
+                            egui::ComboBox::from_id_source("png_compression")
+                                .selected_text(self.options.png_compression.name())
+                                .show_ui(ui, |ui| {
+                                    for option in PngCompression::ALL {
+                                        ui.selectable_value(
+                                            &mut self.options.png_compression,
+                                            option,
+                                            option.name(),
+                                        );
+                                    }
+                                });
+                            ui.label("Filter:");
+                            egui::ComboBox::from_id_source("png_filter")
+                                .selected_text(self.options.png_filter.name())
+                                .show_ui(ui, |ui| {
+                                    for option in PngFilter::ALL {
+                                        ui.selectable_value(
+                                            &mut self.options.png_filter,
+                                            option,
+                                            option.name(),
+                                        );
+                                    }
+                                });
+                            ui.checkbox(&mut self.options.png_interlaced, "Interlaced (Adam7)");
+                        });
+                        ui.horizontal(|ui| {
+                            let mut indexed = self.options.png_palette_size.is_some();
+                            let mut colors = self.options.png_palette_size.unwrap_or(256);
+                            ui.checkbox(&mut indexed, "Reduce colors to");
+                            ui.add_enabled_ui(indexed, |ui| {
+                                ui.add(egui::DragValue::new(&mut colors).clamp_range(2..=256));
+                                ui.checkbox(&mut self.options.png_dither, "Dither");
+                            });
+                            self.options.png_palette_size = indexed.then_some(colors);
+                        });
+                        ui.checkbox(&mut self.options.png_optimize, "Crush output")
+                            .on_hover_text(
+                                "Recompress with oxipng and zopfli for the smallest file. Slow.",
+                            );
+                    }
+                    OutputFormat::Tiff
+                    | OutputFormat::Bmp
+                    | OutputFormat::Icns
+                    | OutputFormat::Pdf
+                    | OutputFormat::VectorDrawable => {}
+                    OutputFormat::Gif
+                    | OutputFormat::Apng
+                    | OutputFormat::PngSequence
+                    | OutputFormat::Mp4
+                    | OutputFormat::Webm => {
+                        let animation = &mut self.options.animation;
+                        ui.horizontal(|ui| {
+                            ui.label("FPS:");
+                            ui.add(
+                                egui::DragValue::new(&mut animation.fps).clamp_range(1.0..=100.0),
+                            );
+                            let mut fixed = animation.duration.is_some();
+                            let mut seconds = animation.duration.unwrap_or(1.0);
+                            ui.checkbox(&mut fixed, "Duration:").on_hover_text(
+                                "Seconds to record; otherwise until the animation ends or loops",
+                            );
+                            ui.add_enabled_ui(fixed, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut seconds)
+                                        .clamp_range(0.1..=600.0)
+                                        .speed(0.1)
+                                        .suffix(" s"),
+                                )
+                            });
+                            animation.duration = fixed.then_some(seconds);
+                        });
+                        if matches!(self.options.format, OutputFormat::Mp4 | OutputFormat::Webm) {
+                            ui.horizontal(|ui| {
+                                let mut fixed = animation.bitrate.is_some();
+                                let mut kbps = animation.bitrate.unwrap_or(2000);
+                                ui.checkbox(&mut fixed, "Bitrate:").on_hover_text(
+                                    "Otherwise ffmpeg picks it for quality. Needs ffmpeg on PATH.",
+                                );
+                                ui.add_enabled_ui(fixed, |ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut kbps)
+                                            .clamp_range(100..=100_000)
+                                            .suffix(" kbit/s"),
+                                    )
+                                });
+                                animation.bitrate = fixed.then_some(kbps);
+                            });
+                        }
+                    }
+                    OutputFormat::Svg => {
+                        ui.checkbox(
+                            &mut self.options.outline_text_only,
+                            "Only outline text, keep the rest of the markup",
+                        );
+                    }
+                    OutputFormat::Jpeg => {
+                        ui.horizontal(|ui| {
+                            ui.label("Quality:");
+                            ui.add(egui::Slider::new(&mut self.options.quality, 1..=100));
+                        });
+                    }
+                    OutputFormat::Webp => {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.options.webp_lossless, "Lossless");
+                            ui.add_enabled_ui(!self.options.webp_lossless, |ui| {
+                                ui.label("Quality:");
+                                ui.add(egui::Slider::new(&mut self.options.quality, 1..=100));
+                            });
+                        });
+                    }
+                    OutputFormat::Avif => {
+                        ui.horizontal(|ui| {
+                            ui.label("Quality:");
+                            ui.add(egui::Slider::new(&mut self.options.quality, 1..=100));
+                            ui.label("Speed:");
+                            ui.add(egui::Slider::new(&mut self.options.avif_speed, 1..=10));
+                        });
+                    }
+                    OutputFormat::Ico => {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Sizes:");
+                            for size in ICO_SIZES {
+                                let mut selected = self.options.ico_sizes.contains(&size);
+                                if ui.checkbox(&mut selected, size.to_string()).changed() {
+                                    if selected {
+                                        self.options.ico_sizes.push(size);
+                                        self.options.ico_sizes.sort_unstable();
+                                    } else {
+                                        self.options.ico_sizes.retain(|&s| s != size);
+                                    }
+                                }
+                            }
+                        });
+                    }
+                }
+                ui.checkbox(&mut self.options.data_uri, "Also write data URIs")
+                    .on_hover_text("Save each output's data: URI to NAME.EXT.txt beside it");
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label("Scale:");
+                    ui.set_enabled(
+                        self.options.width.is_none()
+                            && self.options.height.is_none()
+                            && !self.options.uses_print_size(),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.options.scale, 0.1..=64.0)
+                            .logarithmic(true)
+                            .max_decimals(2)
+                            .suffix("x"),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Width:");
+                    optional_number_edit(ui, &mut self.options.width, u16::MAX as u32);
+                    ui.label("Height:");
+                    optional_number_edit(ui, &mut self.options.height, u16::MAX as u32);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Variants:");
+                    for factor in DENSITY_VARIANTS {
+                        let mut selected = self.options.density_variants.contains(&factor);
+                        if ui
+                            .checkbox(&mut selected, format!("@{}x", factor))
+                            .changed()
+                        {
+                            if selected {
+                                self.options.density_variants.push(factor);
+                                self.options.density_variants.sort_by(f32::total_cmp);
+                            } else {
+                                self.options.density_variants.retain(|&f| f != factor);
+                            }
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Snippet:").on_hover_text(
+                        "Also write markup referencing the variants, named after the output file",
+                    );
+                    let snippet = &mut self.options.snippet;
+                    egui::ComboBox::from_id_source("snippet")
+                        .selected_text(snippet.map_or("None", Snippet::name))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(snippet, None, "None");
+                            for option in Snippet::ALL {
+                                ui.selectable_value(snippet, Some(option), option.name());
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    let mut fill = self.options.background.is_some();
+                    let mut color = self.options.background.unwrap_or([255, 255, 255]);
+                    ui.checkbox(&mut fill, "Background:");
+                    ui.add_enabled_ui(fill, |ui| ui.color_edit_button_srgb(&mut color));
+                    self.options.background = fill.then_some(color);
+                });
+
+                egui::CollapsingHeader::new("Canvas").show(ui, |ui| {
+                    let canvas = &mut self.options.canvas;
+                    ui.horizontal(|ui| {
+                        ui.label("Element id:")
+                            .on_hover_text("Render only this element, cropped to its bounds");
+                        optional_text_edit(ui, &mut canvas.element, "whole drawing");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut canvas.split_layers, "Export each layer")
+                            .on_hover_text(
+                                "Write one image per top-level group, named after its id. \
+                                 Add {id} to the output path to place it yourself.",
+                            );
+                        ui.add_enabled_ui(canvas.split_layers, |ui| {
+                            optional_text_edit(ui, &mut canvas.layer_pattern, "top-level groups");
+                        })
+                        .response
+                        .on_hover_text("Only ids matching this pattern, e.g. icon-*");
+                    });
+                    ui.horizontal(|ui| {
+                        let mut enabled = canvas.crop.is_some();
+                        let mut crop = canvas.crop.unwrap_or([0.0, 0.0, 100.0, 100.0]);
+                        ui.checkbox(&mut enabled, "Crop region:").on_hover_text(
+                            "Render only this part of the drawing, in SVG user units",
+                        );
+                        ui.add_enabled_ui(enabled, |ui| {
+                            for (label, value) in ["x", "y", "w", "h"].into_iter().zip(&mut crop) {
+                                ui.label(label);
+                                ui.add(egui::DragValue::new(value).speed(1.0));
+                            }
+                        });
+                        crop[2] = crop[2].max(1.0);
+                        crop[3] = crop[3].max(1.0);
+                        canvas.crop = enabled.then_some(crop);
+                    });
+                    ui.checkbox(&mut canvas.trim, "Trim to content")
+                        .on_hover_text("Crop away empty canvas around the drawing");
+                    ui.checkbox(&mut canvas.square, "Pad to square")
+                        .on_hover_text("Center the artwork on a square canvas, e.g. for app icons");
+                    ui.horizontal(|ui| {
+                        ui.label("Padding:");
+                        ui.add(
+                            egui::DragValue::new(&mut canvas.padding).clamp_range(0.0..=10_000.0),
+                        )
+                        .on_hover_text(
+                            "Space around the artwork, in the background color if one is set",
+                        );
+                        egui::ComboBox::from_id_source("padding_unit")
+                            .width(40.0)
+                            .selected_text(canvas.padding_unit.name())
+                            .show_ui(ui, |ui| {
+                                for unit in [PaddingUnit::Pixels, PaddingUnit::Percent] {
+                                    ui.selectable_value(
+                                        &mut canvas.padding_unit,
+                                        unit,
+                                        unit.name(),
+                                    );
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        let mut enabled = canvas.tile.is_some();
+                        let [mut columns, mut rows] = canvas.tile.unwrap_or([2, 2]);
+                        ui.checkbox(&mut enabled, "Tile:").on_hover_text(
+                            "Repeat the image in a grid, e.g. for background textures",
+                        );
+                        ui.add_enabled_ui(enabled, |ui| {
+                            ui.add(egui::DragValue::new(&mut columns).clamp_range(1..=64));
+                            ui.label("x");
+                            ui.add(egui::DragValue::new(&mut rows).clamp_range(1..=64));
+                            ui.label("Gap:");
+                            ui.add(
+                                egui::DragValue::new(&mut canvas.tile_gap)
+                                    .clamp_range(0..=1000)
+                                    .suffix(" px"),
+                            );
+                        });
+                        canvas.tile = enabled.then_some([columns, rows]);
+                    });
+                });
+
+                egui::CollapsingHeader::new("Print size").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Width:");
+                        optional_number_edit(ui, &mut self.options.print_width, 10_000.0);
+                        ui.label("Height:");
+                        optional_number_edit(ui, &mut self.options.print_height, 10_000.0);
+                        egui::ComboBox::from_id_source("print_unit")
+                            .width(50.0)
+                            .selected_text(self.options.print_unit.name())
+                            .show_ui(ui, |ui| {
+                                for unit in [LengthUnit::Millimeters, LengthUnit::Inches] {
+                                    ui.selectable_value(
+                                        &mut self.options.print_unit,
+                                        unit,
+                                        unit.name(),
+                                    );
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("DPI:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.options.dpi).clamp_range(1.0..=4800.0),
+                        );
+                        ui.add_enabled(
+                            !self.options.uses_print_size(),
+                            egui::Checkbox::new(&mut self.options.embed_dpi, "Embed in output"),
+                        )
+                        .on_hover_text(
+                            "Record the DPI so apps like Word or InDesign place the image at \
+                             its physical size. Always on when a print size is set.",
+                        );
+                    });
+                });
+
+                egui::CollapsingHeader::new("Colors").show(ui, |ui| {
+                    let colors = &mut self.options.colors;
+                    ui.horizontal(|ui| {
+                        let mut enabled = colors.current_color.is_some();
+                        let mut color = colors.current_color.unwrap_or([0, 0, 0]);
+                        ui.checkbox(&mut enabled, "currentColor:").on_hover_text(
+                            "Color used by fill=\"currentColor\" and similar, common in icon \
+                             sets; black when off",
+                        );
+                        ui.add_enabled_ui(enabled, |ui| ui.color_edit_button_srgb(&mut color));
+                        colors.current_color = enabled.then_some(color);
+                    });
+                    let replace = &mut colors.replace;
+                    let mut removed = None;
+                    for (i, (from, to)) in replace.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgb(from);
+                            ui.label("→");
+                            ui.color_edit_button_srgb(to);
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = removed {
+                        replace.remove(i);
+                    }
+                    if ui
+                        .button("Add replacement")
+                        .on_hover_text("Swap one exact fill, stroke or gradient color for another")
+                        .clicked()
+                    {
+                        replace.push(([0, 0, 0], [0, 0, 0]));
+                    }
+                });
+
+                egui::CollapsingHeader::new("Effects").show(ui, |ui| {
+                    let effects = &mut self.options.effects;
+                    ui.horizontal(|ui| {
+                        let mut enabled = effects.grayscale.is_some();
+                        let mut weights = effects.grayscale.unwrap_or(LumaWeights::Rec709);
+                        ui.checkbox(&mut enabled, "Grayscale");
+                        ui.add_enabled_ui(enabled, |ui| {
+                            egui::ComboBox::from_id_source("luma_weights")
+                                .selected_text(weights.name())
+                                .show_ui(ui, |ui| {
+                                    for option in LumaWeights::ALL {
+                                        ui.selectable_value(&mut weights, option, option.name());
+                                    }
+                                });
+                        });
+                        effects.grayscale = enabled.then_some(weights);
+                    });
+
+                    ui.checkbox(&mut effects.invert, "Invert colors")
+                        .on_hover_text("Keeps transparency; handy for dark-mode diagrams");
+
+                    ui.horizontal(|ui| {
+                        let mut enabled = effects.tint.is_some();
+                        let mut color = effects.tint.unwrap_or([0, 0, 0]);
+                        ui.checkbox(&mut enabled, "Tint:").on_hover_text(
+                            "Paint everything in one color, keeping only the shape, for \
+                             template or mask-style icons",
+                        );
+                        ui.add_enabled_ui(enabled, |ui| ui.color_edit_button_srgb(&mut color));
+                        effects.tint = enabled.then_some(color);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Opacity:");
+                        let mut percent = effects.opacity * 100.0;
+                        ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).suffix("%"))
+                            .on_hover_text("Fade the whole output, e.g. for disabled icon states");
+                        effects.opacity = percent / 100.0;
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Watermark:")
+                            .on_hover_text("A PNG or SVG composited over the output");
+                        if ui.button("Choose…").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Images", &["png", "svg", "svgz"])
+                                .pick_file()
+                            {
+                                effects.watermark.get_or_insert_with(Default::default).path = path;
+                            }
+                        }
+                        if let Some(watermark) = &effects.watermark {
+                            ui.label(watermark.path.display().to_string());
+                            if ui.small_button("Remove").clicked() {
+                                effects.watermark = None;
+                            }
+                        }
+                    });
+                    if let Some(watermark) = &mut effects.watermark {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("watermark_corner")
+                                .selected_text(watermark.corner.name())
+                                .show_ui(ui, |ui| {
+                                    for corner in Corner::ALL {
+                                        ui.selectable_value(
+                                            &mut watermark.corner,
+                                            corner,
+                                            corner.name(),
+                                        );
+                                    }
+                                });
+                            ui.label("Width:");
+                            let mut percent = watermark.scale * 100.0;
+                            ui.add(egui::Slider::new(&mut percent, 1.0..=100.0).suffix("%"));
+                            watermark.scale = percent / 100.0;
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Opacity:");
+                            let mut percent = watermark.opacity * 100.0;
+                            ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).suffix("%"));
+                            watermark.opacity = percent / 100.0;
+                        });
+                    }
+                });
+
+                egui::CollapsingHeader::new("Metadata").show(ui, |ui| {
+                    let metadata = &mut self.options.metadata;
+                    egui::Grid::new("metadata").show(ui, |ui| {
+                        for (label, value) in [
+                            ("Title:", &mut metadata.title),
+                            ("Author:", &mut metadata.author),
+                            ("Copyright:", &mut metadata.copyright),
+                        ] {
+                            ui.label(label);
+                            optional_text_edit(ui, value, "none");
+                            ui.end_row();
+                        }
+                    });
+                    ui.checkbox(&mut metadata.source_filename, "Include source file name");
+                    ui.label(egui::RichText::new("Written to PNG output only.").weak());
+
+                    ui.horizontal(|ui| {
+                        let profile = &mut self.options.icc_profile;
+                        ui.label("Color profile:")
+                            .on_hover_text("ICC profile embedded in PNG and JPEG output");
+                        let selected = match profile {
+                            None => "None",
+                            Some(IccProfile::Srgb) => "sRGB",
+                            Some(IccProfile::File(_)) => "File",
+                        };
+                        egui::ComboBox::from_id_source("icc_profile")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui.selectable_label(profile.is_none(), "None").clicked() {
+                                    *profile = None;
+                                }
+                                let srgb = matches!(profile, Some(IccProfile::Srgb));
+                                if ui.selectable_label(srgb, "sRGB").clicked() {
+                                    *profile = Some(IccProfile::Srgb);
+                                }
+                                let file = matches!(profile, Some(IccProfile::File(_)));
+                                if ui.selectable_label(file, "File…").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("ICC profiles", &["icc", "icm"])
+                                        .pick_file()
+                                    {
+                                        *profile = Some(IccProfile::File(path));
+                                    }
+                                }
+                            });
+                        if let Some(IccProfile::File(path)) = profile {
+                            ui.label(path.display().to_string());
+                        }
+                    });
+                });
+
+                egui::CollapsingHeader::new("Fonts").show(ui, |ui| {
+                    ui.checkbox(&mut self.options.fonts.system, "Use system fonts")
+                        .on_hover_text(
+                            "Render <text> with installed fonts. Turn off for output that is \
+                             identical on every machine.",
+                        );
+                    let mut removed = None;
+                    for (i, path) in self.options.fonts.paths.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(path.display().to_string());
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = removed {
+                        self.options.fonts.paths.remove(i);
+                    }
+                    egui::Grid::new("font_families").show(ui, |ui| {
+                        let fonts = &mut self.options.fonts;
+                        for (label, family) in [
+                            ("Default:", &mut fonts.default_family),
+                            ("Serif:", &mut fonts.serif),
+                            ("Sans-serif:", &mut fonts.sans_serif),
+                            ("Monospace:", &mut fonts.monospace),
+                            ("Cursive:", &mut fonts.cursive),
+                        ] {
+                            ui.label(label);
+                            optional_text_edit(ui, family, "automatic");
+                            ui.end_row();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Add font files…").clicked() {
+                            if let Some(paths) = rfd::FileDialog::new()
+                                .add_filter("Fonts", &["ttf", "otf", "ttc", "otc"])
+                                .pick_files()
+                            {
+                                self.options.fonts.paths.extend(paths);
+                            }
+                        }
+                        if ui.button("Add font folder…").clicked() {
+                            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                self.options.fonts.paths.push(dir);
+                            }
+                        }
+                    });
+                });
+
+                egui::CollapsingHeader::new("Advanced").show(ui, |ui| {
+                    ui.checkbox(&mut self.options.linear_light, "Linear-light anti-aliasing")
+                        .on_hover_text(
+                            "Supersample and blend edges in linear light. Cleaner on \
+                             high-contrast edges, slower to render.",
+                        );
+                    let parse = &mut self.options.parse;
+                    ui.checkbox(&mut parse.safe_mode, "Safe mode")
+                        .on_hover_text("Refuse SVGs that reference external files or URLs");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut parse.sanitize, "Sanitize").on_hover_text(
+                            "Strip scripts, event handlers and foreignObject before converting",
+                        );
+                        ui.add_enabled(
+                            parse.sanitize,
+                            egui::Checkbox::new(&mut parse.write_sanitized, "Save sanitized SVG"),
+                        )
+                        .on_hover_text("Write name.sanitized.svg next to the output");
+                    });
+                    egui::Grid::new("parse_options").show(ui, |ui| {
+                        ui.label("Resources folder:");
+                        ui.horizontal(|ui| {
+                            let mut dir = parse
+                                .resources_dir
+                                .as_ref()
+                                .map(|dir| dir.display().to_string())
+                                .unwrap_or_default();
+                            if ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut dir)
+                                        .hint_text("Input file's folder"),
+                                )
+                                .on_hover_text("Where relative image hrefs are looked up")
+                                .changed()
+                            {
+                                parse.resources_dir =
+                                    (!dir.trim().is_empty()).then(|| PathBuf::from(dir));
+                            }
+                            if ui.button("Browse").clicked() {
+                                if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                    parse.resources_dir = Some(dir);
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("SVG DPI:");
+                        ui.add(egui::DragValue::new(&mut parse.dpi).clamp_range(1.0..=4800.0))
+                            .on_hover_text("Resolution for mm, in and pt units inside the SVG");
+                        ui.end_row();
+
+                        ui.label("Languages:");
+                        let mut languages = parse.languages.join(",");
+                        if ui
+                            .text_edit_singleline(&mut languages)
+                            .on_hover_text("Matched against systemLanguage, e.g. en,de")
+                            .changed()
+                        {
+                            parse.languages = languages
+                                .split(',')
+                                .map(|language| language.trim().to_string())
+                                .filter(|language| !language.is_empty())
+                                .collect();
+                        }
+                        ui.end_row();
+
+                        ui.label("Shape rendering:");
+                        egui::ComboBox::from_id_source("shape_rendering")
+                            .selected_text(parse.shape_rendering.name())
+                            .show_ui(ui, |ui| {
+                                for mode in ShapeRendering::ALL {
+                                    ui.selectable_value(
+                                        &mut parse.shape_rendering,
+                                        mode,
+                                        mode.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Text rendering:");
+                        egui::ComboBox::from_id_source("text_rendering")
+                            .selected_text(parse.text_rendering.name())
+                            .show_ui(ui, |ui| {
+                                for mode in TextRendering::ALL {
+                                    ui.selectable_value(
+                                        &mut parse.text_rendering,
+                                        mode,
+                                        mode.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Image rendering:");
+                        egui::ComboBox::from_id_source("image_rendering")
+                            .selected_text(parse.image_rendering.name())
+                            .show_ui(ui, |ui| {
+                                for mode in ImageRendering::ALL {
+                                    ui.selectable_value(
+                                        &mut parse.image_rendering,
+                                        mode,
+                                        mode.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Default size:");
+                        ui.horizontal(|ui| {
+                            let (width, height) = &mut parse.default_size;
+                            ui.add(egui::DragValue::new(width).clamp_range(1.0..=100_000.0));
+                            ui.label("x");
+                            ui.add(egui::DragValue::new(height).clamp_range(1.0..=100_000.0));
+                        })
+                        .response
+                        .on_hover_text("Used when the SVG has no viewBox, width or height");
+                        ui.end_row();
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                if let Some((original_width, original_height)) = self.original_dimensions {
+                    ui.label(format!(
+                        "Original size: {}x{}",
+                        original_width, original_height
+                    ));
+                }
+
+                if let Some((will_be_width, will_be_height)) = self.scaled_dimensions {
+                    ui.label(format!(
+                        "Will be size: {}x{}",
+                        will_be_width, will_be_height
+                    ));
+                    show_memory_estimate(ui, (will_be_width, will_be_height), &self.options);
+                }
+
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    let running = self.conversion.is_some();
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Convert"))
+                        .clicked()
+                    {
+                        if self.queue.is_empty() {
+                            self.convert_single(ctx, true);
+                        } else {
+                            self.convert_queue(ctx);
+                        }
+                    }
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Copy PNG to clipboard"))
+                        .clicked()
+                    {
+                        self.status_message = match self.copy_to_clipboard() {
+                            Ok(()) => format!("Copied {} to the clipboard", self.input_label()),
+                            Err(e) => format!("Error: {}", e),
+                        };
+                    }
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Copy data URI"))
+                        .on_hover_text("Copy the output as a data: URI for inlining in HTML or CSS")
+                        .clicked()
+                    {
+                        self.status_message = match self.copy_data_uri() {
+                            Ok(()) => format!(
+                                "Copied {} to the clipboard as a {} data URI",
+                                self.input_label(),
+                                self.options.format.name()
+                            ),
+                            Err(e) => format!("Error: {}", e),
+                        };
+                    }
+                    if let Some(conversion) = &self.conversion {
+                        ui.spinner();
+                        if ui
+                            .add_enabled(
+                                !conversion.worker.is_cancelled(),
+                                egui::Button::new("Cancel"),
+                            )
+                            .clicked()
+                        {
+                            conversion.worker.cancel();
+                            self.status_message = String::from("Cancelling…");
+                        }
+                    }
+                    ui.checkbox(&mut self.watch, "Watch")
+                        .on_hover_text("Re-convert the input whenever it is saved");
+                });
+
+                if let Some(conversion) = &self.conversion {
+                    ui.add_space(5.0);
+                    show_progress(ui, conversion);
+                }
+
+                ui.add_space(5.0);
+                ui.label(&self.status_message);
+                if let Some(output) = self.last_output.clone() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Open").clicked() {
+                            if let Err(e) = platform::open(&output) {
+                                self.status_message = format!("Error opening {}: {}", output, e);
+                            }
+                        }
+                        if ui
+                            .button("Reveal in file manager")
+                            .on_hover_text("Show the output in its folder")
+                            .clicked()
+                        {
+                            if let Err(e) = platform::reveal(&output) {
+                                self.status_message = format!("Error revealing {}: {}", output, e);
+                            }
+                        }
+                    });
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,259 @@
+use crate::convert::{load_tree, render_tree, ConversionOptions, CANCELLED};
+use crate::encode::{self, OutputFormat};
+use crate::template::input_name;
+use rayon::prelude::*;
+use resvg::tiny_skia::{Pixmap, PixmapPaint, Transform};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// File written next to a sprite sheet, describing where each sprite sits in it.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum SpriteData {
+    /// Sprite names with their rectangles.
+    Json,
+    /// A `.sprite` class plus one `.sprite-{name}` class per sprite.
+    Css,
+}
+
+impl SpriteData {
+    pub const ALL: [SpriteData; 2] = [SpriteData::Json, SpriteData::Css];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SpriteData::Json => "JSON",
+            SpriteData::Css => "CSS",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SpriteData::Json => "json",
+            SpriteData::Css => "css",
+        }
+    }
+}
+
+/// How a set of SVGs is packed into one sprite sheet.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AtlasOptions {
+    pub data: SpriteData,
+    /// Transparent pixels between neighbouring sprites, so filtering doesn't bleed.
+    pub gap: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            data: SpriteData::Json,
+            gap: 2,
+        }
+    }
+}
+
+/// Where one input ended up in the sheet, in pixels.
+#[derive(serde::Serialize)]
+struct Sprite {
+    name: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(serde::Serialize)]
+struct SheetData<'a> {
+    image: &'a str,
+    width: u32,
+    height: u32,
+    sprites: &'a [Sprite],
+}
+
+/// Renders every input with `options` and packs them into one image at `output_path`, with
+/// the sprite rectangles written beside it in `atlas.data` format. Returns the written paths.
+pub fn build_atlas(
+    inputs: &[String],
+    output_path: &str,
+    options: &ConversionOptions,
+    atlas: &AtlasOptions,
+    cancel: &AtomicBool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if matches!(
+        options.format,
+        OutputFormat::Ico
+            | OutputFormat::Icns
+            | OutputFormat::Pdf
+            | OutputFormat::VectorDrawable
+            | OutputFormat::Svg
+    ) {
+        return Err(format!(
+            "Sprite sheets can't be written as {}",
+            options.format.name()
+        )
+        .into());
+    }
+    if inputs.is_empty() {
+        return Err("No SVGs to pack into a sprite sheet".into());
+    }
+
+    let pixmaps = inputs
+        .par_iter()
+        .map(|input| {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(CANCELLED.to_string());
+            }
+            load_tree(input, options)
+                .and_then(|rtree| render_tree(&rtree, options))
+                .map_err(|e| format!("{}: {}", input, e))
+        })
+        .collect::<Result<Vec<Pixmap>, String>>()?;
+
+    let sizes: Vec<(u32, u32)> = pixmaps.iter().map(|p| (p.width(), p.height())).collect();
+    let (positions, (width, height)) = pack(&sizes, atlas.gap);
+    let mut sheet = Pixmap::new(width, height).ok_or("Sprite sheet is too large")?;
+    for (pixmap, &(x, y)) in pixmaps.iter().zip(&positions) {
+        sheet.draw_pixmap(
+            x as i32,
+            y as i32,
+            pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+    if cancel.load(Ordering::Relaxed) {
+        return Err(CANCELLED.into());
+    }
+
+    let sprites = sprite_names(inputs)
+        .into_iter()
+        .zip(positions.iter().zip(&sizes))
+        .map(|(name, (&(x, y), &(width, height)))| Sprite {
+            name,
+            x,
+            y,
+            width,
+            height,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(parent) = Path::new(output_path).parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    encode::save(&sheet, "", output_path, options)?;
+
+    let image = Path::new(output_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let data_path = Path::new(output_path)
+        .with_extension(atlas.data.extension())
+        .display()
+        .to_string();
+    let data = match atlas.data {
+        SpriteData::Json => {
+            serde_json::to_string_pretty(&SheetData {
+                image: &image,
+                width,
+                height,
+                sprites: &sprites,
+            })? + "\n"
+        }
+        SpriteData::Css => sprite_css(&image, &sprites),
+    };
+    std::fs::write(&data_path, data)?;
+
+    Ok(vec![output_path.to_string(), data_path])
+}
+
+/// Shelf-packs rectangles into a roughly square sheet, tallest first. Returns each
+/// rectangle's position, in input order, and the sheet size.
+fn pack(sizes: &[(u32, u32)], gap: u32) -> (Vec<(u32, u32)>, (u32, u32)) {
+    let area: u64 = sizes
+        .iter()
+        .map(|&(w, h)| (w + gap) as u64 * (h + gap) as u64)
+        .sum();
+    let widest = sizes.iter().map(|&(w, _)| w).max().unwrap_or(0);
+    let target_width = widest.max((area as f64).sqrt().ceil() as u32);
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height, mut width) = (0, 0, 0, 0);
+    for i in order {
+        let (w, h) = sizes[i];
+        if x > 0 && x + w > target_width {
+            y += shelf_height + gap;
+            x = 0;
+            shelf_height = 0;
+        }
+        positions[i] = (x, y);
+        width = width.max(x + w);
+        shelf_height = shelf_height.max(h);
+        x += w + gap;
+    }
+    (positions, (width.max(1), (y + shelf_height).max(1)))
+}
+
+/// Sprite names from the input file names, numbered when several share one.
+fn sprite_names(inputs: &[String]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    inputs
+        .iter()
+        .map(|input| {
+            let name = input_name(input);
+            let count = seen.entry(name.clone()).or_default();
+            *count += 1;
+            if *count == 1 {
+                name
+            } else {
+                format!("{}-{}", name, count)
+            }
+        })
+        .collect()
+}
+
+fn sprite_css(image: &str, sprites: &[Sprite]) -> String {
+    let mut css = format!(
+        ".sprite {{\n  background-image: url(\"{}\");\n  background-repeat: no-repeat;\n  \
+         display: inline-block;\n}}\n",
+        image.replace('"', "\\\"")
+    );
+    for sprite in sprites {
+        css.push_str(&format!(
+            "\n.sprite-{} {{\n  width: {}px;\n  height: {}px;\n  background-position: {} {};\n}}\n",
+            css_class(&sprite.name),
+            sprite.width,
+            sprite.height,
+            css_offset(sprite.x),
+            css_offset(sprite.y)
+        ));
+    }
+    css
+}
+
+/// `name` with anything that can't appear in a CSS class name replaced by `-`.
+pub fn css_class(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// A `background-position` component that moves the sheet `pixels` up or left.
+fn css_offset(pixels: u32) -> String {
+    if pixels == 0 {
+        String::from("0")
+    } else {
+        format!("-{}px", pixels)
+    }
+}
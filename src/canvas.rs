@@ -0,0 +1,268 @@
+use crate::convert::{self, ConversionOptions};
+use resvg::tiny_skia::{Color, Pixmap, PixmapPaint, Transform};
+use usvg::{Node, NodeExt, NodeKind, Rect, Size, Tree};
+
+/// What a padding amount is measured in.
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PaddingUnit {
+    /// Output pixels on each side.
+    Pixels,
+    /// Percent of the artwork's longer side, on each side.
+    Percent,
+}
+
+impl PaddingUnit {
+    pub fn name(self) -> &'static str {
+        match self {
+            PaddingUnit::Pixels => "px",
+            PaddingUnit::Percent => "%",
+        }
+    }
+}
+
+/// Changes to which region of the SVG is rendered. They edit the parsed tree's viewBox and
+/// size, so output sizes, previews and icons all follow; PDF output is unaffected.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CanvasOptions {
+    /// Render only the element with this id, cropped to its bounding box.
+    pub element: Option<String>,
+    /// Export each layer to its own file instead of rendering the whole drawing.
+    pub split_layers: bool,
+    /// Ids exported by `split_layers`, with `*` matching any characters; `None` exports the
+    /// top-level groups.
+    pub layer_pattern: Option<String>,
+    /// Region to render as `[x, y, width, height]` in user units, replacing the viewBox.
+    pub crop: Option<[f64; 4]>,
+    /// Shrink the canvas to the bounding box of the drawn content.
+    pub trim: bool,
+    /// Extend the shorter side so the artwork sits centered on a square canvas.
+    pub square: bool,
+    /// Empty space added around the artwork, filled with the background color if one is set.
+    /// Exact pixel sizes include the padding; scaled output grows by it.
+    pub padding: f32,
+    pub padding_unit: PaddingUnit,
+    /// Repeat the rendered image in a `[columns, rows]` grid, e.g. for background textures.
+    pub tile: Option<[u32; 2]>,
+    /// Pixels between tiles, in the background color if one is set.
+    pub tile_gap: u32,
+}
+
+impl Default for CanvasOptions {
+    fn default() -> Self {
+        Self {
+            element: None,
+            split_layers: false,
+            layer_pattern: None,
+            crop: None,
+            trim: false,
+            square: false,
+            padding: 0.0,
+            padding_unit: PaddingUnit::Pixels,
+            tile: None,
+            tile_gap: 0,
+        }
+    }
+}
+
+impl CanvasOptions {
+    /// Applies the canvas changes in `options` to a freshly parsed tree.
+    pub fn apply(
+        rtree: &mut Tree,
+        options: &ConversionOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let canvas = &options.canvas;
+        if let Some(id) = &canvas.element {
+            let node = rtree
+                .node_by_id(id)
+                .ok_or_else(|| format!("No element with id `{}`", id))?;
+            isolate(&node);
+            let bbox = node
+                .calculate_bbox()
+                .and_then(|bbox| bbox.to_rect())
+                .ok_or_else(|| format!("Element `{}` draws nothing", id))?;
+            set_view_box(rtree, bbox);
+        }
+        if let Some([x, y, width, height]) = canvas.crop {
+            if let Some(rect) = Rect::new(x, y, width, height) {
+                set_view_box(rtree, rect);
+            }
+        }
+        if canvas.trim {
+            if let Some(bbox) = content_bbox(rtree) {
+                set_view_box(rtree, bbox);
+            }
+        }
+        if canvas.square {
+            let (width, height) = (rtree.size.width(), rtree.size.height());
+            let side = width.max(height);
+            expand(rtree, (side - width) / 2.0, (side - height) / 2.0);
+        }
+        if canvas.padding > 0.0 {
+            let (x, y) = canvas.padding_size(rtree.size, options);
+            expand(rtree, x, y);
+        }
+        Ok(())
+    }
+
+    /// Output size once a `width`x`height` render is tiled, or why it can't be tiled.
+    pub fn tiled_size(&self, (width, height): (u32, u32)) -> Result<(u32, u32), String> {
+        let Some([columns, rows]) = self.tile else {
+            return Ok((width, height));
+        };
+        let side = |count: u32, length: u32| {
+            count
+                .checked_mul(length)?
+                .checked_add(count.saturating_sub(1).checked_mul(self.tile_gap)?)
+        };
+        match (side(columns, width), side(rows, height)) {
+            (Some(width), Some(height)) => Ok((width, height)),
+            _ => Err(format!(
+                "Tiling a {}×{} image {}×{} times is too large",
+                width, height, columns, rows
+            )),
+        }
+    }
+
+    /// Repeats `pixmap` in the `tile` grid, filling the gaps with `background`.
+    pub fn tile(
+        &self,
+        pixmap: Pixmap,
+        background: Option<[u8; 3]>,
+    ) -> Result<Pixmap, Box<dyn std::error::Error>> {
+        let Some([columns, rows]) = self.tile else {
+            return Ok(pixmap);
+        };
+        let (width, height) = self.tiled_size((pixmap.width(), pixmap.height()))?;
+        let mut tiled = convert::new_pixmap(width, height)?;
+        if let Some([r, g, b]) = background {
+            tiled.fill(Color::from_rgba8(r, g, b, 255));
+        }
+        for row in 0..rows {
+            for column in 0..columns {
+                tiled.draw_pixmap(
+                    (column * (pixmap.width() + self.tile_gap)) as i32,
+                    (row * (pixmap.height() + self.tile_gap)) as i32,
+                    pixmap.as_ref(),
+                    &PixmapPaint::default(),
+                    Transform::identity(),
+                    None,
+                );
+            }
+        }
+        Ok(tiled)
+    }
+
+    /// Horizontal and vertical padding in the tree's size units, chosen so it comes out at
+    /// the requested number of output pixels.
+    fn padding_size(&self, size: Size, options: &ConversionOptions) -> (f64, f64) {
+        let padding = self.padding as f64;
+        if self.padding_unit == PaddingUnit::Percent {
+            let side = padding / 100.0 * size.width().max(size.height());
+            return (side, side);
+        }
+
+        // Exact sizes keep their total, so the artwork gets what the padding leaves over.
+        let inner = |total: u32| (total as f64 - 2.0 * padding).max(1.0);
+        let (width, height) = options.target_size();
+        let (scale_x, scale_y) = match (width, height) {
+            (Some(w), Some(h)) => (inner(w) / size.width(), inner(h) / size.height()),
+            (Some(w), None) => (inner(w) / size.width(), inner(w) / size.width()),
+            (None, Some(h)) => (inner(h) / size.height(), inner(h) / size.height()),
+            (None, None) => (options.scale as f64, options.scale as f64),
+        };
+        (padding / scale_x, padding / scale_y)
+    }
+}
+
+/// Ids of the elements written by `split_layers`: the top-level groups, or every element
+/// whose id matches `pattern`.
+pub fn layer_ids(rtree: &Tree, pattern: Option<&str>) -> Vec<String> {
+    let candidates: Vec<Node> = match pattern {
+        Some(_) => rtree.root.descendants().collect(),
+        None => rtree
+            .root
+            .children()
+            .filter(|node| matches!(*node.borrow(), NodeKind::Group(_)))
+            .collect(),
+    };
+    candidates
+        .iter()
+        .map(|node| node.id().to_string())
+        .filter(|id| !id.is_empty() && pattern.is_none_or(|pattern| matches_pattern(pattern, id)))
+        .collect()
+}
+
+/// Whether `id` matches `pattern`, where `*` stands for any run of characters.
+fn matches_pattern(pattern: &str, id: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = id.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Detaches everything except `node`, its descendants and the groups containing it, whose
+/// transforms, opacity and clipping still apply.
+fn isolate(node: &Node) {
+    let mut kept = node.clone();
+    while let Some(parent) = kept.parent() {
+        let others: Vec<Node> = parent.children().filter(|child| *child != kept).collect();
+        for other in others {
+            other.detach();
+        }
+        kept = parent;
+    }
+}
+
+/// Bounding box of everything drawn inside the viewBox, strokes included, in user units.
+fn content_bbox(rtree: &Tree) -> Option<Rect> {
+    let bbox = rtree.root.calculate_bbox()?.to_rect()?;
+    let view_box = rtree.view_box.rect;
+    let (left, top) = (
+        bbox.left().max(view_box.left()),
+        bbox.top().max(view_box.top()),
+    );
+    let (right, bottom) = (
+        bbox.right().min(view_box.right()),
+        bbox.bottom().min(view_box.bottom()),
+    );
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+/// Renders `rect`, in user units, at the tree's current scale.
+fn set_view_box(rtree: &mut Tree, rect: Rect) {
+    let view_box = rtree.view_box.rect;
+    let scale_x = rtree.size.width() / view_box.width();
+    let scale_y = rtree.size.height() / view_box.height();
+    if let Some(size) = Size::new(rect.width() * scale_x, rect.height() * scale_y) {
+        rtree.size = size;
+        rtree.view_box.rect = rect;
+    }
+}
+
+/// Grows the canvas by `x` and `y`, in the tree's size units, on each side.
+fn expand(rtree: &mut Tree, x: f64, y: f64) {
+    let view_box = rtree.view_box.rect;
+    let units_x = view_box.width() / rtree.size.width();
+    let units_y = view_box.height() / rtree.size.height();
+    if let Some(rect) = Rect::new(
+        view_box.x() - x * units_x,
+        view_box.y() - y * units_y,
+        view_box.width() + 2.0 * x * units_x,
+        view_box.height() + 2.0 * y * units_y,
+    ) {
+        set_view_box(rtree, rect);
+    }
+}
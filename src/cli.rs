@@ -0,0 +1,948 @@
+use crate::config;
+use crate::preset::Preset;
+use crate::serve;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use svg_to_png::animation::AnimationOptions;
+use svg_to_png::atlas::{build_atlas, AtlasOptions, SpriteData};
+use svg_to_png::canvas::{CanvasOptions, PaddingUnit};
+use svg_to_png::color::ColorOptions;
+use svg_to_png::convert::{
+    batch_output_template, convert_batch, find_svgs, is_url, read_svg, BatchError, BatchJob,
+    BatchProgress, ConversionOptions, ErrorKind, FontOptions, LengthUnit, STDIN,
+};
+use svg_to_png::effects::{Corner, EffectOptions, LumaWeights, Watermark};
+use svg_to_png::encode::{MetadataOptions, OutputFormat, PngCompression, PngFilter};
+use svg_to_png::icc::IccProfile;
+use svg_to_png::icon::ICO_SIZES;
+use svg_to_png::info;
+use svg_to_png::package::{Package, PackageOptions};
+use svg_to_png::parsing::{ImageRendering, ParseOptions, ShapeRendering, TextRendering};
+use svg_to_png::snippet::Snippet;
+use svg_to_png::template;
+use svg_to_png::trace::{self, TraceMode, TraceOptions};
+use svg_to_png::Converter;
+
+/// Convert SVG files to PNG without opening the GUI.
+#[derive(Parser)]
+#[command(
+    name = "rust-svg-converter",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Exit codes: 0 success, 1 other errors, 2 invalid arguments, 3 an input \
+                  couldn't be parsed, 4 a file couldn't be read or written, 5 an image \
+                  couldn't be encoded, 6 only some inputs failed"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print errors to stderr as JSON objects, one per line, with the input, the kind of
+    /// error (parse, io, encode or other) and the message
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Input SVG files, folders or http(s) URLs; - reads the SVG from stdin
+    #[arg(required_unless_present = "serve")]
+    inputs: Vec<String>,
+
+    /// Serve POST /convert on ADDRESS (127.0.0.1:8080 if omitted) instead of converting
+    /// files. The body is the SVG, and the query may set scale, width, height, format and
+    /// background, e.g. /convert?format=webp&width=256; other options apply to every request.
+    /// Only still formats are served, up to 64 megapixels
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "127.0.0.1:8080")]
+    serve: Option<String>,
+
+    /// Descend into subfolders of input folders, mirroring them in the output folder
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Files converted in parallel; 0 uses every core
+    #[arg(short, long, default_value_t = 0, env = "SVGCONV_JOBS")]
+    jobs: usize,
+
+    /// Pack every input into one sprite sheet at --output, with a JSON or CSS file beside it
+    /// giving each sprite's position
+    #[arg(long, requires = "output")]
+    sprite_sheet: bool,
+
+    /// Format of the file describing the sprite sheet
+    #[arg(long, value_enum, default_value = "json", requires = "sprite_sheet")]
+    sprite_data: SpriteData,
+
+    /// Transparent pixels between sprites
+    #[arg(long, default_value_t = 2, requires = "sprite_sheet")]
+    sprite_gap: u32,
+
+    /// Trace raster inputs (PNG, JPEG, GIF, BMP, TIFF) into SVGs instead of rendering SVGs
+    #[arg(long, value_enum)]
+    trace: Option<TraceMode>,
+
+    /// With --trace bilevel, the brightness (0-255) below which pixels are traced
+    #[arg(long, default_value_t = 128, requires = "trace")]
+    threshold: u8,
+
+    /// With --trace color, the number of colors traced (2-256)
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(2..=256), requires = "trace")]
+    trace_colors: u16,
+
+    /// With --trace, drop shapes and holes of at most this many pixels
+    #[arg(long, default_value_t = 2, requires = "trace")]
+    speckle: u32,
+
+    /// With --trace, how readily corners are rounded, from 0 (never) to 1.34 (always)
+    #[arg(long, default_value_t = 1.0, requires = "trace")]
+    smoothness: f32,
+
+    /// Output image path, or output directory when converting several files.
+    /// May contain {name}, {id}, {scale}, {width}, {height}, {date} and {ext} tokens.
+    /// - writes the image to stdout, which is the default when reading from stdin
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Folder to write outputs to instead of next to each input; --output takes precedence
+    #[arg(long, value_name = "DIR", env = "SVGCONV_OUTPUT_DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Scale factor applied to the SVG's size, e.g. 0.5 or 1.5
+    #[arg(short, long, default_value_t = 1.0, value_parser = parse_scale, env = "SVGCONV_SCALE")]
+    scale: f32,
+
+    /// Exact output width in pixels; the height follows the aspect ratio unless also given
+    #[arg(long, env = "SVGCONV_WIDTH")]
+    width: Option<u32>,
+
+    /// Exact output height in pixels; the width follows the aspect ratio unless also given
+    #[arg(long, env = "SVGCONV_HEIGHT")]
+    height: Option<u32>,
+
+    /// Printed width in --unit, sized in pixels at --dpi
+    #[arg(long)]
+    print_width: Option<f32>,
+
+    /// Printed height in --unit, sized in pixels at --dpi
+    #[arg(long)]
+    print_height: Option<f32>,
+
+    /// Unit for --print-width and --print-height
+    #[arg(long, value_enum, default_value = "mm")]
+    unit: LengthUnit,
+
+    /// Resolution for print sizes, embedded in PNG and JPEG output when printing
+    #[arg(long, default_value_t = 300.0)]
+    dpi: f32,
+
+    /// Embed --dpi in PNG and JPEG output even without a print size
+    #[arg(long)]
+    embed_dpi: bool,
+
+    /// Output format; inferred from the output extension when omitted
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Quality for lossy formats (1-100)
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100), env = "SVGCONV_QUALITY")]
+    quality: u8,
+
+    /// zlib effort for PNG output; best is smallest and slowest
+    #[arg(long, value_enum, default_value = "fast")]
+    png_compression: PngCompression,
+
+    /// Scanline filter for PNG output; adaptive picks the best one per row
+    #[arg(long, value_enum, default_value = "sub")]
+    png_filter: PngFilter,
+
+    /// Write Adam7-interlaced PNGs that display progressively while loading
+    #[arg(long)]
+    interlace: bool,
+
+    /// Write an indexed PNG with at most this many colors (2-256)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(2..=256))]
+    colors: Option<u16>,
+
+    /// With --colors, dither (Floyd–Steinberg) to hide banding
+    #[arg(long, requires = "colors")]
+    dither: bool,
+
+    /// Losslessly shrink PNG output with oxipng and zopfli (slow)
+    #[arg(long)]
+    crush: bool,
+
+    /// Encode WebP losslessly instead of using --quality
+    #[arg(long)]
+    lossless: bool,
+
+    /// AVIF encoder speed, 1 (slowest, smallest) to 10 (fastest)
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=10))]
+    speed: u8,
+
+    /// Write a folder of platform assets instead of one image; --output names the folder
+    #[arg(long, value_enum)]
+    package: Option<Package>,
+
+    /// With --package android or ios, the longer side of the 1x image in dp or points;
+    /// defaults to the size from --scale, --width or --height
+    #[arg(long, value_name = "DP", requires = "package")]
+    base_size: Option<u32>,
+
+    /// With --package android, also write an unscaled copy to drawable-nodpi
+    #[arg(long, requires = "package")]
+    nodpi: bool,
+
+    /// Icon sizes bundled into .ico output
+    #[arg(long, value_delimiter = ',', default_values_t = ICO_SIZES)]
+    ico_sizes: Vec<u32>,
+
+    /// Frames per second sampled from SMIL and CSS animations, for animated and video output
+    #[arg(long, default_value_t = 25.0, value_parser = parse_scale)]
+    fps: f32,
+
+    /// Seconds of animation to record; by default until it ends or loops
+    #[arg(long, value_name = "SECONDS", value_parser = parse_scale)]
+    duration: Option<f32>,
+
+    /// Video bitrate in kbit/s for MP4 and WebM; by default ffmpeg picks it for quality
+    #[arg(long, value_name = "KBPS")]
+    bitrate: Option<u32>,
+
+    /// With SVG output, only replace <text> with outlined paths and keep the rest as written
+    #[arg(long)]
+    outline_text_only: bool,
+
+    /// Also write each output's data: URI to NAME.EXT.txt, for inlining in HTML or CSS
+    #[arg(long)]
+    data_uri: bool,
+
+    /// Also write density variants, e.g. 1,2,3 for icon.png, icon@2x.png and icon@3x.png
+    #[arg(long, value_delimiter = ',', value_parser = parse_scale)]
+    variants: Vec<f32>,
+
+    /// Also write an <img srcset> tag or CSS rule referencing the variants, as NAME.html or
+    /// NAME.css
+    #[arg(long, value_enum)]
+    snippet: Option<Snippet>,
+
+    /// Background color painted under the artwork, as #RRGGBB (JPEG defaults to white)
+    #[arg(long, value_parser = parse_hex_color, env = "SVGCONV_BACKGROUND")]
+    background: Option<[u8; 3]>,
+
+    /// Render only the element with this id, cropped to its bounds
+    #[arg(long, value_name = "ID")]
+    element: Option<String>,
+
+    /// Write each top-level group to its own image, named after the group's id
+    #[arg(long)]
+    split_layers: bool,
+
+    /// With --split-layers, export every element whose id matches this pattern instead;
+    /// `*` matches any characters
+    #[arg(long, value_name = "PATTERN", requires = "split_layers")]
+    layer_pattern: Option<String>,
+
+    /// Render only this region, in SVG user units, as X,Y,WIDTH,HEIGHT (overrides the viewBox)
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_crop)]
+    crop: Option<[f64; 4]>,
+
+    /// Crop to the bounding box of the drawn content, dropping empty canvas around it
+    #[arg(long)]
+    trim: bool,
+
+    /// Pad the shorter side so the output is square, with the artwork centered
+    #[arg(long)]
+    square: bool,
+
+    /// Space around the artwork, in pixels or as a percentage like 10%; takes the
+    /// --background color if one is set
+    #[arg(long, default_value = "0", value_parser = parse_padding)]
+    padding: (f32, PaddingUnit),
+
+    /// Replace a fill, stroke or gradient color before rendering, as #FROM=#TO; repeat for
+    /// several colors
+    #[arg(long, value_name = "FROM=TO", value_parser = parse_recolor)]
+    recolor: Vec<([u8; 3], [u8; 3])>,
+
+    /// Color that `currentColor` resolves to, as #RRGGBB (black by default)
+    #[arg(long, value_name = "COLOR", value_parser = parse_hex_color)]
+    current_color: Option<[u8; 3]>,
+
+    /// Repeat the output in a COLUMNSxROWS grid, e.g. 4x4 for a background texture
+    #[arg(long, value_name = "COLUMNSxROWS", value_parser = parse_tile)]
+    tile: Option<[u32; 2]>,
+
+    /// Pixels between tiles, in the --background color if one is set
+    #[arg(long, default_value_t = 0, value_name = "PIXELS", requires = "tile")]
+    tile_gap: u32,
+
+    /// Convert the output to grayscale, weighting channels by WEIGHTS
+    #[arg(long, value_enum, value_name = "WEIGHTS", num_args = 0..=1, default_missing_value = "rec709")]
+    grayscale: Option<LumaWeights>,
+
+    /// Invert the output's colors, keeping transparency
+    #[arg(long)]
+    invert: bool,
+
+    /// Paint the whole output in one #RRGGBB color, keeping only its shape and transparency
+    #[arg(long, value_name = "COLOR", value_parser = parse_hex_color)]
+    tint: Option<[u8; 3]>,
+
+    /// Multiply the output's alpha by this percentage, e.g. 40 for a faded variant
+    #[arg(long, default_value_t = 100.0, value_name = "PERCENT", value_parser = parse_percent)]
+    opacity: f32,
+
+    /// Composite this PNG or SVG over the output, e.g. to stamp previews
+    #[arg(long, value_name = "IMAGE")]
+    watermark: Option<PathBuf>,
+
+    /// Where to place the watermark
+    #[arg(
+        long,
+        value_enum,
+        default_value = "bottom-right",
+        requires = "watermark"
+    )]
+    watermark_corner: Corner,
+
+    /// Watermark width as a percentage of the output width
+    #[arg(
+        long,
+        default_value_t = 25.0,
+        value_name = "PERCENT",
+        requires = "watermark"
+    )]
+    watermark_scale: f32,
+
+    /// Watermark opacity as a percentage
+    #[arg(
+        long,
+        default_value_t = 50.0,
+        value_name = "PERCENT",
+        requires = "watermark",
+        value_parser = parse_percent
+    )]
+    watermark_opacity: f32,
+
+    /// Anti-alias in linear light (4x supersampled), for cleaner high-contrast edges
+    #[arg(long)]
+    linear_light: bool,
+
+    /// ICC profile embedded in PNG and JPEG output: `srgb` or a path to an .icc file
+    #[arg(long, value_name = "PROFILE", value_parser = parse_icc_profile)]
+    icc_profile: Option<IccProfile>,
+
+    /// Title stored in PNG metadata
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Author stored in PNG metadata
+    #[arg(long)]
+    author: Option<String>,
+
+    /// Copyright notice stored in PNG metadata
+    #[arg(long)]
+    copyright: Option<String>,
+
+    /// Store the input's file name in PNG metadata
+    #[arg(long)]
+    source_filename: bool,
+
+    /// Don't load installed fonts, so <text> is left out and output is the same on every machine
+    #[arg(long)]
+    no_system_fonts: bool,
+
+    /// Font file or folder of fonts to render <text> with; may be repeated
+    #[arg(long = "font", value_name = "PATH", env = "SVGCONV_FONT_DIR")]
+    fonts: Vec<PathBuf>,
+
+    /// Font family for text that doesn't name one
+    #[arg(long, value_name = "FAMILY", global = true)]
+    font_family: Option<String>,
+
+    /// Font used for the generic serif family
+    #[arg(long, value_name = "FAMILY")]
+    serif_family: Option<String>,
+
+    /// Font used for the generic sans-serif family
+    #[arg(long, value_name = "FAMILY")]
+    sans_serif_family: Option<String>,
+
+    /// Font used for the generic monospace family
+    #[arg(long, value_name = "FAMILY")]
+    monospace_family: Option<String>,
+
+    /// Font used for the generic cursive family
+    #[arg(long, value_name = "FAMILY")]
+    cursive_family: Option<String>,
+
+    /// Folder relative image hrefs resolve against; defaults to each input's folder
+    #[arg(long, value_name = "DIR", global = true)]
+    resources_dir: Option<PathBuf>,
+
+    /// Refuse SVGs that reference external files or URLs, listing what was blocked
+    #[arg(long, global = true)]
+    safe_mode: bool,
+
+    /// Strip scripts, event handlers, foreignObject, javascript: links and animations that
+    /// change links from the input before converting
+    #[arg(long)]
+    sanitize: bool,
+
+    /// With --sanitize, also save the cleaned markup next to the output as NAME.sanitized.svg
+    #[arg(long, requires = "sanitize")]
+    write_sanitized: bool,
+
+    /// Resolution for physical units (mm, in, pt) inside the SVG
+    #[arg(long, default_value_t = 96.0, global = true)]
+    svg_dpi: f64,
+
+    /// Languages matched against systemLanguage, e.g. en,de
+    #[arg(long, value_delimiter = ',', default_value = "en", global = true)]
+    languages: Vec<String>,
+
+    /// Shape rendering mode where the SVG doesn't set one
+    #[arg(long, value_enum, default_value = "geometric-precision")]
+    shape_rendering: ShapeRendering,
+
+    /// Text rendering mode where the SVG doesn't set one
+    #[arg(long, value_enum, default_value = "optimize-legibility")]
+    text_rendering: TextRendering,
+
+    /// Image rendering mode where the SVG doesn't set one
+    #[arg(long, value_enum, default_value = "optimize-quality")]
+    image_rendering: ImageRendering,
+
+    /// Size of SVGs without a viewBox, width and height, as WIDTHxHEIGHT
+    #[arg(long, default_value = "100x100", value_parser = parse_size, global = true)]
+    default_size: (f64, f64),
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the size, viewBox, element counts, fonts and images of SVGs as JSON, without
+    /// rendering them. Several inputs print an array. The parsing options --safe-mode,
+    /// --svg-dpi, --default-size, --languages, --resources-dir and --font-family apply
+    Info {
+        /// Input SVG files or http(s) URLs; - reads the SVG from stdin
+        #[arg(required = true)]
+        inputs: Vec<String>,
+    },
+    /// Print a completion script for every option and subcommand, e.g.
+    /// `rust-svg-converter completions bash > ~/.local/share/bash-completion/completions/rust-svg-converter`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+pub fn parse_scale(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(scale),
+        _ => Err(format!("expected a positive number, got `{}`", value)),
+    }
+}
+
+fn parse_size(value: &str) -> Result<(f64, f64), String> {
+    let parse = |side: &str| side.trim().parse::<f64>().ok().filter(|&n| n > 0.0);
+    match value.split_once('x').map(|(w, h)| (parse(w), parse(h))) {
+        Some((Some(width), Some(height))) => Ok((width, height)),
+        _ => Err(format!("expected WIDTHxHEIGHT, got `{}`", value)),
+    }
+}
+
+fn parse_crop(value: &str) -> Result<[f64; 4], String> {
+    let numbers: Vec<f64> = value
+        .split(',')
+        .filter_map(|n| n.trim().parse().ok())
+        .collect();
+    match numbers[..] {
+        [x, y, width, height] if width > 0.0 && height > 0.0 => Ok([x, y, width, height]),
+        _ => Err(format!("expected X,Y,WIDTH,HEIGHT, got `{}`", value)),
+    }
+}
+
+fn parse_padding(value: &str) -> Result<(f32, PaddingUnit), String> {
+    let (number, unit) = match value.strip_suffix('%') {
+        Some(percent) => (percent, PaddingUnit::Percent),
+        None => (
+            value.strip_suffix("px").unwrap_or(value),
+            PaddingUnit::Pixels,
+        ),
+    };
+    match number.trim().parse::<f32>() {
+        Ok(padding) if padding >= 0.0 && padding.is_finite() => Ok((padding, unit)),
+        _ => Err(format!("expected pixels or a percentage, got `{}`", value)),
+    }
+}
+
+fn parse_tile(value: &str) -> Result<[u32; 2], String> {
+    let grid = value.split_once(['x', 'X']).and_then(|(columns, rows)| {
+        Some([columns.trim().parse().ok()?, rows.trim().parse().ok()?])
+    });
+    match grid {
+        Some([columns, rows]) if columns > 0 && rows > 0 => Ok([columns, rows]),
+        _ => Err(format!("expected a grid like 3x2, got `{}`", value)),
+    }
+}
+
+fn parse_percent(value: &str) -> Result<f32, String> {
+    match value.trim().trim_end_matches('%').parse::<f32>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!(
+            "expected a percentage from 0 to 100, got `{}`",
+            value
+        )),
+    }
+}
+
+fn parse_icc_profile(value: &str) -> Result<IccProfile, String> {
+    if value.eq_ignore_ascii_case("srgb") {
+        Ok(IccProfile::Srgb)
+    } else {
+        Ok(IccProfile::File(PathBuf::from(value)))
+    }
+}
+
+pub fn parse_hex_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.trim_start_matches('#');
+    // Checking the digits first keeps the slicing below on character boundaries.
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("expected a #RRGGBB color, got `{}`", value));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+fn parse_recolor(value: &str) -> Result<([u8; 3], [u8; 3]), String> {
+    let (from, to) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected #FROM=#TO, got `{}`", value))?;
+    Ok((parse_hex_color(from.trim())?, parse_hex_color(to.trim())?))
+}
+
+/// The conversion options given by the command line, including config file defaults.
+fn conversion_options(cli: &Cli) -> ConversionOptions {
+    let format = cli
+        .format
+        .or_else(|| cli.output.as_deref().and_then(OutputFormat::from_path))
+        .unwrap_or(OutputFormat::Png);
+    ConversionOptions {
+        scale: cli.scale,
+        width: cli.width,
+        height: cli.height,
+        print_width: cli.print_width,
+        print_height: cli.print_height,
+        print_unit: cli.unit,
+        dpi: cli.dpi,
+        embed_dpi: cli.embed_dpi,
+        icc_profile: cli.icc_profile.clone(),
+        linear_light: cli.linear_light,
+        format,
+        quality: cli.quality,
+        background: cli.background,
+        png_compression: cli.png_compression,
+        png_filter: cli.png_filter,
+        png_interlaced: cli.interlace,
+        png_optimize: cli.crush,
+        png_palette_size: cli.colors,
+        png_dither: cli.dither,
+        webp_lossless: cli.lossless,
+        avif_speed: cli.speed,
+        ico_sizes: cli.ico_sizes.clone(),
+        outline_text_only: cli.outline_text_only,
+        data_uri: cli.data_uri,
+        package: cli.package,
+        package_options: PackageOptions {
+            base_size: cli.base_size,
+            android_nodpi: cli.nodpi,
+        },
+        density_variants: cli.variants.clone(),
+        snippet: cli.snippet,
+        fonts: FontOptions {
+            system: !cli.no_system_fonts,
+            paths: cli.fonts.clone(),
+            default_family: cli.font_family.clone(),
+            serif: cli.serif_family.clone(),
+            sans_serif: cli.sans_serif_family.clone(),
+            monospace: cli.monospace_family.clone(),
+            cursive: cli.cursive_family.clone(),
+        },
+        parse: ParseOptions {
+            resources_dir: cli.resources_dir.clone(),
+            safe_mode: cli.safe_mode,
+            sanitize: cli.sanitize,
+            write_sanitized: cli.write_sanitized,
+            dpi: cli.svg_dpi,
+            languages: cli.languages.clone(),
+            shape_rendering: cli.shape_rendering,
+            text_rendering: cli.text_rendering,
+            image_rendering: cli.image_rendering,
+            default_size: cli.default_size,
+        },
+        canvas: CanvasOptions {
+            element: cli.element.clone(),
+            split_layers: cli.split_layers,
+            layer_pattern: cli.layer_pattern.clone(),
+            crop: cli.crop,
+            trim: cli.trim,
+            square: cli.square,
+            padding: cli.padding.0,
+            padding_unit: cli.padding.1,
+            tile: cli.tile,
+            tile_gap: cli.tile_gap,
+        },
+        colors: ColorOptions {
+            replace: cli.recolor.clone(),
+            current_color: cli.current_color,
+        },
+        effects: EffectOptions {
+            grayscale: cli.grayscale,
+            invert: cli.invert,
+            tint: cli.tint,
+            opacity: cli.opacity / 100.0,
+            watermark: cli.watermark.clone().map(|path| Watermark {
+                path,
+                corner: cli.watermark_corner,
+                scale: cli.watermark_scale / 100.0,
+                opacity: cli.watermark_opacity / 100.0,
+            }),
+        },
+        metadata: MetadataOptions {
+            title: cli.title.clone(),
+            author: cli.author.clone(),
+            copyright: cli.copyright.clone(),
+            source_filename: cli.source_filename,
+        },
+        animation: AnimationOptions {
+            fps: cli.fps,
+            duration: cli.duration,
+            bitrate: cli.bitrate,
+        },
+    }
+}
+
+/// Parses the command line, with defaults from the config file.
+pub fn parse() -> Cli {
+    let command = config::apply(Cli::command()).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
+}
+
+/// The conversion options and output path set in the config file, or `None` without one.
+pub fn config_preset() -> Result<Option<Preset>, Box<dyn std::error::Error>> {
+    if !config::path().is_some_and(|path| path.exists()) {
+        return Ok(None);
+    }
+    // Environment variables only configure the CLI.
+    let command = config::apply(Cli::command().mut_args(|arg| arg.env(None)))?;
+    // The input only satisfies the parser; nothing is converted.
+    let matches = command.try_get_matches_from(["rust-svg-converter", STDIN])?;
+    let cli = Cli::from_arg_matches(&matches)?;
+    Ok(Some(Preset {
+        name: String::new(),
+        output: cli.output.clone().unwrap_or_default(),
+        options: conversion_options(&cli),
+    }))
+}
+
+/// Exit code when some inputs of several failed; see `after_help` for the rest.
+const EXIT_PARTIAL: i32 = 6;
+
+fn exit_code(kind: ErrorKind) -> i32 {
+    match kind {
+        ErrorKind::Other => 1,
+        ErrorKind::Parse => 3,
+        ErrorKind::Io => 4,
+        ErrorKind::Encode => 5,
+    }
+}
+
+/// Exit code for a run of `total` inputs that failed with `failures`: the shared kind when
+/// every input failed the same way.
+fn batch_exit_code(total: usize, failures: &[ErrorKind]) -> i32 {
+    match failures {
+        [] => 0,
+        _ if failures.len() < total => EXIT_PARTIAL,
+        [kind, rest @ ..] if rest.iter().all(|other| other == kind) => exit_code(*kind),
+        _ => 1,
+    }
+}
+
+/// Prints `Error …: message` to stderr, or a JSON line with --json, and returns the exit code.
+pub fn report(json: bool, context: &str, input: Option<&str>, error: &BatchError) -> i32 {
+    if json {
+        let object = serde_json::json!({
+            "input": input,
+            "kind": error.kind,
+            "message": error.message,
+            "line": error.location.map(|(line, _)| line),
+            "column": error.location.map(|(_, column)| column),
+        });
+        eprintln!("{}", object);
+    } else {
+        eprintln!("{}: {}", context, error);
+    }
+    exit_code(error.kind)
+}
+
+/// A usage error found after parsing, reported like other errors.
+fn usage_error(json: bool, message: &str) -> i32 {
+    let error = BatchError {
+        kind: ErrorKind::Other,
+        message: message.to_string(),
+        location: None,
+    };
+    report(json, "Error", None, &error);
+    2
+}
+
+/// Runs a headless conversion and returns the process exit code.
+pub fn run(cli: Cli) -> i32 {
+    let json = cli.json;
+    let options = conversion_options(&cli);
+    if let Some(command) = cli.command {
+        return match command {
+            Command::Info { inputs } => run_info(&inputs, &options, json),
+            Command::Completions { shell } => {
+                let mut command = Cli::command();
+                let name = command.get_name().to_string();
+                clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+                0
+            }
+        };
+    }
+
+    if let Some(mode) = cli.trace {
+        let options = TraceOptions {
+            mode,
+            threshold: cli.threshold,
+            colors: cli.trace_colors,
+            speckle: cli.speckle,
+            smoothness: cli.smoothness,
+        };
+        return run_trace(&cli.inputs, cli.output.as_deref(), &options, json);
+    }
+
+    let format = options.format;
+
+    if let Some(address) = cli.serve {
+        return serve::run(&address, options, json);
+    }
+
+    if cli.inputs.len() > 1 && cli.inputs.iter().any(|input| input == STDIN) {
+        return usage_error(json, "stdin can't be combined with other inputs");
+    }
+    if cli.output.as_deref() == Some(STDIN) || (cli.output.is_none() && cli.inputs == [STDIN]) {
+        return run_to_stdout(&cli.inputs, &options, json);
+    }
+
+    // Each job is an input file and the subfolder it is mirrored into.
+    let mut files: Vec<(String, PathBuf)> = Vec::new();
+    for input in &cli.inputs {
+        let input_path = Path::new(input);
+        if !input_path.is_dir() {
+            files.push((input.clone(), PathBuf::new()));
+            continue;
+        }
+        match find_svgs(input_path, cli.recursive) {
+            Ok(found) => files.extend(found.into_iter().map(|path| {
+                let relative_dir = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(input_path).ok())
+                    .unwrap_or(Path::new(""))
+                    .to_path_buf();
+                (path.display().to_string(), relative_dir)
+            })),
+            Err(e) => {
+                let error = BatchError::from(Box::<dyn std::error::Error>::from(e));
+                return report(
+                    json,
+                    &format!("Error reading {}", input),
+                    Some(input),
+                    &error,
+                );
+            }
+        }
+    }
+
+    if cli.sprite_sheet {
+        let inputs: Vec<String> = files.into_iter().map(|(input, _)| input).collect();
+        let atlas = AtlasOptions {
+            data: cli.sprite_data,
+            gap: cli.sprite_gap,
+        };
+        let output = cli.output.unwrap_or_default();
+        return match build_atlas(&inputs, &output, &options, &atlas, &AtomicBool::new(false)) {
+            Ok(written) => {
+                println!("Packed {} files into {}", inputs.len(), written.join(", "));
+                0
+            }
+            Err(e) => report(json, "Error", None, &BatchError::from(e)),
+        };
+    }
+
+    let single_file = files.len() == 1 && !Path::new(&cli.inputs[0]).is_dir();
+    let jobs: Vec<BatchJob> = files
+        .into_iter()
+        .map(|(input, relative_dir)| {
+            let template = match (&cli.output, &cli.output_dir) {
+                (Some(output), _)
+                    if !Path::new(output).is_dir()
+                        && (single_file || template::has_tokens(output)) =>
+                {
+                    output.clone()
+                }
+                (Some(dir), _) => batch_output_template(&Path::new(dir).join(relative_dir), format),
+                (None, Some(dir)) => batch_output_template(&dir.join(relative_dir), format),
+                // Downloads land in the working directory.
+                (None, None) if is_url(&input) => batch_output_template(Path::new(""), format),
+                (None, None) => batch_output_template(
+                    Path::new(&input).parent().unwrap_or(Path::new("")),
+                    format,
+                ),
+            };
+            BatchJob {
+                input,
+                output_template: template,
+                options: options.clone(),
+            }
+        })
+        .collect();
+
+    let finished = AtomicUsize::new(0);
+    let results = convert_batch(
+        &jobs,
+        cli.jobs,
+        &AtomicBool::new(false),
+        |index, progress| {
+            let BatchProgress::Finished(result) = progress else {
+                return;
+            };
+            let count = finished.fetch_add(1, Ordering::Relaxed) + 1;
+            let input = &jobs[index].input;
+            match result {
+                Ok(written) => println!(
+                    "[{}/{}] Converted {} to {}",
+                    count,
+                    jobs.len(),
+                    input,
+                    written.join(", ")
+                ),
+                Err(e) => {
+                    let context = format!("[{}/{}] Error converting {}", count, jobs.len(), input);
+                    report(json, &context, Some(input), e);
+                }
+            }
+        },
+    );
+
+    match results {
+        Ok(results) => {
+            let failures: Vec<ErrorKind> = results
+                .iter()
+                .filter_map(|result| Some(result.as_ref().err()?.kind))
+                .collect();
+            batch_exit_code(results.len(), &failures)
+        }
+        Err(e) => report(json, "Error", None, &BatchError::from(e)),
+    }
+}
+
+/// Prints what each input contains as JSON.
+fn run_info(inputs: &[String], options: &ConversionOptions, json: bool) -> i32 {
+    let mut infos = Vec::new();
+    let mut failures = Vec::new();
+    for input in inputs {
+        match read_svg(input).and_then(|svg_data| info::inspect(&svg_data, input, options)) {
+            Ok(info) => infos.push(info),
+            Err(e) => {
+                let error = BatchError::from(e);
+                report(
+                    json,
+                    &format!("Error reading {}", input),
+                    Some(input),
+                    &error,
+                );
+                failures.push(error.kind);
+            }
+        }
+    }
+    let output = match &infos[..] {
+        [info] if inputs.len() == 1 => serde_json::to_string_pretty(info),
+        [] if inputs.len() == 1 => return batch_exit_code(1, &failures),
+        _ => serde_json::to_string_pretty(&infos),
+    };
+    match output {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            return report(
+                json,
+                "Error",
+                None,
+                &BatchError::from(Box::<dyn std::error::Error>::from(e)),
+            )
+        }
+    }
+    batch_exit_code(inputs.len(), &failures)
+}
+
+/// Converts the one input and writes the encoded image to stdout, for use in pipelines.
+fn run_to_stdout(inputs: &[String], options: &ConversionOptions, json: bool) -> i32 {
+    let [input] = inputs else {
+        return usage_error(json, "only one input can be written to stdout");
+    };
+    let converted = read_svg(input)
+        .and_then(|svg_data| Converter::new(options.clone()).convert(&svg_data, input))
+        .and_then(|data| Ok(std::io::stdout().lock().write_all(&data)?));
+    match converted {
+        Ok(()) => 0,
+        Err(e) => {
+            let context = format!("Error converting {}", input);
+            report(json, &context, Some(input), &BatchError::from(e))
+        }
+    }
+}
+
+/// Traces each raster input into an SVG named after it, or into `output` for one input.
+fn run_trace(inputs: &[String], output: Option<&str>, options: &TraceOptions, json: bool) -> i32 {
+    let mut failures = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let output_path = match output {
+            Some(output) if inputs.len() == 1 && !Path::new(output).is_dir() => output.to_string(),
+            _ => {
+                let dir = output.map_or_else(
+                    || Path::new(input).parent().unwrap_or(Path::new("")),
+                    Path::new,
+                );
+                dir.join(format!("{}.svg", template::input_name(input)))
+                    .display()
+                    .to_string()
+            }
+        };
+        let traced = if Path::new(input).is_dir() {
+            Err("folders can't be traced; pass the images in it".into())
+        } else {
+            trace::save(input, &output_path, options)
+        };
+        match traced {
+            Ok(()) => println!(
+                "[{}/{}] Traced {} to {}",
+                index + 1,
+                inputs.len(),
+                input,
+                output_path
+            ),
+            Err(e) => {
+                let error = BatchError::from(e);
+                let context = format!("[{}/{}] Error tracing {}", index + 1, inputs.len(), input);
+                report(json, &context, Some(input), &error);
+                failures.push(error.kind);
+            }
+        }
+    }
+    batch_exit_code(inputs.len(), &failures)
+}
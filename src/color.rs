@@ -0,0 +1,108 @@
+use crate::sanitize::attribute_range;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::rc::Rc;
+use usvg::{roxmltree, Color, Node, NodeKind, Paint, Tree};
+
+/// Color changes made to the document before rendering, so one master file can be
+/// exported in several palettes.
+#[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColorOptions {
+    /// Exact `(from, to)` swaps applied to fills, strokes and gradient stops. Each color is
+    /// replaced at most once, so pairs can swap two colors.
+    pub replace: Vec<([u8; 3], [u8; 3])>,
+    /// The document's `color`, which `currentColor` fills and strokes resolve to; icon sets
+    /// rely on it, and without one it is black.
+    pub current_color: Option<[u8; 3]>,
+}
+
+impl ColorOptions {
+    /// Sets `current_color` as the root element's `color` attribute, replacing any it has.
+    /// Elements inside that set their own `color` keep it.
+    pub fn prepare<'a>(&self, svg_data: &'a str) -> Result<Cow<'a, str>, roxmltree::Error> {
+        let Some([red, green, blue]) = self.current_color else {
+            return Ok(Cow::Borrowed(svg_data));
+        };
+        let document = roxmltree::Document::parse_with_options(
+            svg_data,
+            roxmltree::ParsingOptions {
+                allow_dtd: true,
+                ..Default::default()
+            },
+        )?;
+        let root = document.root_element();
+        let attribute = format!("color=\"#{:02x}{:02x}{:02x}\"", red, green, blue);
+
+        let existing = root
+            .attributes()
+            .find(|attribute| attribute.name() == "color" && attribute.namespace().is_none())
+            .and_then(|attribute| attribute_range(svg_data, attribute.position()));
+        let mut prepared = svg_data.to_string();
+        match existing {
+            Some(range) => prepared.replace_range(range, &attribute),
+            None => {
+                // Right after `<svg`, or `<prefix:svg`.
+                let tag = &svg_data[root.range().start..];
+                let name_end = tag
+                    .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .unwrap_or(tag.len());
+                prepared.insert_str(root.range().start + name_end, &format!(" {}", attribute));
+            }
+        }
+        Ok(Cow::Owned(prepared))
+    }
+
+    pub fn apply(&self, rtree: &Tree) {
+        if !self.replace.is_empty() {
+            let mut patterns = HashSet::new();
+            recolor(&rtree.root, &self.replace, &mut patterns);
+        }
+    }
+}
+
+/// Replaces colors in every path under `root`, including the contents of patterns they use.
+/// `patterns` tracks the ones already done, since several paths can share one.
+fn recolor(root: &Node, replace: &[([u8; 3], [u8; 3])], patterns: &mut HashSet<String>) {
+    for node in root.descendants() {
+        let mut pattern_roots = Vec::new();
+        if let NodeKind::Path(ref mut path) = *node.borrow_mut() {
+            let paints = path
+                .fill
+                .iter_mut()
+                .map(|fill| &mut fill.paint)
+                .chain(path.stroke.iter_mut().map(|stroke| &mut stroke.paint));
+            for paint in paints {
+                match paint {
+                    Paint::Color(color) => *color = replaced(*color, replace),
+                    Paint::LinearGradient(gradient) => {
+                        for stop in &mut Rc::make_mut(gradient).base.stops {
+                            stop.color = replaced(stop.color, replace);
+                        }
+                    }
+                    Paint::RadialGradient(gradient) => {
+                        for stop in &mut Rc::make_mut(gradient).base.stops {
+                            stop.color = replaced(stop.color, replace);
+                        }
+                    }
+                    Paint::Pattern(pattern) => {
+                        if patterns.insert(pattern.id.clone()) {
+                            pattern_roots.push(pattern.root.clone());
+                        }
+                    }
+                }
+            }
+        }
+        for pattern_root in pattern_roots {
+            recolor(&pattern_root, replace, patterns);
+        }
+    }
+}
+
+fn replaced(color: Color, replace: &[([u8; 3], [u8; 3])]) -> Color {
+    let rgb = [color.red, color.green, color.blue];
+    match replace.iter().find(|(from, _)| *from == rgb) {
+        Some(&(_, [red, green, blue])) => Color::new_rgb(red, green, blue),
+        None => color,
+    }
+}
@@ -0,0 +1,62 @@
+use clap::Command;
+use std::path::PathBuf;
+
+/// Arguments that choose what the CLI does rather than how it converts; a default for one
+/// would change every run.
+const MODES: [&str; 4] = ["inputs", "serve", "trace", "sprite_sheet"];
+
+/// `rust-svg-converter/config.toml` in `$XDG_CONFIG_HOME`, or else in `~/.config`.
+pub fn path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            Some(PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_dir.join("rust-svg-converter").join("config.toml"))
+}
+
+/// Makes the values in the config file the defaults of `command`'s options, so flags given
+/// on the command line still win. Keys are long option names, e.g. `scale = 2`,
+/// `output = "build/{name}.{ext}"` or `font = ["fonts/"]`; a missing file changes nothing.
+pub fn apply(mut command: Command) -> Result<Command, Box<dyn std::error::Error>> {
+    let Some(path) = path() else {
+        return Ok(command);
+    };
+    let data = match std::fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(command),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e).into()),
+    };
+    let table: toml::Table =
+        toml::from_str(&data).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+
+    for (key, value) in table {
+        let id = command
+            .get_arguments()
+            .find(|arg| {
+                arg.get_long() == Some(key.as_str()) && !MODES.contains(&arg.get_id().as_str())
+            })
+            .map(|arg| arg.get_id().clone())
+            .ok_or_else(|| format!("Unknown option `{}` in {}", key, path.display()))?;
+        let values = match &value {
+            toml::Value::Array(items) => items.iter().map(value_string).collect(),
+            value => value_string(value).map(|value| vec![value]),
+        }
+        .ok_or_else(|| format!("Unsupported value for `{}` in {}", key, path.display()))?;
+        command = command.mut_arg(id, |arg| arg.default_values(values));
+    }
+    Ok(command)
+}
+
+/// The value as it would be written on the command line.
+fn value_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(string) => Some(string.clone()),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        toml::Value::Boolean(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
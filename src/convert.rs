@@ -0,0 +1,1486 @@
+use crate::animation::{self, AnimationOptions};
+use crate::canvas::{self, CanvasOptions};
+use crate::color::ColorOptions;
+use crate::effects::EffectOptions;
+use crate::encode::{self, MetadataOptions, OutputFormat, PngCompression, PngFilter};
+use crate::icc::IccProfile;
+use crate::icon::{self, ICO_SIZES};
+use crate::large_output;
+use crate::outline;
+use crate::package::{Package, PackageOptions};
+use crate::parsing::ParseOptions;
+use crate::sanitize;
+use crate::snippet::Snippet;
+use crate::svg_writer;
+use crate::template;
+use crate::vector_drawable;
+use rayon::prelude::*;
+use resvg::usvg_text_layout::{fontdb, TreeTextToPath};
+use resvg::{render, tiny_skia};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tiny_skia::Pixmap;
+use usvg::{roxmltree, Options, Tree};
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum LengthUnit {
+    #[value(name = "mm")]
+    Millimeters,
+    #[value(name = "in")]
+    Inches,
+}
+
+impl LengthUnit {
+    pub fn name(self) -> &'static str {
+        match self {
+            LengthUnit::Millimeters => "mm",
+            LengthUnit::Inches => "in",
+        }
+    }
+
+    fn to_inches(self, length: f32) -> f32 {
+        match self {
+            LengthUnit::Millimeters => length / 25.4,
+            LengthUnit::Inches => length,
+        }
+    }
+}
+
+/// Settings shared by every conversion, whether started from the GUI or the CLI.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ConversionOptions {
+    pub scale: f32,
+    /// Exact output width in pixels; overrides `scale` when set.
+    pub width: Option<u32>,
+    /// Exact output height in pixels; overrides `scale` when set.
+    pub height: Option<u32>,
+    /// Printed width in `print_unit`, converted to pixels at `dpi`.
+    pub print_width: Option<f32>,
+    /// Printed height in `print_unit`, converted to pixels at `dpi`.
+    pub print_height: Option<f32>,
+    pub print_unit: LengthUnit,
+    /// Resolution used for print sizes, and recorded in PNG (`pHYs`) and JPEG (JFIF) output
+    /// whenever a print size is set or `embed_dpi` is on.
+    pub dpi: f32,
+    /// Record `dpi` in the output even when sizing in pixels.
+    pub embed_dpi: bool,
+    /// Anti-alias by supersampling and averaging in linear light, which keeps thin
+    /// high-contrast edges from looking too dark.
+    pub linear_light: bool,
+    /// Color profile embedded in PNG and JPEG output.
+    pub icc_profile: Option<IccProfile>,
+    pub format: OutputFormat,
+    /// Quality for lossy formats (JPEG, lossy WebP and AVIF), 1-100.
+    pub quality: u8,
+    /// Solid color painted under the artwork; formats without alpha fall back to white.
+    pub background: Option<[u8; 3]>,
+    pub png_compression: PngCompression,
+    pub png_filter: PngFilter,
+    /// Write Adam7-interlaced PNGs, which display progressively while downloading.
+    pub png_interlaced: bool,
+    /// Write an indexed PNG with at most this many colors (2-256) instead of RGBA.
+    pub png_palette_size: Option<u16>,
+    /// Diffuse the quantization error (Floyd–Steinberg) when reducing to a palette.
+    pub png_dither: bool,
+    /// Losslessly recompress PNGs with oxipng and zopfli; much slower, noticeably smaller.
+    pub png_optimize: bool,
+    pub webp_lossless: bool,
+    /// AVIF encoder speed, 1 (slowest, smallest) to 10 (fastest).
+    pub avif_speed: u8,
+    /// Square sizes bundled into `.ico` output.
+    pub ico_sizes: Vec<u32>,
+    /// With SVG output, keep the markup as written and only replace `<text>` with paths,
+    /// instead of writing out the whole cleaned tree.
+    pub outline_text_only: bool,
+    /// Also write each output's `data:` URI to a `.txt` file beside it, for inlining in
+    /// HTML or CSS.
+    pub data_uri: bool,
+    /// Write a folder of platform assets, named after the output path without its
+    /// extension, instead of a single image.
+    pub package: Option<Package>,
+    pub package_options: PackageOptions,
+    /// Density multipliers written as `name@2x.png`-style siblings; empty for a single file.
+    pub density_variants: Vec<f32>,
+    /// Also write an HTML or CSS snippet referencing the density variants.
+    pub snippet: Option<Snippet>,
+    pub fonts: FontOptions,
+    pub parse: ParseOptions,
+    pub canvas: CanvasOptions,
+    pub colors: ColorOptions,
+    pub effects: EffectOptions,
+    pub metadata: MetadataOptions,
+    pub animation: AnimationOptions,
+}
+
+/// Fonts used to render `<text>`.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FontOptions {
+    /// Use installed fonts; without them only `paths` are available, which keeps output
+    /// identical across machines.
+    pub system: bool,
+    /// Extra `.ttf`/`.otf` files, or folders of them, e.g. brand fonts that aren't installed.
+    pub paths: Vec<PathBuf>,
+    /// Family for text without a `font-family`; `None` keeps usvg's default.
+    pub default_family: Option<String>,
+    /// Fonts the generic `serif`, `sans-serif`, `monospace` and `cursive` families map to.
+    pub serif: Option<String>,
+    pub sans_serif: Option<String>,
+    pub monospace: Option<String>,
+    pub cursive: Option<String>,
+}
+
+impl Default for FontOptions {
+    fn default() -> Self {
+        Self {
+            system: true,
+            paths: Vec::new(),
+            default_family: None,
+            serif: None,
+            sans_serif: None,
+            monospace: None,
+            cursive: None,
+        }
+    }
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            width: None,
+            height: None,
+            print_width: None,
+            print_height: None,
+            print_unit: LengthUnit::Millimeters,
+            dpi: 300.0,
+            embed_dpi: false,
+            icc_profile: None,
+            linear_light: false,
+            format: OutputFormat::Png,
+            quality: 90,
+            background: None,
+            png_compression: PngCompression::Fast,
+            png_filter: PngFilter::Sub,
+            png_interlaced: false,
+            png_optimize: false,
+            png_palette_size: None,
+            png_dither: false,
+            webp_lossless: false,
+            avif_speed: 6,
+            ico_sizes: ICO_SIZES.to_vec(),
+            outline_text_only: false,
+            data_uri: false,
+            package: None,
+            package_options: PackageOptions::default(),
+            density_variants: Vec::new(),
+            snippet: None,
+            fonts: FontOptions::default(),
+            parse: ParseOptions::default(),
+            canvas: CanvasOptions::default(),
+            colors: ColorOptions::default(),
+            effects: EffectOptions::default(),
+            metadata: MetadataOptions::default(),
+            animation: AnimationOptions::default(),
+        }
+    }
+}
+
+impl ConversionOptions {
+    /// Whether the output size comes from a physical print size.
+    pub fn uses_print_size(&self) -> bool {
+        self.print_width.is_some() || self.print_height.is_some()
+    }
+
+    /// Resolution to record in the output file, if any.
+    pub fn embedded_dpi(&self) -> Option<f32> {
+        (self.embed_dpi || self.uses_print_size()).then_some(self.dpi)
+    }
+
+    /// Requested output width and height in pixels, from pixel or print sizes.
+    pub fn target_size(&self) -> (Option<u32>, Option<u32>) {
+        let to_pixels = |length: f32| {
+            (self.print_unit.to_inches(length) * self.dpi)
+                .round()
+                .max(1.0) as u32
+        };
+        (
+            self.width.or(self.print_width.map(to_pixels)),
+            self.height.or(self.print_height.map(to_pixels)),
+        )
+    }
+
+    /// Horizontal and vertical scale factors for an SVG of the given size.
+    ///
+    /// A single target dimension keeps the aspect ratio; both stretch to fit exactly.
+    pub fn scale_factors(&self, width: f32, height: f32) -> (f32, f32) {
+        match self.target_size() {
+            (Some(w), Some(h)) => (w as f32 / width, h as f32 / height),
+            (Some(w), None) => (w as f32 / width, w as f32 / width),
+            (None, Some(h)) => (h as f32 / height, h as f32 / height),
+            (None, None) => (self.scale, self.scale),
+        }
+    }
+
+    /// The same settings rendered at `factor` times the pixel density.
+    pub fn at_density(&self, factor: f32) -> ConversionOptions {
+        let multiply = |pixels: u32| (pixels as f32 * factor).round() as u32;
+        ConversionOptions {
+            scale: self.scale * factor,
+            width: self.width.map(multiply),
+            height: self.height.map(multiply),
+            dpi: self.dpi * factor,
+            density_variants: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Output pixel size for an SVG of the given size.
+    pub fn output_size(&self, width: f32, height: f32) -> (u32, u32) {
+        let (scale_x, scale_y) = self.scale_factors(width, height);
+        let (target_width, target_height) = self.target_size();
+        (
+            target_width.unwrap_or_else(|| (width * scale_x).ceil() as u32),
+            target_height.unwrap_or_else(|| (height * scale_y).ceil() as u32),
+        )
+    }
+}
+
+/// Whether an input is an `http://` or `https://` URL rather than a file path.
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("https://") || input.starts_with("http://")
+}
+
+/// File extensions picked up from folders and file dialogs.
+pub const SVG_EXTENSIONS: [&str; 2] = ["svg", "svgz"];
+
+pub fn is_svg_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        SVG_EXTENSIONS
+            .iter()
+            .any(|svg| ext.eq_ignore_ascii_case(svg))
+    })
+}
+
+/// Downloads SVG markup, e.g. an icon hosted on a CDN.
+pub fn fetch_svg(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    ureq::get(url)
+        .call()?
+        .into_reader()
+        .read_to_end(&mut data)?;
+    decode_svg(data)
+}
+
+/// The input path that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// Files at least this large are memory-mapped instead of read into memory, once
+/// [`map_large_inputs`] has been called.
+const MAP_MIN_BYTES: u64 = 16 << 20;
+
+static MAP_INPUTS: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`read_svg`] memory-map large uncompressed files instead of reading them, so the
+/// operating system pages them in as the parser reads them.
+///
+/// # Safety
+///
+/// The mapping follows the file, so another process rewriting a file in place while its
+/// [`SvgData`] is alive changes the markup underneath, and truncating it makes reads past
+/// the new end fault with SIGBUS. The caller must make sure input files aren't changed
+/// while they're being converted.
+pub unsafe fn map_large_inputs() {
+    MAP_INPUTS.store(true, Ordering::Relaxed);
+}
+
+/// SVG markup read by [`read_svg`]; dereferences to the markup.
+pub struct SvgData(SvgBytes);
+
+enum SvgBytes {
+    Owned(String),
+    /// Checked to be UTF-8 when mapped; see [`map_large_inputs`] for why it stays so.
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for SvgData {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match &self.0 {
+            SvgBytes::Owned(svg_data) => svg_data,
+            // SAFETY: checked in `read_svg`, and the file is unchanged since, as the caller of
+            // `map_large_inputs` promised.
+            SvgBytes::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+/// Reads the SVG markup of a file, URL or [`STDIN`].
+pub fn read_svg(input_path: &str) -> Result<SvgData, Box<dyn std::error::Error>> {
+    let owned = |svg_data| Ok(SvgData(SvgBytes::Owned(svg_data)));
+    if is_url(input_path) {
+        return owned(fetch_svg(input_path)?);
+    }
+    if input_path == STDIN {
+        let mut data = Vec::new();
+        std::io::stdin().read_to_end(&mut data)?;
+        return owned(decode_svg(data)?);
+    }
+
+    let mut file = std::fs::File::open(input_path)?;
+    let size = file.metadata()?.len();
+    if size < MAP_MIN_BYTES || !MAP_INPUTS.load(Ordering::Relaxed) {
+        let mut data = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut data)?;
+        return owned(decode_svg(data)?);
+    }
+    // SAFETY: upheld by the caller of `map_large_inputs`.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if map.starts_with(&GZIP_MAGIC) {
+        return owned(decode_svg(usvg::decompress_svgz(&map)?)?);
+    }
+    std::str::from_utf8(&map)?;
+    Ok(SvgData(SvgBytes::Mapped(map)))
+}
+
+/// The first bytes of gzip data, as in `.svgz` files.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Turns raw file contents into markup, decompressing gzipped `.svgz` data.
+fn decode_svg(data: Vec<u8>) -> Result<String, Box<dyn std::error::Error>> {
+    // Detected by the gzip magic bytes, so misnamed files work too.
+    let data = if data.starts_with(&GZIP_MAGIC) {
+        usvg::decompress_svgz(&data)?
+    } else {
+        data
+    };
+    Ok(String::from_utf8(data)?)
+}
+
+pub fn load_tree(
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Tree, Box<dyn std::error::Error>> {
+    parse_tree(&read_svg(input_path)?, input_path, options)
+}
+
+/// Parses SVG markup held in memory, e.g. pasted from the clipboard. Relative `href`s resolve
+/// next to `input_path` unless the options say otherwise.
+///
+/// `<text>` is turned into paths here, since resvg only renders text that has been converted.
+pub fn parse_tree(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Tree, Box<dyn std::error::Error>> {
+    let svg_data = options
+        .colors
+        .prepare(svg_data)
+        .map_err(|e| ParseError::new(&e, svg_data))?;
+    let (mut rtree, blocked) = {
+        let document = parse_xml(&svg_data)?;
+        document_tree(&document, input_path, options)?
+    };
+    check_blocked(&blocked)?;
+    finish_tree(&mut rtree, options)?;
+    Ok(rtree)
+}
+
+/// Parses markup as XML, the way usvg does.
+pub fn parse_xml(svg_data: &str) -> Result<roxmltree::Document<'_>, ParseError> {
+    roxmltree::Document::parse_with_options(
+        svg_data,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| ParseError::new(&e, svg_data))
+}
+
+/// The first step of [`parse_tree`]: the tree of an XML document, built with the parse
+/// options. Text is still text, and [`finish_tree`] does the rest.
+///
+/// In safe mode external references aren't loaded, and are returned instead for
+/// [`check_blocked`].
+pub fn document_tree(
+    document: &roxmltree::Document,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<(Tree, Vec<String>), Box<dyn std::error::Error>> {
+    let mut usvg_options = Options::default();
+    options.parse.apply(&mut usvg_options, input_path);
+    if let Some(family) = &options.fonts.default_family {
+        usvg_options.font_family = family.clone();
+    }
+
+    // Safe mode swaps in a resolver that records external hrefs instead of loading them;
+    // embedded `data:` images still load.
+    let blocked = Arc::new(Mutex::new(Vec::new()));
+    if options.parse.safe_mode {
+        let blocked = Arc::clone(&blocked);
+        usvg_options.image_href_resolver.resolve_string = Box::new(move |href, _| {
+            let mut blocked = blocked.lock().unwrap_or_else(|e| e.into_inner());
+            blocked.push(href.to_string());
+            None
+        });
+    }
+
+    let rtree = Tree::from_xmltree(document, &usvg_options)?;
+    let blocked = std::mem::take(&mut *blocked.lock().unwrap_or_else(|e| e.into_inner()));
+    Ok((rtree, blocked))
+}
+
+/// Fails with the external references safe mode blocked, if there are any.
+pub fn check_blocked(blocked: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if blocked.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Safe mode blocked external references: {}",
+        blocked.join(", ")
+    )
+    .into())
+}
+
+/// The last step of [`parse_tree`]: converts text to paths, and applies the color and canvas
+/// options.
+pub fn finish_tree(
+    rtree: &mut Tree,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fontdb = font_database(&options.fonts)?;
+    rtree.convert_text(&fontdb);
+    options.colors.apply(rtree);
+    CanvasOptions::apply(rtree, options)
+}
+
+/// The font database for `fonts`. The last one is cached, since every parse needs one.
+pub(crate) fn font_database(
+    fonts: &FontOptions,
+) -> Result<Arc<fontdb::Database>, Box<dyn std::error::Error>> {
+    static CACHE: Mutex<Option<(FontOptions, Arc<fontdb::Database>)>> = Mutex::new(None);
+
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached, fontdb)) = cache.as_ref() {
+        if cached == fonts {
+            return Ok(Arc::clone(fontdb));
+        }
+    }
+
+    let mut fontdb = if fonts.system {
+        system_fonts().clone()
+    } else {
+        fontdb::Database::new()
+    };
+    for path in &fonts.paths {
+        if path.is_dir() {
+            fontdb.load_fonts_dir(path);
+        } else {
+            fontdb
+                .load_font_file(path)
+                .map_err(|e| format!("Can't load font {}: {}", path.display(), e))?;
+        }
+    }
+
+    if let Some(family) = &fonts.serif {
+        fontdb.set_serif_family(family.as_str());
+    }
+    if let Some(family) = &fonts.sans_serif {
+        fontdb.set_sans_serif_family(family.as_str());
+    }
+    if let Some(family) = &fonts.monospace {
+        fontdb.set_monospace_family(family.as_str());
+    }
+    if let Some(family) = &fonts.cursive {
+        fontdb.set_cursive_family(family.as_str());
+    }
+
+    let fontdb = Arc::new(fontdb);
+    *cache = Some((fonts.clone(), Arc::clone(&fontdb)));
+    Ok(fontdb)
+}
+
+/// Installed fonts, loaded on first use since scanning them is slow.
+fn system_fonts() -> &'static fontdb::Database {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_system_fonts();
+        fontdb
+    })
+}
+
+pub fn render_tree(
+    rtree: &Tree,
+    options: &ConversionOptions,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let (width, height) = options.output_size(svg_width, svg_height);
+    let (scale_x, scale_y) = options.scale_factors(svg_width, svg_height);
+
+    let transform = tiny_skia::Transform::from_scale(scale_x, scale_y);
+    let pixmap = render_pixmap(rtree, (width, height), transform, options)?;
+    options.canvas.tile(pixmap, options.background)
+}
+
+/// Outputs with more pixels than this are rendered in bands on every core.
+const PARALLEL_PIXELS: u64 = 4 << 20;
+
+/// Like [`render_tree`], but a large output is split into horizontal bands rendered in
+/// parallel straight into the output pixmap. Trees can't be shared between threads, so each
+/// thread that takes part parses `svg_data` once and renders bands until none are left.
+///
+/// Anti-aliased edges crossing a band can differ from a single render by one coverage
+/// sample. Trees with filters render in one piece, since resvg clips filters to the pixmap.
+pub fn render_markup(
+    rtree: &Tree,
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let (width, height) = options.output_size(svg_width, svg_height);
+    let threads = rayon::current_num_threads();
+    if threads < 2
+        || height < 2
+        || u64::from(width) * u64::from(height) <= PARALLEL_PIXELS
+        || has_filters(rtree)
+    {
+        return render_tree(rtree, options);
+    }
+    // More bands than threads, so a thread that finishes early can take another.
+    let bands = (threads * 4).min(height as usize) as u32;
+
+    let (scale_x, scale_y) = options.scale_factors(svg_width, svg_height);
+    let transform = tiny_skia::Transform::from_scale(scale_x, scale_y);
+    // Effects like watermarks depend on the whole image, so they're applied once at the end.
+    let mut band_options = options.clone();
+    band_options.effects = EffectOptions::default();
+    let band_height = height.div_ceil(bands);
+    let row_bytes = width as usize * 4;
+
+    let started = Instant::now();
+    let mut pixmap = new_pixmap(width, height)?;
+    let band_data: Vec<_> = pixmap
+        .data_mut()
+        .chunks_mut(row_bytes * band_height as usize)
+        .map(|data| Mutex::new(Some(data)))
+        .collect();
+    let next_band = AtomicUsize::new(0);
+    // Each band is taken once, by whichever thread gets to it first.
+    let take_band = || {
+        let band = next_band.fetch_add(1, Ordering::Relaxed);
+        let data = band_data.get(band)?.lock().unwrap().take()?;
+        Some((band, data))
+    };
+    (0..threads)
+        .into_par_iter()
+        .try_for_each(|_| -> Result<(), String> {
+            // Threads that only start once the bands are gone don't parse at all.
+            let Some(mut next) = take_band() else {
+                return Ok(());
+            };
+            let rtree = parse_tree(svg_data, input_path, options).map_err(|e| e.to_string())?;
+            loop {
+                let (band, data) = next;
+                let rows = (data.len() / row_bytes) as u32;
+                let transform =
+                    transform.post_translate(0.0, -((band as u32 * band_height) as f32));
+                if options.linear_light {
+                    // Supersampling renders into a larger pixmap of its own anyway.
+                    let pixels = render_pixmap(&rtree, (width, rows), transform, &band_options)
+                        .map_err(|e| e.to_string())?;
+                    data.copy_from_slice(pixels.data());
+                } else {
+                    let mut band = tiny_skia::PixmapMut::from_bytes(data, width, rows)
+                        .ok_or("Failed to create pixmap")?;
+                    if let Some([r, g, b]) = options.background {
+                        band.fill(tiny_skia::Color::from_rgba8(r, g, b, 255));
+                    }
+                    render(&rtree, usvg::FitTo::Original, transform, band);
+                }
+                match take_band() {
+                    Some(band) => next = band,
+                    None => return Ok(()),
+                }
+            }
+        })?;
+    log::info!(
+        "rendered in bands input={:?} bands={} took={:?}",
+        input_path,
+        band_data.len(),
+        started.elapsed()
+    );
+    options.effects.apply(&mut pixmap)?;
+    options.canvas.tile(pixmap, options.background)
+}
+
+/// Whether any group in the tree has a filter.
+fn has_filters(rtree: &Tree) -> bool {
+    rtree.root.descendants().any(|node| match *node.borrow() {
+        usvg::NodeKind::Group(ref group) => !group.filters.is_empty(),
+        _ => false,
+    })
+}
+
+/// Widest pixmap tiny-skia can create, since the bytes of a row must fit in an `i32`.
+pub const MAX_PIXMAP_WIDTH: u32 = i32::MAX as u32 / 4;
+
+/// Pixmaps larger than this get a warning, as they may not fit in memory.
+pub const LARGE_PIXMAP_BYTES: u64 = 1 << 30;
+
+/// Bytes of the RGBA pixmap an output of this size renders into.
+pub fn pixmap_bytes((width, height): (u32, u32)) -> u64 {
+    u64::from(width) * u64::from(height) * 4
+}
+
+/// Why a pixmap of this size can't be created, without allocating it.
+pub fn check_pixmap_size((width, height): (u32, u32)) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        Err(format!(
+            "The output would be {}×{} pixels, which is empty",
+            width, height
+        ))
+    } else if width > MAX_PIXMAP_WIDTH {
+        Err(format!(
+            "The output would be {} pixels wide, but at most {} are supported",
+            width, MAX_PIXMAP_WIDTH
+        ))
+    } else if usize::try_from(pixmap_bytes((width, height))).is_err() {
+        Err(format!(
+            "The output would be {}×{} pixels, more than this system can address",
+            width, height
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// A byte count in B, KB, MB or GB.
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    match bytes {
+        bytes if bytes < KB => format!("{} B", bytes),
+        bytes if bytes < KB * KB => format!("{:.1} KB", bytes as f64 / KB as f64),
+        bytes if bytes < KB * KB * KB => format!("{:.1} MB", bytes as f64 / (KB * KB) as f64),
+        bytes => format!("{:.1} GB", bytes as f64 / (KB * KB * KB) as f64),
+    }
+}
+
+/// Creates a transparent pixmap, failing with the reason rather than aborting when it's
+/// too large or the memory isn't there.
+pub fn new_pixmap(width: u32, height: u32) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    check_pixmap_size((width, height))?;
+    let bytes = pixmap_bytes((width, height));
+    if bytes > LARGE_PIXMAP_BYTES {
+        log::warn!(
+            "large output width={} height={} memory={:?}",
+            width,
+            height,
+            format_bytes(bytes)
+        );
+    }
+    // An allocation that fails inside tiny-skia aborts the process, so try it here first.
+    if Vec::<u8>::new().try_reserve_exact(bytes as usize).is_err() {
+        return Err(format!(
+            "Not enough memory for a {}×{} output, which needs {}",
+            width,
+            height,
+            format_bytes(bytes)
+        )
+        .into());
+    }
+    Ok(Pixmap::new(width, height).ok_or("Failed to create pixmap")?)
+}
+
+/// Renders the tree into a `width`x`height` pixmap with the background, linear-light
+/// anti-aliasing and effects from `options`.
+pub fn render_pixmap(
+    rtree: &Tree,
+    (width, height): (u32, u32),
+    transform: tiny_skia::Transform,
+    options: &ConversionOptions,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    // Supersample as much as fits in 64 megapixels.
+    let factor = if options.linear_light {
+        (1..=LINEAR_LIGHT_SAMPLES)
+            .rev()
+            .find(|&f| u64::from(width) * u64::from(height) * u64::from(f * f) <= 64 << 20)
+            .unwrap_or(1)
+    } else {
+        1
+    };
+
+    let (Some(large_width), Some(large_height)) =
+        (width.checked_mul(factor), height.checked_mul(factor))
+    else {
+        return Err(format!(
+            "The output would be {}×{} pixels, which is too large",
+            width, height
+        )
+        .into());
+    };
+    let mut pixmap = new_pixmap(large_width, large_height)?;
+    fill_background(&mut pixmap, options.background);
+    let transform = transform.post_scale(factor as f32, factor as f32);
+    render(rtree, usvg::FitTo::Original, transform, pixmap.as_mut());
+    if factor > 1 {
+        pixmap = downsample_linear(&pixmap, factor).ok_or("Failed to create pixmap")?;
+    }
+    options.effects.apply(&mut pixmap)?;
+
+    Ok(pixmap)
+}
+
+fn fill_background(pixmap: &mut Pixmap, background: Option<[u8; 3]>) {
+    if let Some([r, g, b]) = background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, 255));
+    }
+}
+
+/// Samples per axis rendered for each output pixel with `linear_light`.
+const LINEAR_LIGHT_SAMPLES: u32 = 4;
+
+/// Averages `factor`x`factor` blocks in linear light, so edges blend the way light does
+/// instead of by their sRGB-encoded values.
+fn downsample_linear(large: &Pixmap, factor: u32) -> Option<Pixmap> {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| {
+            let encoded = value as f32 / 255.0;
+            if encoded <= 0.04045 {
+                encoded / 12.92
+            } else {
+                ((encoded + 0.055) / 1.055).powf(2.4)
+            }
+        })
+        .collect();
+    let to_srgb = |linear: f32| {
+        let encoded = if linear <= 0.0031308 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    let (width, height) = (large.width() / factor, large.height() / factor);
+    let mut pixmap = Pixmap::new(width, height)?;
+    let samples = (factor * factor) as f32;
+    let source = large.pixels();
+    for (i, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let mut sum = [0.0f32; 4];
+        for sy in y * factor..(y + 1) * factor {
+            for sx in x * factor..(x + 1) * factor {
+                let color = source[(sy * large.width() + sx) as usize].demultiply();
+                let alpha = color.alpha() as f32 / 255.0;
+                sum[0] += to_linear[color.red() as usize] * alpha;
+                sum[1] += to_linear[color.green() as usize] * alpha;
+                sum[2] += to_linear[color.blue() as usize] * alpha;
+                sum[3] += alpha;
+            }
+        }
+        if sum[3] > 0.0 {
+            let channel = |c: usize| to_srgb(sum[c] / sum[3]);
+            let alpha = (sum[3] / samples * 255.0).round() as u8;
+            *pixel = tiny_skia::ColorU8::from_rgba(channel(0), channel(1), channel(2), alpha)
+                .premultiply();
+        }
+    }
+    Some(pixmap)
+}
+
+/// Error message for a conversion stopped through its cancel flag.
+pub const CANCELLED: &str = "Cancelled";
+
+/// Time a conversion spent in each stage, summed over the files it wrote. Outputs made in
+/// one step, like PDFs, animations and icons, count as rendering.
+#[derive(Clone, Copy, Default)]
+pub struct StageTimings {
+    /// Reading, sanitizing and parsing the input.
+    pub parse: Duration,
+    pub render: Duration,
+    pub encode: Duration,
+    pub total: Duration,
+}
+
+impl std::ops::AddAssign for StageTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.parse += other.parse;
+        self.render += other.render;
+        self.encode += other.encode;
+        self.total += other.total;
+    }
+}
+
+impl std::fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "parse {:.2}s, render {:.2}s, encode {:.2}s, total {:.2}s",
+            self.parse.as_secs_f64(),
+            self.render.as_secs_f64(),
+            self.encode.as_secs_f64(),
+            self.total.as_secs_f64()
+        )
+    }
+}
+
+/// Converts one SVG, expanding tokens in `output_template`, and returns the written paths.
+///
+/// `cancel` is checked between stages; a cancelled conversion removes the files it already
+/// wrote and fails with [`CANCELLED`]. How long each stage took is added to `timings`.
+pub fn convert_file(
+    input_path: &str,
+    output_template: &str,
+    options: &ConversionOptions,
+    cancel: &AtomicBool,
+    timings: &mut StageTimings,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let reading = Instant::now();
+    let svg_data = read_svg(input_path).inspect_err(|e| {
+        log::error!(
+            "read failed input={:?} error={:?}",
+            input_path,
+            e.to_string()
+        );
+    })?;
+    let read = reading.elapsed();
+    let result = convert_svg(
+        &svg_data,
+        input_path,
+        output_template,
+        options,
+        cancel,
+        timings,
+    );
+    timings.parse += read;
+    timings.total += read;
+    result
+}
+
+/// Converts SVG markup like [`convert_file`]; `input_path` only names the output.
+pub fn convert_svg(
+    svg_data: &str,
+    input_path: &str,
+    output_template: &str,
+    options: &ConversionOptions,
+    cancel: &AtomicBool,
+    timings: &mut StageTimings,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    log::info!(
+        "converting input={:?} output={:?} settings={}",
+        input_path,
+        output_template,
+        serde_json::to_string(options).unwrap_or_default()
+    );
+    let result = convert_markup(
+        svg_data,
+        input_path,
+        output_template,
+        options,
+        cancel,
+        timings,
+    )
+    .and_then(|mut written| {
+        if options.data_uri {
+            let encoding = Instant::now();
+            written.extend(write_data_uris(&written)?);
+            timings.encode += encoding.elapsed();
+        }
+        Ok(written)
+    });
+    timings.total += started.elapsed();
+    let took = started.elapsed().as_secs_f64();
+    match &result {
+        Ok(written) => log::info!(
+            "converted input={:?} wrote={:?} parse={:.3}s render={:.3}s encode={:.3}s took={:.3}s",
+            input_path,
+            written,
+            timings.parse.as_secs_f64(),
+            timings.render.as_secs_f64(),
+            timings.encode.as_secs_f64(),
+            took
+        ),
+        Err(e) => log::error!(
+            "conversion failed input={:?} error={:?} took={:.3}s",
+            input_path,
+            e.to_string(),
+            took
+        ),
+    }
+    result
+}
+
+/// Writes the `data:` URI of each written file in a known format to `NAME.EXT.txt`.
+fn write_data_uris(written: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut uri_paths = Vec::new();
+    for path in written {
+        let Some(format) = OutputFormat::from_path(path) else {
+            continue;
+        };
+        let uri_path = format!("{}.txt", path);
+        std::fs::write(&uri_path, encode::data_uri(&std::fs::read(path)?, format))?;
+        uri_paths.push(uri_path);
+    }
+    Ok(uri_paths)
+}
+
+fn convert_markup(
+    svg_data: &str,
+    input_path: &str,
+    output_template: &str,
+    options: &ConversionOptions,
+    cancel: &AtomicBool,
+    timings: &mut StageTimings,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let check_cancel = |written: &[String]| {
+        if !cancel.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        for path in written {
+            let _ = std::fs::remove_file(path);
+        }
+        Err(CANCELLED)
+    };
+
+    let parsing = Instant::now();
+    let sanitized;
+    let svg_data = if options.parse.sanitize {
+        sanitized = sanitize::sanitize(svg_data)
+            .map_err(|e| ParseError::new(&e, svg_data))?
+            .0;
+        sanitized.as_str()
+    } else {
+        svg_data
+    };
+
+    let rtree = parse_tree(svg_data, input_path, options)?;
+    timings.parse += parsing.elapsed();
+    check_cancel(&[])?;
+    let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let mut written = Vec::new();
+
+    if options.parse.sanitize && options.parse.write_sanitized {
+        let output_path = template::expand(
+            output_template,
+            input_path,
+            options,
+            (svg_width.ceil() as u32, svg_height.ceil() as u32),
+            1.0,
+        );
+        let sanitized_path = sanitized_svg_path(&output_path);
+        create_parent_dir(&sanitized_path)?;
+        std::fs::write(&sanitized_path, svg_data)?;
+        written.push(sanitized_path);
+    }
+
+    if options.canvas.split_layers && options.canvas.element.is_none() {
+        if options.format == OutputFormat::Pdf {
+            return Err("Layers can't be split into PDF output".into());
+        }
+        let ids = canvas::layer_ids(&rtree, options.canvas.layer_pattern.as_deref());
+        if ids.is_empty() {
+            return Err("No layers to export: nothing has a matching id".into());
+        }
+        let layer_template = if output_template.contains("{id}") {
+            output_template.to_string()
+        } else {
+            layer_template(output_template)
+        };
+        // Each layer is a conversion of its own, of the already sanitized markup.
+        let mut layer = options.clone();
+        layer.parse.sanitize = false;
+        layer.parse.write_sanitized = false;
+        for id in ids {
+            layer.canvas.element = Some(id);
+            match convert_markup(
+                svg_data,
+                input_path,
+                &layer_template,
+                &layer,
+                cancel,
+                timings,
+            ) {
+                Ok(paths) => written.extend(paths),
+                Err(e) => {
+                    check_cancel(&written)?;
+                    return Err(e);
+                }
+            }
+        }
+        return Ok(written);
+    }
+
+    if let Some(package) = options.package {
+        let output_path = template::expand(
+            output_template,
+            input_path,
+            options,
+            (svg_width.ceil() as u32, svg_height.ceil() as u32),
+            1.0,
+        );
+        let dir = Path::new(&output_path).with_extension("");
+        let rendering = Instant::now();
+        written.extend(package.write(&rtree, input_path, &dir, options)?);
+        timings.render += rendering.elapsed();
+        check_cancel(&written)?;
+        return Ok(written);
+    }
+
+    if options.format == OutputFormat::PngSequence {
+        let output_path = template::expand(
+            output_template,
+            input_path,
+            options,
+            (svg_width.ceil() as u32, svg_height.ceil() as u32),
+            1.0,
+        );
+        let dir = Path::new(&output_path).with_extension("");
+        let rendering = Instant::now();
+        let saved =
+            animation::save_frames(svg_data, input_path, &dir, options, &|| check_cancel(&[]))?;
+        timings.render += rendering.elapsed();
+        written.extend(saved);
+        check_cancel(&written)?;
+        return Ok(written);
+    }
+
+    if options.format == OutputFormat::Pdf {
+        let rendering = Instant::now();
+        let pdf = pdf(&rtree)?;
+        timings.render += rendering.elapsed();
+        let output_path = template::expand(
+            output_template,
+            input_path,
+            options,
+            (svg_width.ceil() as u32, svg_height.ceil() as u32),
+            1.0,
+        );
+        create_parent_dir(&output_path)?;
+        std::fs::write(&output_path, pdf)?;
+        written.push(output_path);
+        return Ok(written);
+    }
+
+    if options.format == OutputFormat::Svg && options.outline_text_only {
+        let output_path = template::expand(
+            output_template,
+            input_path,
+            options,
+            (svg_width.ceil() as u32, svg_height.ceil() as u32),
+            1.0,
+        );
+        create_parent_dir(&output_path)?;
+        let rendering = Instant::now();
+        outline::save(svg_data, input_path, &output_path, options)?;
+        timings.render += rendering.elapsed();
+        written.push(output_path);
+        return Ok(written);
+    }
+
+    let factors = if options.density_variants.is_empty() {
+        vec![1.0]
+    } else {
+        options.density_variants.clone()
+    };
+
+    let mut variants = Vec::new();
+    for factor in factors {
+        let variant = options.at_density(factor);
+        let mut output_path = template::expand(
+            output_template,
+            input_path,
+            &variant,
+            variant
+                .canvas
+                .tiled_size(variant.output_size(svg_width, svg_height))?,
+            variant.scale_factors(svg_width, svg_height).0,
+        );
+        if !options.density_variants.is_empty() && !output_template.contains("{scale}") {
+            output_path = density_variant_path(&output_path, factor);
+        }
+
+        create_parent_dir(&output_path)?;
+        let saved = if variant.format.is_animated() {
+            // Animations are rendered frame by frame from the markup, not the parsed tree.
+            let rendering = Instant::now();
+            let saved = animation::save(svg_data, input_path, &output_path, &variant, &|| {
+                check_cancel(&[])
+            });
+            timings.render += rendering.elapsed();
+            saved
+        } else {
+            save_tree(
+                &rtree,
+                svg_data,
+                input_path,
+                &output_path,
+                &variant,
+                &|| check_cancel(&[]),
+                timings,
+            )
+        };
+        if saved.is_ok() {
+            written.push(output_path.clone());
+            variants.push((factor, output_path));
+        }
+        check_cancel(&written)?;
+        saved?;
+    }
+
+    if let Some(snippet) = options.snippet {
+        let size = options
+            .canvas
+            .tiled_size(options.output_size(svg_width, svg_height))?;
+        written.push(snippet.write(input_path, &variants, size)?);
+    }
+
+    Ok(written)
+}
+
+/// The tree as a vector PDF, sized like the SVG at 96 DPI.
+///
+/// svg2pdf is built on an older usvg, so it's given the SVG usvg made of the input rather
+/// than the input itself: safe mode, fonts, recoloring and canvas options all apply, and
+/// images go over embedded instead of being loaded again.
+pub fn pdf(rtree: &Tree) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let svg_data = svg_writer::to_svg(rtree, (rtree.size.width(), rtree.size.height()));
+    let pdf_options = svg2pdf::Options {
+        dpi: 96.0,
+        ..Default::default()
+    };
+    Ok(svg2pdf::convert_str(&svg_data, pdf_options)?)
+}
+
+fn create_parent_dir(output_path: &str) -> std::io::Result<()> {
+    match Path::new(output_path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+fn save_tree(
+    rtree: &Tree,
+    svg_data: &str,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+    timings: &mut StageTimings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rendering = Instant::now();
+    let saved = match options.format {
+        OutputFormat::Ico => icon::save_ico(rtree, output_path, options),
+        OutputFormat::Icns => icon::save_icns(rtree, output_path, options),
+        OutputFormat::VectorDrawable => vector_drawable::save(rtree, output_path, options),
+        OutputFormat::Svg => svg_writer::save(rtree, input_path, output_path, options),
+        _ => {
+            let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+            let size = options
+                .canvas
+                .tiled_size(options.output_size(svg_width, svg_height))?;
+            if large_output::is_needed(size) {
+                large_output::save_png(rtree, input_path, output_path, options, check_cancel)
+            } else {
+                let pixmap = render_markup(rtree, svg_data, input_path, options)?;
+                timings.render += rendering.elapsed();
+                // Rendering is the slow part; skip encoding if cancelled meanwhile.
+                check_cancel()?;
+                let encoding = Instant::now();
+                let saved = encode::save(&pixmap, input_path, output_path, options);
+                timings.encode += encoding.elapsed();
+                return saved;
+            }
+        }
+    };
+    timings.render += rendering.elapsed();
+    saved
+}
+
+/// Characters of a long line shown on either side of a parse error, so minified SVGs
+/// don't print as one huge line.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Markup that isn't well-formed XML, with where the problem is.
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based line and column of the problem in the markup.
+    pub line: u32,
+    pub column: u32,
+    /// The offending line and the one before it, numbered, with a caret under the column.
+    pub snippet: String,
+}
+
+impl ParseError {
+    pub fn new(error: &roxmltree::Error, svg_data: &str) -> Self {
+        let position = error.pos();
+        // Positions in the message, including a nested cause's, are shown separately.
+        let message = error
+            .to_string()
+            .replacen(&format!(" at {}:{}", position.row, position.col), "", 1)
+            .replacen(" cause ", ": ", 1);
+        let message = match message.rsplit_once(" at ") {
+            Some((text, at))
+                if at.split(':').count() == 2
+                    && at.split(':').all(|n| n.parse::<u32>().is_ok()) =>
+            {
+                text.to_string()
+            }
+            _ => message,
+        };
+        Self {
+            message,
+            line: position.row,
+            column: position.col,
+            snippet: snippet(svg_data, position.row as usize, position.col as usize),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}\n{}",
+            self.message, self.line, self.column, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn snippet(svg_data: &str, line: usize, column: usize) -> String {
+    let lines: Vec<Vec<char>> = svg_data
+        .lines()
+        .skip(line.saturating_sub(2))
+        .take(if line > 1 { 2 } else { 1 })
+        // Tabs would throw off the caret.
+        .map(|text| {
+            text.chars()
+                .map(|c| if c == '\t' { ' ' } else { c })
+                .collect()
+        })
+        .collect();
+    let caret = column.saturating_sub(1);
+    let start = caret.saturating_sub(SNIPPET_CONTEXT);
+    let number_width = line.to_string().len();
+
+    let mut snippet = String::new();
+    for (index, text) in lines.iter().enumerate() {
+        let number = line + 1 + index - lines.len();
+        let end = (caret + SNIPPET_CONTEXT).min(text.len());
+        let shown: String = text.get(start..end).unwrap_or_default().iter().collect();
+        let before = if start > 0 { "…" } else { "" };
+        let after = if end < text.len() { "…" } else { "" };
+        snippet += &format!(
+            "{:>width$} | {}{}{}\n",
+            number,
+            before,
+            shown,
+            after,
+            width = number_width
+        );
+    }
+    let offset = caret - start + usize::from(start > 0);
+    snippet += &format!(
+        "{:>width$} | {}^",
+        "",
+        " ".repeat(offset),
+        width = number_width
+    );
+    snippet
+}
+
+/// Broad cause of a failed conversion, so scripts can tell failures apart.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    /// The input isn't valid SVG, or an input image can't be decoded.
+    Parse,
+    /// A file couldn't be read or written, or a download failed.
+    Io,
+    /// The rendered image couldn't be encoded in the output format.
+    Encode,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn of(error: &(dyn std::error::Error + 'static)) -> Self {
+        if let Some(error) = error.downcast_ref::<image::ImageError>() {
+            return match error {
+                image::ImageError::Decoding(_) => ErrorKind::Parse,
+                image::ImageError::IoError(_) => ErrorKind::Io,
+                _ => ErrorKind::Encode,
+            };
+        }
+        if error.is::<usvg::Error>()
+            || error.is::<ParseError>()
+            || error.is::<usvg::roxmltree::Error>()
+            || error.is::<std::string::FromUtf8Error>()
+        {
+            ErrorKind::Parse
+        } else if error.is::<std::io::Error>() || error.is::<ureq::Error>() {
+            ErrorKind::Io
+        } else if error.is::<png::EncodingError>()
+            || error.is::<oxipng::PngError>()
+            || error.is::<ravif::Error>()
+        {
+            ErrorKind::Encode
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// Why a batch item failed. Unlike the error itself it can be sent between threads.
+#[derive(Clone, Debug)]
+pub struct BatchError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Line and column of a parse error.
+    pub location: Option<(u32, u32)>,
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for BatchError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        Self {
+            kind: ErrorKind::of(&*error),
+            message: error.to_string(),
+            location: error
+                .downcast_ref::<ParseError>()
+                .map(|error| (error.line, error.column)),
+        }
+    }
+}
+
+/// Outcome of one batch item: the written paths, or why it failed.
+pub type BatchResult = Result<Vec<String>, BatchError>;
+
+/// Progress of one batch item, reported as it happens.
+pub enum BatchProgress<'a> {
+    Started,
+    Finished(&'a BatchResult),
+}
+
+/// One item of a batch, with options of its own so items can differ.
+#[derive(Clone)]
+pub struct BatchJob {
+    pub input: String,
+    pub output_template: String,
+    pub options: ConversionOptions,
+}
+
+/// Converts the jobs in parallel on `threads` threads (0 uses every core), calling
+/// `on_progress` with each item's index as it starts and finishes. Items not yet finished
+/// when `cancel` is set fail with [`CANCELLED`].
+pub fn convert_batch(
+    jobs: &[BatchJob],
+    threads: usize,
+    cancel: &AtomicBool,
+    on_progress: impl Fn(usize, BatchProgress) + Sync,
+) -> Result<Vec<BatchResult>, Box<dyn std::error::Error>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+
+    Ok(pool.install(|| {
+        jobs.par_iter()
+            .enumerate()
+            .map(|(index, job)| {
+                on_progress(index, BatchProgress::Started);
+                let result = convert_file(
+                    &job.input,
+                    &job.output_template,
+                    &job.options,
+                    cancel,
+                    &mut StageTimings::default(),
+                )
+                .map_err(BatchError::from);
+                on_progress(index, BatchProgress::Finished(&result));
+                result
+            })
+            .collect()
+    }))
+}
+
+/// Path of a density variant: `icon.png` at 2x becomes `icon@2x.png`; 1x keeps the path.
+pub fn density_variant_path(output_path: &str, factor: f32) -> String {
+    if factor == 1.0 {
+        return output_path.to_string();
+    }
+
+    let path = Path::new(output_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}@{}x.{}", stem, factor, extension.to_string_lossy()),
+        None => format!("{}@{}x", stem, factor),
+    };
+    path.with_file_name(file_name).display().to_string()
+}
+
+/// Output template for one exported layer: `icons.png` becomes `icons-{id}.png`.
+fn layer_template(output_template: &str) -> String {
+    let path = Path::new(output_template);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{{id}}.{}", stem, extension.to_string_lossy()),
+        None => format!("{}-{{id}}", stem),
+    };
+    path.with_file_name(file_name).display().to_string()
+}
+
+/// Path the sanitized SVG is written to: `icon.png` becomes `icon.sanitized.svg`, so the
+/// input is never overwritten.
+fn sanitized_svg_path(output_path: &str) -> String {
+    let path = Path::new(output_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.sanitized.svg", stem))
+        .display()
+        .to_string()
+}
+
+/// Output template for a batch written into `output_dir`, named after each input.
+pub fn batch_output_template(output_dir: &Path, format: OutputFormat) -> String {
+    // Cleaned SVGs would otherwise replace inputs converted in place.
+    let suffix = if format == OutputFormat::Svg {
+        ".min"
+    } else {
+        ""
+    };
+    output_dir
+        .join(format!("{{name}}{}.{}", suffix, format.extension()))
+        .display()
+        .to_string()
+}
+
+/// Collects the `.svg` and `.svgz` files in `dir`, descending into subfolders when `recursive` is set.
+pub fn find_svgs(dir: &Path, recursive: bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                found.extend(find_svgs(&path, true)?);
+            }
+        } else if is_svg_path(&path) {
+            found.push(path);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
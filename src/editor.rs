@@ -0,0 +1,58 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+/// How long typing has to pause before the edited source is re-rendered.
+const DEBOUNCE: Duration = Duration::from_millis(400);
+
+/// Text editor for the input's SVG source.
+///
+/// Edits are only handed back once typing pauses, so the preview isn't re-rendered on every
+/// keystroke.
+pub struct SourceEditor {
+    text: String,
+    /// Input path the text was loaded from, to notice when the input changes.
+    loaded_from: String,
+    edited: Option<Instant>,
+}
+
+impl SourceEditor {
+    pub fn new(text: String, loaded_from: String) -> Self {
+        Self {
+            text,
+            loaded_from,
+            edited: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn loaded_from(&self) -> &str {
+        &self.loaded_from
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::both().show(ui, |ui| {
+            let response = ui.add(
+                egui::TextEdit::multiline(&mut self.text)
+                    .code_editor()
+                    .desired_width(f32::INFINITY),
+            );
+            if response.changed() {
+                self.edited = Some(Instant::now());
+            }
+        });
+    }
+
+    /// The edited text once typing has paused, scheduling a repaint to check again until then.
+    pub fn settled(&mut self, ctx: &egui::Context) -> Option<&str> {
+        let elapsed = self.edited?.elapsed();
+        if elapsed < DEBOUNCE {
+            ctx.request_repaint_after(DEBOUNCE - elapsed);
+            return None;
+        }
+        self.edited = None;
+        Some(&self.text)
+    }
+}
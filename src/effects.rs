@@ -0,0 +1,228 @@
+use crate::convert::{is_svg_path, load_tree, ConversionOptions};
+use resvg::tiny_skia::{FilterQuality, Pixmap, PixmapPaint, Transform};
+use std::path::PathBuf;
+
+/// Channel weights used to compute luma for grayscale output.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum LumaWeights {
+    /// ITU-R BT.709, matching sRGB primaries.
+    Rec709,
+    /// ITU-R BT.601, the classic video/JPEG weights.
+    Rec601,
+    /// Equal weights for red, green and blue.
+    Average,
+}
+
+impl LumaWeights {
+    pub const ALL: [LumaWeights; 3] = [
+        LumaWeights::Rec709,
+        LumaWeights::Rec601,
+        LumaWeights::Average,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LumaWeights::Rec709 => "Rec. 709",
+            LumaWeights::Rec601 => "Rec. 601",
+            LumaWeights::Average => "Average",
+        }
+    }
+
+    fn weights(self) -> [f32; 3] {
+        match self {
+            LumaWeights::Rec709 => [0.2126, 0.7152, 0.0722],
+            LumaWeights::Rec601 => [0.299, 0.587, 0.114],
+            LumaWeights::Average => [1.0 / 3.0; 3],
+        }
+    }
+}
+
+/// Where a watermark is placed on the output.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 5] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+        Corner::Center,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+            Corner::Center => "Center",
+        }
+    }
+}
+
+/// A PNG or SVG stamped over the rendered output.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Watermark {
+    pub path: PathBuf,
+    pub corner: Corner,
+    /// Watermark width as a fraction of the output width.
+    pub scale: f32,
+    /// 0 (invisible) to 1 (opaque).
+    pub opacity: f32,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            corner: Corner::BottomRight,
+            scale: 0.25,
+            opacity: 0.5,
+        }
+    }
+}
+
+/// Post-processing applied to the rendered pixels before they are encoded.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EffectOptions {
+    /// Convert to grayscale using these weights.
+    pub grayscale: Option<LumaWeights>,
+    /// Invert red, green and blue, keeping alpha, e.g. for quick dark-mode diagrams.
+    pub invert: bool,
+    /// Paint everything in this one color, keeping only the alpha channel, for template or
+    /// mask-style icons. Overrides grayscale and invert.
+    pub tint: Option<[u8; 3]>,
+    /// Multiplies the alpha of every pixel, 0 (transparent) to 1 (unchanged), e.g. for
+    /// faded or disabled icon variants.
+    pub opacity: f32,
+    /// Image composited over the result, after the other effects.
+    pub watermark: Option<Watermark>,
+}
+
+impl Default for EffectOptions {
+    fn default() -> Self {
+        Self {
+            grayscale: None,
+            invert: false,
+            tint: None,
+            opacity: 1.0,
+            watermark: None,
+        }
+    }
+}
+
+impl EffectOptions {
+    pub fn apply(&self, pixmap: &mut Pixmap) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(weights) = self.grayscale {
+            grayscale(pixmap, weights);
+        }
+        if self.invert {
+            invert(pixmap);
+        }
+        if let Some(color) = self.tint {
+            tint(pixmap, color);
+        }
+        if self.opacity < 1.0 {
+            fade(pixmap, self.opacity.max(0.0));
+        }
+        if let Some(watermark) = &self.watermark {
+            stamp(pixmap, watermark)?;
+        }
+        Ok(())
+    }
+}
+
+fn grayscale(pixmap: &mut Pixmap, weights: LumaWeights) {
+    let [wr, wg, wb] = weights.weights();
+    // Luma is a weighted sum, so it can be taken straight from premultiplied channels.
+    for pixel in pixmap.data_mut().chunks_exact_mut(4) {
+        let luma = wr * pixel[0] as f32 + wg * pixel[1] as f32 + wb * pixel[2] as f32;
+        let luma = (luma.round() as u8).min(pixel[3]);
+        pixel[..3].fill(luma);
+    }
+}
+
+fn invert(pixmap: &mut Pixmap) {
+    // With premultiplied alpha, the inverse of a channel is `alpha - channel`.
+    for pixel in pixmap.data_mut().chunks_exact_mut(4) {
+        let alpha = pixel[3];
+        for channel in &mut pixel[..3] {
+            *channel = alpha - (*channel).min(alpha);
+        }
+    }
+}
+
+fn tint(pixmap: &mut Pixmap, color: [u8; 3]) {
+    for pixel in pixmap.data_mut().chunks_exact_mut(4) {
+        let alpha = pixel[3] as f32 / 255.0;
+        for (channel, value) in pixel[..3].iter_mut().zip(color) {
+            *channel = (value as f32 * alpha).round() as u8;
+        }
+    }
+}
+
+fn fade(pixmap: &mut Pixmap, opacity: f32) {
+    // Premultiplied, so color fades along with alpha.
+    for channel in pixmap.data_mut() {
+        *channel = (*channel as f32 * opacity).round() as u8;
+    }
+}
+
+fn stamp(pixmap: &mut Pixmap, watermark: &Watermark) -> Result<(), Box<dyn std::error::Error>> {
+    let width = (pixmap.width() as f32 * watermark.scale).round().max(1.0);
+    let path = watermark.path.to_string_lossy();
+    // SVGs are rendered straight at the final size; bitmaps are resampled.
+    let image = if is_svg_path(&watermark.path) {
+        let rtree = load_tree(&path, &ConversionOptions::default())?;
+        let scale = width / rtree.size.width() as f32;
+        let height = (rtree.size.height() as f32 * scale).ceil() as u32;
+        let mut image =
+            Pixmap::new(width as u32, height.max(1)).ok_or("Failed to create watermark pixmap")?;
+        resvg::render(
+            &rtree,
+            usvg::FitTo::Original,
+            Transform::from_scale(scale, scale),
+            image.as_mut(),
+        );
+        image
+    } else {
+        Pixmap::load_png(&watermark.path)
+            .map_err(|e| format!("Failed to load watermark {}: {}", path, e))?
+    };
+
+    let scale = width / image.width() as f32;
+    let (stamp_width, stamp_height) = (width, image.height() as f32 * scale);
+    let margin = (pixmap.width().min(pixmap.height()) as f32 * 0.02).round();
+    let (right, bottom) = (
+        pixmap.width() as f32 - stamp_width - margin,
+        pixmap.height() as f32 - stamp_height - margin,
+    );
+    let (x, y) = match watermark.corner {
+        Corner::TopLeft => (margin, margin),
+        Corner::TopRight => (right, margin),
+        Corner::BottomLeft => (margin, bottom),
+        Corner::BottomRight => (right, bottom),
+        Corner::Center => (
+            (pixmap.width() as f32 - stamp_width) / 2.0,
+            (pixmap.height() as f32 - stamp_height) / 2.0,
+        ),
+    };
+
+    let paint = PixmapPaint {
+        opacity: watermark.opacity.clamp(0.0, 1.0),
+        quality: FilterQuality::Bicubic,
+        ..Default::default()
+    };
+    let transform = Transform::from_scale(scale, scale).post_translate(x, y);
+    pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+    Ok(())
+}
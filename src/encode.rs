@@ -0,0 +1,769 @@
+use crate::convert::{is_url, ConversionOptions};
+use crate::icc;
+use base64::Engine;
+use color_quant::NeuQuant;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::gif::GifEncoder;
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::codecs::tiff::TiffEncoder;
+use image::{ColorType, Delay, Frame, RgbaImage};
+use ravif::{Img, RGBA8};
+use resvg::tiny_skia::{Pixmap, PremultipliedColorU8};
+use std::collections::HashMap;
+use std::io::{BufWriter, Cursor, Write};
+use std::num::NonZeroU8;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Tiff,
+    Bmp,
+    Avif,
+    Ico,
+    Icns,
+    Pdf,
+    /// Android VectorDrawable XML.
+    VectorDrawable,
+    /// The parsed SVG written back out, cleaned up and minified.
+    Svg,
+    /// Animated when the SVG has SMIL or CSS animations.
+    Gif,
+    /// Animated PNG, keeping full alpha and 24-bit color unlike GIF.
+    Apng,
+    /// A folder named after the output holding one `frame_0001.png` per animation frame.
+    PngSequence,
+    /// H.264 video, encoded by ffmpeg.
+    Mp4,
+    /// VP9 video with alpha, encoded by ffmpeg.
+    Webm,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 16] = [
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Tiff,
+        OutputFormat::Bmp,
+        OutputFormat::Avif,
+        OutputFormat::Ico,
+        OutputFormat::Icns,
+        OutputFormat::Pdf,
+        OutputFormat::VectorDrawable,
+        OutputFormat::Svg,
+        OutputFormat::Gif,
+        OutputFormat::Apng,
+        OutputFormat::PngSequence,
+        OutputFormat::Mp4,
+        OutputFormat::Webm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "PNG",
+            OutputFormat::Jpeg => "JPEG",
+            OutputFormat::Webp => "WebP",
+            OutputFormat::Tiff => "TIFF",
+            OutputFormat::Bmp => "BMP",
+            OutputFormat::Avif => "AVIF",
+            OutputFormat::Ico => "ICO",
+            OutputFormat::Icns => "ICNS",
+            OutputFormat::Pdf => "PDF",
+            OutputFormat::VectorDrawable => "VectorDrawable",
+            OutputFormat::Svg => "SVG (cleaned)",
+            OutputFormat::Gif => "GIF",
+            OutputFormat::Apng => "APNG",
+            OutputFormat::PngSequence => "PNG sequence",
+            OutputFormat::Mp4 => "MP4",
+            OutputFormat::Webm => "WebM",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png | OutputFormat::Apng | OutputFormat::PngSequence => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Ico => "ico",
+            OutputFormat::Icns => "icns",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::VectorDrawable => "xml",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Gif => "gif",
+            OutputFormat::Mp4 => "mp4",
+            OutputFormat::Webm => "webm",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png | OutputFormat::PngSequence => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Tiff => "image/tiff",
+            OutputFormat::Bmp => "image/bmp",
+            OutputFormat::Avif => "image/avif",
+            OutputFormat::Ico => "image/x-icon",
+            OutputFormat::Icns => "image/icns",
+            OutputFormat::Pdf => "application/pdf",
+            OutputFormat::VectorDrawable => "application/xml",
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Gif => "image/gif",
+            OutputFormat::Apng => "image/apng",
+            OutputFormat::Mp4 => "video/mp4",
+            OutputFormat::Webm => "video/webm",
+        }
+    }
+
+    /// Whether the format is rendered frame by frame from SMIL and CSS animations.
+    pub fn is_animated(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Gif | OutputFormat::Apng | OutputFormat::Mp4 | OutputFormat::Webm
+        )
+    }
+
+    /// Infers the format from a path's extension, if it names a supported one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::Webp),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            "bmp" => Some(OutputFormat::Bmp),
+            "avif" => Some(OutputFormat::Avif),
+            "ico" => Some(OutputFormat::Ico),
+            "icns" => Some(OutputFormat::Icns),
+            "pdf" => Some(OutputFormat::Pdf),
+            "xml" => Some(OutputFormat::VectorDrawable),
+            "svg" => Some(OutputFormat::Svg),
+            "gif" => Some(OutputFormat::Gif),
+            "apng" => Some(OutputFormat::Apng),
+            "mp4" => Some(OutputFormat::Mp4),
+            "webm" => Some(OutputFormat::Webm),
+            _ => None,
+        }
+    }
+}
+
+/// zlib effort used for PNG image data.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+impl PngCompression {
+    pub const ALL: [PngCompression; 3] = [
+        PngCompression::Fast,
+        PngCompression::Default,
+        PngCompression::Best,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PngCompression::Fast => "Fast",
+            PngCompression::Default => "Default",
+            PngCompression::Best => "Best",
+        }
+    }
+
+    fn to_png(self) -> png::Compression {
+        match self {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Default,
+            PngCompression::Best => png::Compression::Best,
+        }
+    }
+
+    fn to_flate2(self) -> flate2::Compression {
+        match self {
+            PngCompression::Fast => flate2::Compression::fast(),
+            PngCompression::Default => flate2::Compression::default(),
+            PngCompression::Best => flate2::Compression::best(),
+        }
+    }
+}
+
+/// Scanline filter applied before compression; `Adaptive` picks one per row.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+    Adaptive,
+}
+
+impl PngFilter {
+    pub const ALL: [PngFilter; 6] = [
+        PngFilter::None,
+        PngFilter::Sub,
+        PngFilter::Up,
+        PngFilter::Average,
+        PngFilter::Paeth,
+        PngFilter::Adaptive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PngFilter::None => "None",
+            PngFilter::Sub => "Sub",
+            PngFilter::Up => "Up",
+            PngFilter::Average => "Average",
+            PngFilter::Paeth => "Paeth",
+            PngFilter::Adaptive => "Adaptive",
+        }
+    }
+}
+
+/// Text written into PNG output as `tEXt` chunks, or `iTXt` when it isn't Latin-1.
+#[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MetadataOptions {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub copyright: Option<String>,
+    /// Record the input's file name under the `Source` keyword.
+    pub source_filename: bool,
+}
+
+impl MetadataOptions {
+    /// Keyword and text pairs for an image converted from `input_path`.
+    fn text_chunks(&self, input_path: &str) -> Vec<(&'static str, String)> {
+        let input_path = if is_url(input_path) {
+            input_path.split(['?', '#']).next().unwrap_or(input_path)
+        } else {
+            input_path
+        };
+        let source = Path::new(input_path)
+            .file_name()
+            .filter(|_| self.source_filename)
+            .map(|name| name.to_string_lossy().into_owned());
+        [
+            ("Title", self.title.clone()),
+            ("Author", self.author.clone()),
+            ("Copyright", self.copyright.clone()),
+            ("Source", source),
+        ]
+        .into_iter()
+        .filter_map(|(keyword, text)| Some((keyword, text?)))
+        .collect()
+    }
+}
+
+/// Encodes the pixmap as `options.format` into `output_path`; `input_path` names the source
+/// in metadata.
+pub fn save(
+    pixmap: &Pixmap,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let streams = matches!(
+        options.format,
+        OutputFormat::Png | OutputFormat::PngSequence
+    ) && !options.png_optimize
+        && options.icc_profile.is_none();
+    if !streams {
+        std::fs::write(output_path, encode(pixmap, input_path, options)?)?;
+        return Ok(());
+    }
+
+    // Optimizing and embedding a profile rewrite the finished PNG; otherwise it goes
+    // straight to the file as it's encoded.
+    let result = std::fs::File::create(output_path)
+        .map_err(Into::into)
+        .and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_png(pixmap, &mut file, input_path, options)?;
+            file.flush()?;
+            Ok(())
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(output_path);
+    }
+    result
+}
+
+/// A `data:` URI holding `data` in `format`, for inlining in HTML or CSS.
+pub fn data_uri(data: &[u8], format: OutputFormat) -> String {
+    format!(
+        "data:{};base64,{}",
+        format.mime_type(),
+        base64::engine::general_purpose::STANDARD.encode(data)
+    )
+}
+
+/// Longest side libwebp encodes.
+const WEBP_MAX_SIDE: u32 = 16383;
+
+/// The pixmap encoded as `options.format`, like [`save`] writes it.
+pub fn encode(
+    pixmap: &Pixmap,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let data = match options.format {
+        OutputFormat::Png | OutputFormat::Apng | OutputFormat::PngSequence => {
+            encode_png(pixmap, input_path, options)?
+        }
+        OutputFormat::Jpeg => {
+            let rgb = flatten(pixmap, options.background.unwrap_or([255, 255, 255]));
+            let mut data = Vec::new();
+            let mut encoder = JpegEncoder::new_with_quality(&mut data, options.quality);
+            if let Some(dpi) = options.embedded_dpi() {
+                encoder.set_pixel_density(PixelDensity::dpi(dpi.round() as u16));
+            }
+            encoder.encode(&rgb, pixmap.width(), pixmap.height(), ColorType::Rgb8)?;
+            if let Some(profile) = &options.icc_profile {
+                data = icc::embed_in_jpeg(&data, profile)?;
+            }
+            data
+        }
+        OutputFormat::Webp => {
+            if pixmap.width() > WEBP_MAX_SIDE || pixmap.height() > WEBP_MAX_SIDE {
+                return Err(format!(
+                    "WebP images are at most {} pixels on each side, and this one is {}×{}",
+                    WEBP_MAX_SIDE,
+                    pixmap.width(),
+                    pixmap.height()
+                )
+                .into());
+            }
+            let rgba = unpremultiply(pixmap);
+            let encoder = webp::Encoder::from_rgba(&rgba, pixmap.width(), pixmap.height());
+            // `encode` and `encode_lossless` unwrap this, so failures would panic.
+            let quality = if options.webp_lossless {
+                75.0
+            } else {
+                options.quality as f32
+            };
+            let data = encoder
+                .encode_simple(options.webp_lossless, quality)
+                .map_err(|e| format!("Encoding the WebP failed: {:?}", e))?;
+            data.to_vec()
+        }
+        OutputFormat::Tiff => {
+            // TIFF offsets are patched after the image data, so the encoder needs to seek.
+            let mut writer = Cursor::new(Vec::new());
+            TiffEncoder::new(&mut writer).encode(
+                &unpremultiply(pixmap),
+                pixmap.width(),
+                pixmap.height(),
+                ColorType::Rgba8,
+            )?;
+            writer.into_inner()
+        }
+        OutputFormat::Bmp => {
+            let mut data = Vec::new();
+            BmpEncoder::new(&mut data).encode(
+                &unpremultiply(pixmap),
+                pixmap.width(),
+                pixmap.height(),
+                ColorType::Rgba8,
+            )?;
+            data
+        }
+        OutputFormat::Avif => {
+            let pixels: Vec<RGBA8> = pixmap
+                .pixels()
+                .iter()
+                .map(|pixel| {
+                    let color = pixel.demultiply();
+                    RGBA8::new(color.red(), color.green(), color.blue(), color.alpha())
+                })
+                .collect();
+            let encoded = ravif::Encoder::new()
+                .with_quality(options.quality as f32)
+                .with_speed(options.avif_speed)
+                .encode_rgba(Img::new(
+                    &pixels[..],
+                    pixmap.width() as usize,
+                    pixmap.height() as usize,
+                ))?;
+            encoded.avif_file
+        }
+        OutputFormat::Gif => {
+            let mut data = Vec::new();
+            GifEncoder::new(&mut data).encode_frame(gif_frame(pixmap, options.animation.fps)?)?;
+            data
+        }
+        OutputFormat::Ico
+        | OutputFormat::Icns
+        | OutputFormat::Pdf
+        | OutputFormat::VectorDrawable
+        | OutputFormat::Svg
+        | OutputFormat::Mp4
+        | OutputFormat::Webm => {
+            return Err(format!(
+                "{} output is not encoded from a pixmap",
+                options.format.name()
+            )
+            .into())
+        }
+    };
+
+    Ok(data)
+}
+
+fn encode_png(
+    pixmap: &Pixmap,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    write_png(pixmap, &mut data, input_path, options)?;
+    if options.png_optimize {
+        data = crush_png(&data)?;
+    }
+    if let Some(profile) = &options.icc_profile {
+        data = icc::embed_in_png(&data, profile)?;
+    }
+
+    Ok(data)
+}
+
+/// Encodes the pixmap as a PNG into `data`. Plain RGBA rows are unpremultiplied one at a
+/// time as the encoder takes them, so only palette and interlaced PNGs copy every pixel.
+fn write_png<W: Write>(
+    pixmap: &Pixmap,
+    data: W,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut info = png::Info::with_size(pixmap.width(), pixmap.height());
+    info.bit_depth = png::BitDepth::Eight;
+    info.interlaced = options.png_interlaced;
+    if options.png_palette_size.is_none() && !options.png_interlaced {
+        info.color_type = png::ColorType::Rgba;
+        let encoder = png_encoder(data, info, input_path, options)?;
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+        let mut row = Vec::with_capacity(pixmap.width() as usize * 4);
+        for pixels in pixmap.pixels().chunks_exact(pixmap.width() as usize) {
+            row.clear();
+            unpremultiply_into(pixels, &mut row);
+            stream.write_all(&row)?;
+        }
+        stream.finish()?;
+        writer.finish()?;
+        return Ok(());
+    }
+
+    let rgba = unpremultiply(pixmap);
+    let (pixels, bytes_per_pixel) = match options.png_palette_size {
+        Some(colors) => {
+            let (palette, indices) = quantize(
+                &rgba,
+                pixmap.width() as usize,
+                colors.clamp(2, 256) as usize,
+                options.png_dither,
+            );
+            info.color_type = png::ColorType::Indexed;
+            info.palette = Some(palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect());
+            if palette.iter().any(|c| c[3] < 255) {
+                info.trns = Some(palette.iter().map(|c| c[3]).collect());
+            }
+            (indices, 1)
+        }
+        None => {
+            info.color_type = png::ColorType::Rgba;
+            (rgba, 4)
+        }
+    };
+
+    let encoder = png_encoder(data, info, input_path, options)?;
+    let mut writer = encoder.write_header()?;
+    if options.png_interlaced {
+        // The png crate only encodes progressive images, so the Adam7 passes are filtered
+        // and deflated here and written as a single IDAT after the interlaced header.
+        let data = adam7_image_data(
+            &pixels,
+            bytes_per_pixel,
+            pixmap.width(),
+            pixmap.height(),
+            options,
+        )?;
+        writer.write_chunk(png::chunk::IDAT, &data)?;
+    } else {
+        writer.write_image_data(&pixels)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Runs oxipng's slowest preset with zopfli, reducing bit depth and color type where the
+/// pixels allow. Interlacing and metadata chunks like `pHYs` are left as encoded.
+fn crush_png(data: &[u8]) -> Result<Vec<u8>, oxipng::PngError> {
+    let mut options = oxipng::Options::from_preset(6);
+    options.interlace = None;
+    options.deflate = oxipng::Deflaters::Zopfli {
+        iterations: NonZeroU8::new(15).unwrap(),
+    };
+    oxipng::optimize_from_memory(data, &options)
+}
+
+/// A PNG encoder for `info` with the compression, filter, density and text chunks from
+/// `options`, shared by still and animated PNGs.
+pub fn png_encoder<W: Write>(
+    data: W,
+    mut info: png::Info<'static>,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<png::Encoder<'static, W>, Box<dyn std::error::Error>> {
+    info.compression = options.png_compression.to_png();
+    if let Some(dpi) = options.embedded_dpi() {
+        let pixels_per_meter = (dpi / 0.0254).round() as u32;
+        info.pixel_dims = Some(png::PixelDimensions {
+            xppu: pixels_per_meter,
+            yppu: pixels_per_meter,
+            unit: png::Unit::Meter,
+        });
+    }
+
+    let mut encoder = png::Encoder::with_info(data, info)?;
+    match options.png_filter {
+        PngFilter::Adaptive => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+        filter => encoder.set_filter(png_filter_type(filter)),
+    }
+    for (keyword, text) in options.metadata.text_chunks(input_path) {
+        if text.chars().all(|c| (c as u32) < 256) {
+            encoder.add_text_chunk(keyword.to_string(), text)?;
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), text)?;
+        }
+    }
+
+    Ok(encoder)
+}
+
+fn png_filter_type(filter: PngFilter) -> png::FilterType {
+    match filter {
+        PngFilter::None => png::FilterType::NoFilter,
+        PngFilter::Sub => png::FilterType::Sub,
+        PngFilter::Up => png::FilterType::Up,
+        PngFilter::Average => png::FilterType::Avg,
+        PngFilter::Paeth | PngFilter::Adaptive => png::FilterType::Paeth,
+    }
+}
+
+/// Splits 8-bit pixels into the seven Adam7 passes and returns their filtered,
+/// zlib-compressed scanlines.
+fn adam7_image_data(
+    pixels: &[u8],
+    bytes_per_pixel: usize,
+    width: u32,
+    height: u32,
+    options: &ConversionOptions,
+) -> std::io::Result<Vec<u8>> {
+    const PASSES: [(u32, u32, u32, u32); 7] = [
+        (0, 0, 8, 8),
+        (4, 0, 8, 8),
+        (0, 4, 4, 8),
+        (2, 0, 4, 4),
+        (0, 2, 2, 4),
+        (1, 0, 2, 2),
+        (0, 1, 1, 2),
+    ];
+
+    let mut encoder =
+        flate2::write::ZlibEncoder::new(Vec::new(), options.png_compression.to_flate2());
+    for (x0, y0, dx, dy) in PASSES {
+        if x0 >= width || y0 >= height {
+            continue;
+        }
+        let pass_width = (width - x0).div_ceil(dx) as usize;
+        let mut previous = vec![0; pass_width * bytes_per_pixel];
+        let mut row = Vec::with_capacity(pass_width * bytes_per_pixel);
+        for y in (y0..height).step_by(dy as usize) {
+            row.clear();
+            for x in (x0..width).step_by(dx as usize) {
+                let i = (y * width + x) as usize * bytes_per_pixel;
+                row.extend_from_slice(&pixels[i..i + bytes_per_pixel]);
+            }
+            encoder.write_all(&filter_row(
+                &row,
+                &previous,
+                bytes_per_pixel,
+                options.png_filter,
+            ))?;
+            std::mem::swap(&mut row, &mut previous);
+        }
+    }
+    encoder.finish()
+}
+
+/// Filters one scanline against the previous one, prefixed with its filter type byte.
+fn filter_row(row: &[u8], previous: &[u8], bpp: usize, filter: PngFilter) -> Vec<u8> {
+    let apply = |filter: PngFilter| {
+        let mut out = Vec::with_capacity(row.len() + 1);
+        out.push(png_filter_type(filter) as u8);
+        for i in 0..row.len() {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= bpp { previous[i - bpp] } else { 0 };
+            let predicted = match filter {
+                PngFilter::None => 0,
+                PngFilter::Sub => left,
+                PngFilter::Up => up,
+                PngFilter::Average => ((left as u16 + up as u16) / 2) as u8,
+                PngFilter::Paeth | PngFilter::Adaptive => paeth(left, up, up_left),
+            };
+            out.push(row[i].wrapping_sub(predicted));
+        }
+        out
+    };
+
+    if filter != PngFilter::Adaptive {
+        return apply(filter);
+    }
+    // Same heuristic as libpng: the filter with the smallest sum of signed residuals.
+    [
+        PngFilter::None,
+        PngFilter::Sub,
+        PngFilter::Up,
+        PngFilter::Average,
+        PngFilter::Paeth,
+    ]
+    .into_iter()
+    .map(apply)
+    .min_by_key(|out| {
+        out[1..]
+            .iter()
+            .map(|&byte| (byte as i8).unsigned_abs() as u64)
+            .sum::<u64>()
+    })
+    .unwrap_or_default()
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Reduces straight-alpha RGBA pixels to at most `colors` palette entries, returning the
+/// RGBA palette and one index per pixel. Images that already fit keep their exact colors;
+/// otherwise NeuQuant picks the palette, optionally with Floyd–Steinberg error diffusion.
+fn quantize(rgba: &[u8], width: usize, colors: usize, dither: bool) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut lookup = HashMap::new();
+    let exact = rgba
+        .chunks_exact(4)
+        .try_fold(Vec::new(), |mut indices, pixel| {
+            let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            let index = match lookup.get(&color) {
+                Some(&index) => index,
+                None if palette.len() < colors => {
+                    let index = palette.len() as u8;
+                    palette.push(color);
+                    lookup.insert(color, index);
+                    index
+                }
+                None => return None,
+            };
+            indices.push(index);
+            Some(indices)
+        });
+    if let Some(indices) = exact {
+        return (palette, indices);
+    }
+
+    let quantizer = NeuQuant::new(10, colors, rgba);
+    let palette = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+    if !dither {
+        let indices = rgba
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect();
+        return (palette, indices);
+    }
+
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    let mut current = vec![[0.0f32; 4]; width + 2];
+    let mut next = vec![[0.0f32; 4]; width + 2];
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            let mut wanted = [0u8; 4];
+            for c in 0..4 {
+                wanted[c] = (pixel[c] as f32 + current[x + 1][c])
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+            let index = quantizer.index_of(&wanted);
+            indices.push(index as u8);
+            let chosen = quantizer.lookup(index).unwrap_or(wanted);
+            for c in 0..4 {
+                let error = wanted[c] as f32 - chosen[c] as f32;
+                current[x + 2][c] += error * 7.0 / 16.0;
+                next[x][c] += error * 3.0 / 16.0;
+                next[x + 1][c] += error * 5.0 / 16.0;
+                next[x + 2][c] += error / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.iter_mut().for_each(|error| *error = [0.0; 4]);
+    }
+    (palette, indices)
+}
+
+/// Composites the premultiplied pixmap over an opaque background, dropping alpha.
+pub fn flatten(pixmap: &Pixmap, background: [u8; 3]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(pixmap.pixels().len() * 3);
+    for pixel in pixmap.pixels() {
+        let inverse_alpha = 255 - pixel.alpha() as u32;
+        for (channel, bg) in [pixel.red(), pixel.green(), pixel.blue()]
+            .into_iter()
+            .zip(background)
+        {
+            rgb.push((channel as u32 + (bg as u32 * inverse_alpha + 127) / 255) as u8);
+        }
+    }
+    rgb
+}
+
+/// The pixmap as a GIF frame, shown for one frame at `fps`.
+pub fn gif_frame(pixmap: &Pixmap, fps: f32) -> Result<Frame, Box<dyn std::error::Error>> {
+    let image = RgbaImage::from_raw(pixmap.width(), pixmap.height(), unpremultiply(pixmap))
+        .ok_or("Failed to create GIF frame")?;
+    let delay = Delay::from_numer_denom_ms(1_000_000, ((fps * 1000.0).round() as u32).max(1));
+    Ok(Frame::from_parts(image, 0, 0, delay))
+}
+
+/// Converts the premultiplied pixmap into straight-alpha RGBA bytes.
+pub fn unpremultiply(pixmap: &Pixmap) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(pixmap.data().len());
+    unpremultiply_into(pixmap.pixels(), &mut rgba);
+    rgba
+}
+
+fn unpremultiply_into(pixels: &[PremultipliedColorU8], rgba: &mut Vec<u8>) {
+    for pixel in pixels {
+        let color = pixel.demultiply();
+        rgba.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+/// ICC profile embedded in PNG and JPEG output.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum IccProfile {
+    /// A compact sRGB IEC 61966-2.1 profile generated on the fly.
+    Srgb,
+    /// A profile read from an `.icc`/`.icm` file.
+    File(PathBuf),
+}
+
+impl IccProfile {
+    /// The profile's bytes, checking that a file really holds an RGB ICC profile.
+    pub fn load(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self {
+            IccProfile::Srgb => Ok(srgb_profile()),
+            IccProfile::File(path) => {
+                let data = std::fs::read(path)?;
+                if data.len() < 128 || &data[36..40] != b"acsp" {
+                    return Err(format!("{} is not an ICC profile", path.display()).into());
+                }
+                if &data[16..20] != b"RGB " {
+                    return Err(format!("{} is not an RGB profile", path.display()).into());
+                }
+                Ok(data)
+            }
+        }
+    }
+
+    /// Short name used in the profile's iCCP chunk.
+    fn name(&self) -> String {
+        match self {
+            IccProfile::Srgb => String::from("sRGB"),
+            IccProfile::File(path) => {
+                // iCCP names are 1-79 Latin-1 characters; keep to ASCII to be safe.
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let name: String = stem.chars().filter(char::is_ascii).take(79).collect();
+                if name.trim().is_empty() {
+                    String::from("ICC profile")
+                } else {
+                    name
+                }
+            }
+        }
+    }
+}
+
+/// Builds an ICC v2 display profile for sRGB: D50-adapted primaries and the sRGB tone curve.
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz([x, y, z]: [f64; 3]) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in [x, y, z] {
+            tag.extend_from_slice(&s15_fixed16(value));
+        }
+        tag
+    }
+
+    let description = b"sRGB IEC61966-2.1\0";
+    let mut desc = b"desc\0\0\0\0".to_vec();
+    desc.extend_from_slice(&(description.len() as u32).to_be_bytes());
+    desc.extend_from_slice(description);
+    // Empty Unicode and ScriptCode descriptions.
+    desc.extend_from_slice(&[0; 8 + 2 + 1 + 67]);
+
+    let mut cprt = b"text\0\0\0\0".to_vec();
+    cprt.extend_from_slice(b"No copyright, use freely\0");
+
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    curve.extend_from_slice(&1024u32.to_be_bytes());
+    for i in 0..1024 {
+        let encoded = i as f64 / 1023.0;
+        let linear = if encoded <= 0.04045 {
+            encoded / 12.92
+        } else {
+            ((encoded + 0.055) / 1.055).powf(2.4)
+        };
+        curve.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", desc),
+        (b"cprt", cprt),
+        (b"wtpt", xyz([0.9642, 1.0, 0.8249])),
+        (b"rXYZ", xyz([0.4360747, 0.2225045, 0.0139322])),
+        (b"gXYZ", xyz([0.3850649, 0.7168786, 0.0971045])),
+        (b"bXYZ", xyz([0.1430804, 0.0606169, 0.7141733])),
+        (b"rTRC", curve),
+        // The green and blue curves share the red one's data.
+        (b"gTRC", Vec::new()),
+        (b"bTRC", Vec::new()),
+    ];
+
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    let data_start = 128 + 4 + tags.len() * 12;
+    let mut shared = (0, 0);
+    for (signature, tag) in &tags {
+        let (offset, size) = if tag.is_empty() {
+            shared
+        } else {
+            let offset = data_start + data.len();
+            data.extend_from_slice(tag);
+            data.resize(data.len().next_multiple_of(4), 0);
+            shared = (offset, tag.len());
+            shared
+        };
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(size as u32).to_be_bytes());
+    }
+
+    let size = data_start + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]); // Preferred CMM
+    profile.extend_from_slice(&[2, 0x10, 0, 0]); // Version 2.1
+    profile.extend_from_slice(b"mntrRGB XYZ ");
+    profile.extend_from_slice(&[0; 12]); // Creation date
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 28]); // Platform, flags, device and attributes
+    profile.extend_from_slice(&[0; 4]); // Perceptual rendering intent
+    for value in [0.9642, 1.0, 0.8249] {
+        profile.extend_from_slice(&s15_fixed16(value));
+    }
+    profile.resize(128, 0);
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+/// Inserts an `iCCP` chunk right after a PNG's `IHDR`.
+pub fn embed_in_png(
+    png: &[u8],
+    profile: &IccProfile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut payload = profile.name().into_bytes();
+    payload.extend_from_slice(&[0, 0]); // Name terminator, zlib compression
+    let mut zlib = flate2::write::ZlibEncoder::new(payload, flate2::Compression::best());
+    zlib.write_all(&profile.load()?)?;
+    let payload = zlib.finish()?;
+
+    // The 8-byte signature is followed by IHDR: length, type, 13 bytes of data and a CRC.
+    let after_header = 8 + 4 + 4 + 13 + 4;
+    let mut crc = flate2::Crc::new();
+    crc.update(b"iCCP");
+    crc.update(&payload);
+
+    let mut output = Vec::with_capacity(png.len() + payload.len() + 12);
+    output.extend_from_slice(&png[..after_header]);
+    output.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    output.extend_from_slice(b"iCCP");
+    output.extend_from_slice(&payload);
+    output.extend_from_slice(&crc.sum().to_be_bytes());
+    output.extend_from_slice(&png[after_header..]);
+    Ok(output)
+}
+
+/// Inserts the profile as `APP2` `ICC_PROFILE` segments after a JPEG's JFIF header.
+pub fn embed_in_jpeg(
+    jpeg: &[u8],
+    profile: &IccProfile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    const SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+    const MAX_CHUNK: usize = 0xFFFF - 2 - SIGNATURE.len() - 2;
+
+    let profile = profile.load()?;
+    let chunks: Vec<&[u8]> = profile.chunks(MAX_CHUNK).collect();
+    if chunks.len() > 255 {
+        return Err("ICC profile is too large to embed in a JPEG".into());
+    }
+
+    // SOI, then the APP0 (JFIF) segment the encoder always writes first.
+    let after_header = if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize
+    } else {
+        2
+    };
+
+    let mut output = Vec::with_capacity(jpeg.len() + profile.len() + chunks.len() * 18);
+    output.extend_from_slice(&jpeg[..after_header]);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let length = (2 + SIGNATURE.len() + 2 + chunk.len()) as u16;
+        output.extend_from_slice(&[0xFF, 0xE2]);
+        output.extend_from_slice(&length.to_be_bytes());
+        output.extend_from_slice(SIGNATURE);
+        output.extend_from_slice(&[i as u8 + 1, chunks.len() as u8]);
+        output.extend_from_slice(chunk);
+    }
+    output.extend_from_slice(&jpeg[after_header..]);
+    Ok(output)
+}
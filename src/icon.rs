@@ -0,0 +1,112 @@
+use crate::convert::{render_pixmap, ConversionOptions};
+use crate::encode::unpremultiply;
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::ColorType;
+use resvg::tiny_skia;
+use std::collections::btree_map::{BTreeMap, Entry};
+use tiny_skia::Pixmap;
+use usvg::Tree;
+
+/// Sizes offered for `.ico` export, in pixels.
+pub const ICO_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+
+/// ICNS entry types with their pixel sizes, covering 16-512pt at 1x and @2x.
+const ICNS_ENTRIES: [(&[u8; 4], u32); 10] = [
+    (b"icp4", 16),
+    (b"ic11", 32),
+    (b"icp5", 32),
+    (b"ic12", 64),
+    (b"ic07", 128),
+    (b"ic13", 256),
+    (b"ic08", 256),
+    (b"ic14", 512),
+    (b"ic09", 512),
+    (b"ic10", 1024),
+];
+
+/// Renders the tree into a `size`x`size` square, preserving its aspect ratio and centering it.
+pub fn render_square(
+    rtree: &Tree,
+    size: u32,
+    options: &ConversionOptions,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let (width, height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let scale = size as f32 / width.max(height);
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (size as f32 - width * scale) / 2.0,
+        (size as f32 - height * scale) / 2.0,
+    );
+
+    render_pixmap(rtree, (size, size), transform, options)
+}
+
+pub fn save_ico(
+    rtree: &Tree,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(output_path, encode_ico(rtree, options)?)?;
+    Ok(())
+}
+
+/// The `.ico` written by [`save_ico`], with a PNG frame for each of `options.ico_sizes`.
+pub fn encode_ico(
+    rtree: &Tree,
+    options: &ConversionOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if options.ico_sizes.is_empty() {
+        return Err("No icon sizes selected".into());
+    }
+
+    let mut frames = Vec::with_capacity(options.ico_sizes.len());
+    for &size in &options.ico_sizes {
+        let pixmap = render_square(rtree, size, options)?;
+        frames.push(IcoFrame::as_png(
+            &unpremultiply(&pixmap),
+            size,
+            size,
+            ColorType::Rgba8,
+        )?);
+    }
+
+    let mut ico = Vec::new();
+    IcoEncoder::new(&mut ico).encode_images(&frames)?;
+    Ok(ico)
+}
+
+/// Writes an `.icns` with PNG-encoded entries for every standard macOS icon size.
+pub fn save_icns(
+    rtree: &Tree,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(output_path, encode_icns(rtree, options)?)?;
+    Ok(())
+}
+
+/// The `.icns` written by [`save_icns`].
+pub fn encode_icns(
+    rtree: &Tree,
+    options: &ConversionOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut pngs = BTreeMap::new();
+    for (_, size) in ICNS_ENTRIES {
+        if let Entry::Vacant(entry) = pngs.entry(size) {
+            entry.insert(render_square(rtree, size, options)?.encode_png()?);
+        }
+    }
+
+    let mut body = Vec::new();
+    for (kind, size) in ICNS_ENTRIES {
+        let png = &pngs[&size];
+        body.extend_from_slice(kind);
+        body.extend_from_slice(&(png.len() as u32 + 8).to_be_bytes());
+        body.extend_from_slice(png);
+    }
+
+    let mut icns = Vec::with_capacity(body.len() + 8);
+    icns.extend_from_slice(b"icns");
+    icns.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    icns.extend_from_slice(&body);
+    Ok(icns)
+}
@@ -0,0 +1,180 @@
+use crate::convert::{self, ConversionOptions};
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+use usvg::{roxmltree, NodeKind, Paint, Tree};
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// What an SVG contains, as reported by the `info` command.
+#[derive(serde::Serialize)]
+pub struct SvgInfo {
+    pub input: String,
+    /// Size in CSS pixels, from `width` and `height` or the `viewBox`.
+    pub width: f64,
+    pub height: f64,
+    /// `[x, y, width, height]`.
+    pub view_box: [f64; 4],
+    /// How many of each element the markup has, by tag name.
+    pub elements: BTreeMap<String, usize>,
+    /// Font families asked for by text, in the order they first appear.
+    pub fonts: Vec<String>,
+    /// `href`s of images, with embedded `data:` images shortened to their media type.
+    pub images: Vec<String>,
+    /// Size of the input file in bytes, or of the markup when it isn't a file.
+    pub file_size: u64,
+    pub stats: TreeStats,
+}
+
+/// What the parsed document draws, once hidden and unused elements are dropped; these are
+/// what rendering time depends on.
+#[derive(Default, serde::Serialize)]
+pub struct TreeStats {
+    pub paths: usize,
+    pub groups: usize,
+    pub texts: usize,
+    pub images: usize,
+    /// Distinct linear and radial gradients in use.
+    pub gradients: usize,
+    pub patterns: usize,
+    pub clip_paths: usize,
+    pub masks: usize,
+    pub filters: usize,
+}
+
+impl TreeStats {
+    fn of(rtree: &Tree) -> Self {
+        let mut stats = Self::default();
+        let mut gradients = HashSet::new();
+        let mut patterns = HashSet::new();
+        let mut clip_paths = HashSet::new();
+        let mut masks = HashSet::new();
+        let mut filters = HashSet::new();
+        let mut add_paint = |paint: &Paint| match paint {
+            Paint::Color(_) => {}
+            Paint::LinearGradient(gradient) => {
+                gradients.insert(Rc::as_ptr(gradient) as *const ());
+            }
+            Paint::RadialGradient(gradient) => {
+                gradients.insert(Rc::as_ptr(gradient) as *const ());
+            }
+            Paint::Pattern(pattern) => {
+                patterns.insert(Rc::as_ptr(pattern));
+            }
+        };
+
+        // The root group stands for the `<svg>` element.
+        for node in rtree.root.descendants().skip(1) {
+            match *node.borrow() {
+                NodeKind::Group(ref group) => {
+                    stats.groups += 1;
+                    clip_paths.extend(group.clip_path.as_ref().map(Rc::as_ptr));
+                    masks.extend(group.mask.as_ref().map(Rc::as_ptr));
+                    filters.extend(group.filters.iter().map(Rc::as_ptr));
+                }
+                NodeKind::Path(ref path) => {
+                    stats.paths += 1;
+                    path.fill.iter().for_each(|fill| add_paint(&fill.paint));
+                    path.stroke
+                        .iter()
+                        .for_each(|stroke| add_paint(&stroke.paint));
+                }
+                NodeKind::Image(_) => stats.images += 1,
+                NodeKind::Text(ref text) => {
+                    stats.texts += 1;
+                    for span in text.chunks.iter().flat_map(|chunk| &chunk.spans) {
+                        span.fill.iter().for_each(|fill| add_paint(&fill.paint));
+                        span.stroke
+                            .iter()
+                            .for_each(|stroke| add_paint(&stroke.paint));
+                    }
+                }
+            }
+        }
+
+        stats.gradients = gradients.len();
+        stats.patterns = patterns.len();
+        stats.clip_paths = clip_paths.len();
+        stats.masks = masks.len();
+        stats.filters = filters.len();
+        stats
+    }
+}
+
+/// Parses the markup with the parse options and describes it, without rendering or laying
+/// out text. In safe mode, external images are listed but not loaded.
+pub fn inspect(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<SvgInfo, Box<dyn std::error::Error>> {
+    let document = convert::parse_xml(svg_data)?;
+    let (rtree, _) = convert::document_tree(&document, input_path, options)?;
+    Ok(describe(&document, &rtree, input_path))
+}
+
+/// Describes markup already parsed into `document` and, by [`convert::document_tree`], into
+/// `rtree`.
+pub fn describe(document: &roxmltree::Document, rtree: &Tree, input_path: &str) -> SvgInfo {
+    let mut elements = BTreeMap::new();
+    let mut images = Vec::new();
+    for node in document.descendants().filter(|node| node.is_element()) {
+        let name = node.tag_name().name();
+        *elements.entry(name.to_string()).or_insert(0) += 1;
+        if name == "image" || name == "feImage" {
+            let href = node
+                .attribute((XLINK_NS, "href"))
+                .or_else(|| node.attribute("href"));
+            if let Some(href) = href.map(describe_href) {
+                if !images.contains(&href) {
+                    images.push(href);
+                }
+            }
+        }
+    }
+
+    let mut fonts = Vec::new();
+    for node in rtree.root.descendants() {
+        if let NodeKind::Text(ref text) = *node.borrow() {
+            let spans = text.chunks.iter().flat_map(|chunk| &chunk.spans);
+            for family in spans.flat_map(|span| &span.font.families) {
+                // Quoted CSS names keep their quotes.
+                let family = family.trim_matches(['"', '\'']).to_string();
+                if !fonts.contains(&family) {
+                    fonts.push(family);
+                }
+            }
+        }
+    }
+
+    let view_box = rtree.view_box.rect;
+    SvgInfo {
+        input: input_path.to_string(),
+        width: rtree.size.width(),
+        height: rtree.size.height(),
+        view_box: [
+            view_box.x(),
+            view_box.y(),
+            view_box.width(),
+            view_box.height(),
+        ],
+        elements,
+        fonts,
+        images,
+        file_size: std::fs::metadata(input_path)
+            .map_or(document.input_text().len() as u64, |metadata| {
+                metadata.len()
+            }),
+        stats: TreeStats::of(rtree),
+    }
+}
+
+/// The href, or just `data:TYPE` for an embedded image.
+fn describe_href(href: &str) -> String {
+    match href.trim_start().strip_prefix("data:") {
+        Some(data) => {
+            let media_type = data.split([';', ',']).next().unwrap_or_default();
+            format!("data:{}", media_type)
+        }
+        None => href.to_string(),
+    }
+}
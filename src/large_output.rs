@@ -0,0 +1,210 @@
+use crate::convert::{
+    check_pixmap_size, format_bytes, pixmap_bytes, render_pixmap, ConversionOptions,
+    LARGE_PIXMAP_BYTES, MAX_PIXMAP_WIDTH,
+};
+use crate::encode::{self, OutputFormat};
+use resvg::tiny_skia::Transform;
+use std::io::{BufWriter, Write};
+use usvg::Tree;
+
+/// Rows rendered at a time are kept to about this many bytes, but a strip is always at
+/// least one row.
+const STRIP_BYTES: u64 = 64 << 20;
+
+/// Widest tile rendered at once; wider strips are rendered in several tiles.
+const TILE_WIDTH: u32 = 8192;
+
+/// Whether an output of this size is rendered in strips rather than into one pixmap: it's
+/// wider than a pixmap can be, more than this system can address, or a large one and the
+/// memory for it can't be reserved.
+pub fn is_needed(size: (u32, u32)) -> bool {
+    let bytes = pixmap_bytes(size);
+    size.0 > MAX_PIXMAP_WIDTH
+        || usize::try_from(bytes).map_or(true, |bytes| {
+            bytes > LARGE_PIXMAP_BYTES as usize
+                && Vec::<u8>::new().try_reserve_exact(bytes).is_err()
+        })
+}
+
+/// Why these options can't be rendered in strips, which only streams plain PNGs.
+pub fn unsupported(options: &ConversionOptions) -> Option<&'static str> {
+    if options.format != OutputFormat::Png {
+        Some("only works for PNG output")
+    } else if options.canvas.tile.is_some() {
+        Some("doesn't support repeating the image in a grid")
+    } else if options.effects.watermark.is_some() {
+        Some("doesn't support watermarks")
+    } else if options.png_palette_size.is_some() {
+        Some("doesn't support palette PNGs")
+    } else if options.png_interlaced {
+        Some("doesn't support interlacing")
+    } else if options.png_optimize {
+        Some("doesn't support optimizing the PNG")
+    } else if options.icc_profile.is_some() {
+        Some("doesn't support ICC profiles")
+    } else {
+        None
+    }
+}
+
+/// Renders the tree as a PNG at `output_path` a strip of rows at a time, each strip in
+/// tiles of at most [`TILE_WIDTH`], and streams the rows into the encoder. Memory stays
+/// around [`STRIP_BYTES`] however tall the output is; an output so wide that one row is
+/// larger still takes a few rows' worth, as the encoder filters a row against the last.
+///
+/// Anti-aliased curves that cross tiles can differ very slightly from a single render, as
+/// tiny-skia flattens the pieces of a clipped curve separately.
+pub fn save_png(
+    rtree: &Tree,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let (width, height) = options.output_size(svg_width, svg_height);
+    if let Some(reason) = unsupported(options) {
+        let (width, height) = options.canvas.tiled_size((width, height))?;
+        return Err(format!(
+            "The output would be {}×{} pixels ({}), too large to render at once, and \
+             rendering it in strips {}",
+            width,
+            height,
+            format_bytes(pixmap_bytes((width, height))),
+            reason
+        )
+        .into());
+    }
+    if width == 0 || height == 0 {
+        check_pixmap_size((width, height))?;
+    }
+    log::info!(
+        "rendering in strips input={:?} width={} height={}",
+        input_path,
+        width,
+        height
+    );
+
+    let result = std::fs::File::create(output_path)
+        .map_err(Into::into)
+        .and_then(|file| {
+            write_png(
+                rtree,
+                (width, height),
+                BufWriter::new(file),
+                input_path,
+                options,
+                (STRIP_BYTES, TILE_WIDTH),
+                check_cancel,
+            )
+        });
+    if result.is_err() {
+        // Don't leave a truncated PNG behind.
+        let _ = std::fs::remove_file(output_path);
+    }
+    result
+}
+
+fn write_png(
+    rtree: &Tree,
+    (width, height): (u32, u32),
+    output: impl Write + 'static,
+    input_path: &str,
+    options: &ConversionOptions,
+    (strip_bytes, tile_width): (u64, u32),
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (svg_width, svg_height) = (rtree.size.width() as f32, rtree.size.height() as f32);
+    let (scale_x, scale_y) = options.scale_factors(svg_width, svg_height);
+    let transform = Transform::from_scale(scale_x, scale_y);
+
+    let mut info = png::Info::with_size(width, height);
+    info.color_type = png::ColorType::Rgba;
+    info.bit_depth = png::BitDepth::Eight;
+    let encoder = encode::png_encoder(output, info, input_path, options)?;
+    let mut writer = encoder.write_header()?.into_stream_writer()?;
+
+    let row_bytes = width as usize * 4;
+    let strip_height = (strip_bytes / row_bytes as u64).clamp(1, u64::from(height)) as u32;
+    let mut strip = vec![0; row_bytes * strip_height as usize];
+    for y in (0..height).step_by(strip_height as usize) {
+        let rows = strip_height.min(height - y);
+        // A row of margin on either side: resvg trims group layers to their content, but
+        // gets the width wrong when that is one row tall, as it often is at a strip's edge.
+        let top = y.saturating_sub(1);
+        let bottom = (y + rows + 1).min(height);
+        for x in (0..width).step_by(tile_width as usize) {
+            let columns = tile_width.min(width - x);
+            let tile_transform = transform.post_translate(-(x as f32), -(top as f32));
+            let tile = render_pixmap(rtree, (columns, bottom - top), tile_transform, options)?;
+            let tile_row_bytes = columns as usize * 4;
+            for (row, pixels) in encode::unpremultiply(&tile)
+                .chunks_exact(tile_row_bytes)
+                .skip((y - top) as usize)
+                .take(rows as usize)
+                .enumerate()
+            {
+                let start = row * row_bytes + x as usize * 4;
+                strip[start..start + tile_row_bytes].copy_from_slice(pixels);
+            }
+            check_cancel()?;
+        }
+        writer.write_all(&strip[..rows as usize * row_bytes])?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::parse_tree;
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="30">
+        <linearGradient id="g"><stop offset="0" stop-color="#f80"/><stop offset="1" stop-color="#08f" stop-opacity="0.5"/></linearGradient>
+        <rect x="2" y="3" width="36" height="24" fill="url(#g)"/>
+        <rect x="8" y="6" width="16" height="12" fill="#0a0" opacity="0.7"/>
+    </svg>"##;
+
+    #[test]
+    fn strips_match_one_pixmap() {
+        let options = ConversionOptions {
+            scale: 2.0,
+            ..ConversionOptions::default()
+        };
+        let rtree = parse_tree(SVG, "test.svg", &options).unwrap();
+        let size = options.output_size(rtree.size.width() as f32, rtree.size.height() as f32);
+        let (scale_x, scale_y) =
+            options.scale_factors(rtree.size.width() as f32, rtree.size.height() as f32);
+        let pixmap = render_pixmap(
+            &rtree,
+            size,
+            Transform::from_scale(scale_x, scale_y),
+            &options,
+        )
+        .unwrap();
+
+        // Strips of 7 rows in tiles 32 pixels wide, so neither divides the 80×60 output.
+        let path = std::env::temp_dir().join(format!("strips-{}.png", std::process::id()));
+        let file = BufWriter::new(std::fs::File::create(&path).unwrap());
+        let strip_bytes = u64::from(size.0) * 4 * 7;
+        write_png(
+            &rtree,
+            size,
+            file,
+            "test.svg",
+            &options,
+            (strip_bytes, 32),
+            &|| Ok(()),
+        )
+        .unwrap();
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut strips = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut strips).unwrap();
+        assert_eq!((frame.width, frame.height), size);
+        assert_eq!(strips, encode::unpremultiply(&pixmap));
+    }
+}
@@ -0,0 +1,182 @@
+//! Parsing, rendering and encoding behind the `rust-svg-converter` app and CLI.
+//!
+//! [`Converter`] covers the common cases: it holds a set of [`ConversionOptions`] and turns
+//! SVG markup into encoded bytes or files. The modules below expose the individual stages
+//! for finer control.
+//!
+//! ```no_run
+//! use svg_to_png::{ConversionOptions, Converter, OutputFormat};
+//!
+//! let converter = Converter::new(ConversionOptions {
+//!     scale: 2.0,
+//!     format: OutputFormat::Webp,
+//!     ..ConversionOptions::default()
+//! });
+//! let svg = std::fs::read_to_string("icon.svg")?;
+//! let webp = converter.convert(&svg, "icon.svg")?;
+//! std::fs::write("icon.webp", webp)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod animation;
+pub mod atlas;
+pub mod canvas;
+pub mod color;
+pub mod convert;
+pub mod effects;
+pub mod encode;
+pub mod icc;
+pub mod icon;
+pub mod info;
+pub mod large_output;
+pub mod lint;
+pub mod outline;
+pub mod package;
+pub mod parsing;
+pub mod sanitize;
+pub mod snippet;
+pub mod svg_writer;
+pub mod template;
+pub mod trace;
+pub mod vector_drawable;
+pub mod video;
+
+pub use convert::{ConversionOptions, CANCELLED};
+pub use encode::OutputFormat;
+pub use resvg::tiny_skia::Pixmap;
+pub use usvg::Tree;
+
+use std::borrow::Cow;
+use std::sync::atomic::AtomicBool;
+
+/// Converts SVGs with one set of options.
+#[derive(Clone, Default)]
+pub struct Converter {
+    options: ConversionOptions,
+}
+
+impl Converter {
+    pub fn new(options: ConversionOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &ConversionOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut ConversionOptions {
+        &mut self.options
+    }
+
+    /// Parses SVG markup, applying the crop, recolor and font options. Relative `href`s
+    /// resolve next to `input_path`.
+    pub fn parse(
+        &self,
+        svg_data: &str,
+        input_path: &str,
+    ) -> Result<Tree, Box<dyn std::error::Error>> {
+        convert::parse_tree(svg_data, input_path, &self.options)
+    }
+
+    /// Reads and parses an SVG file, `.svgz` file or `http(s)` URL.
+    pub fn load(&self, input_path: &str) -> Result<Tree, Box<dyn std::error::Error>> {
+        convert::load_tree(input_path, &self.options)
+    }
+
+    /// Renders a parsed tree at the output size, with background and effects.
+    pub fn render(&self, rtree: &Tree) -> Result<Pixmap, Box<dyn std::error::Error>> {
+        convert::render_tree(rtree, &self.options)
+    }
+
+    /// Converts SVG markup into the bytes of one file in the output format.
+    ///
+    /// Options that write several files, like layers, packages, density variants and
+    /// snippets, only apply to [`Converter::convert_file`].
+    pub fn convert(
+        &self,
+        svg_data: &str,
+        input_path: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let svg_data = self.sanitize(svg_data)?;
+        if let Some(data) = self.convert_markup(&svg_data, input_path) {
+            return data;
+        }
+        let rtree = self.parse(&svg_data, input_path)?;
+        self.convert_tree(&rtree, &svg_data, input_path)
+    }
+
+    /// Strips scripting from the markup if the sanitize option is set, as
+    /// [`Converter::convert`] does before parsing.
+    pub fn sanitize<'a>(&self, svg_data: &'a str) -> Result<Cow<'a, str>, convert::ParseError> {
+        if !self.options.parse.sanitize {
+            return Ok(Cow::Borrowed(svg_data));
+        }
+        let (sanitized, _) =
+            sanitize::sanitize(svg_data).map_err(|e| convert::ParseError::new(&e, svg_data))?;
+        Ok(Cow::Owned(sanitized))
+    }
+
+    /// Like [`Converter::convert`], for markup already sanitized and parsed into `rtree`.
+    /// Animations, PNG sequences and outlined text are made from the markup instead.
+    pub fn convert_tree(
+        &self,
+        rtree: &Tree,
+        svg_data: &str,
+        input_path: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if let Some(data) = self.convert_markup(svg_data, input_path) {
+            return data;
+        }
+        let options = &self.options;
+        match options.format {
+            OutputFormat::Pdf => convert::pdf(rtree),
+            OutputFormat::Ico => icon::encode_ico(rtree, options),
+            OutputFormat::Icns => icon::encode_icns(rtree, options),
+            OutputFormat::VectorDrawable => {
+                Ok(vector_drawable::to_xml(rtree, options).into_bytes())
+            }
+            OutputFormat::Svg => Ok(svg_writer::document(rtree, options).into_bytes()),
+            _ => {
+                let pixmap = convert::render_markup(rtree, svg_data, input_path, options)?;
+                encode::encode(&pixmap, input_path, options)
+            }
+        }
+    }
+
+    /// Converts the markup itself for the formats made without a parsed tree.
+    fn convert_markup(
+        &self,
+        svg_data: &str,
+        input_path: &str,
+    ) -> Option<Result<Vec<u8>, Box<dyn std::error::Error>>> {
+        let options = &self.options;
+        match options.format {
+            OutputFormat::PngSequence => Some(Err(
+                "PNG sequences are written as separate files, not one".into(),
+            )),
+            format if format.is_animated() => {
+                Some(animation::encode(svg_data, input_path, options, &|| Ok(())))
+            }
+            OutputFormat::Svg if options.outline_text_only => {
+                Some(outline::outline_text(svg_data, input_path, options).map(String::into_bytes))
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts an SVG file, expanding tokens like `{name}` in `output_template`, and
+    /// returns the paths written.
+    pub fn convert_file(
+        &self,
+        input_path: &str,
+        output_template: &str,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        convert::convert_file(
+            input_path,
+            output_template,
+            &self.options,
+            &AtomicBool::new(false),
+            &mut convert::StageTimings::default(),
+        )
+    }
+}
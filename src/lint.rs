@@ -0,0 +1,209 @@
+use crate::convert::{self, is_url, ConversionOptions};
+use crate::info::SvgInfo;
+use resvg::usvg_text_layout::fontdb;
+use std::path::Path;
+use usvg::roxmltree;
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// Element count past which rendering gets slow enough to mention.
+const MANY_ELEMENTS: usize = 10_000;
+
+/// Filter primitives resvg renders; other children of `<filter>` are ignored.
+const SUPPORTED_FILTERS: [&str; 17] = [
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDropShadow",
+    "feFlood",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMorphology",
+    "feOffset",
+    "feSpecularLighting",
+    "feTile",
+    "feTurbulence",
+];
+
+/// Filter inputs resvg has nothing to render from.
+const UNSUPPORTED_FILTER_INPUTS: [&str; 4] = [
+    "BackgroundImage",
+    "BackgroundAlpha",
+    "FillPaint",
+    "StrokePaint",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The output may differ from what a browser shows.
+    Warning,
+    /// Part of the drawing will be missing from the output.
+    Error,
+}
+
+/// A problem found in an SVG before converting it.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based line of the element the issue is about, if it's about one.
+    pub line: Option<u32>,
+}
+
+/// Looks for things that commonly make the output differ from what was intended: a missing
+/// size, fonts and images that can't be found, unsupported filters, external references and
+/// very large documents. Errors come first.
+///
+/// Works from the markup already parsed into `document`, and `info` describing it as
+/// parsed with `options`.
+pub fn check(
+    document: &roxmltree::Document,
+    info: &SvgInfo,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
+    let mut issues = Vec::new();
+    let mut report = |severity, message: String, node: Option<roxmltree::Node>| {
+        let line = node.map(|node| document.text_pos_at(node.range().start).row);
+        issues.push(Issue {
+            severity,
+            message,
+            line,
+        });
+    };
+
+    let root = document.root_element();
+    let has_view_box = root.has_attribute("viewBox");
+    match (root.has_attribute("width"), root.has_attribute("height")) {
+        (true, true) => {}
+        _ if has_view_box => report(
+            Severity::Warning,
+            String::from("No width or height; the size comes from the viewBox"),
+            Some(root),
+        ),
+        _ => report(
+            Severity::Warning,
+            format!(
+                "No width, height or viewBox; it's taken to be {}×{}",
+                info.width, info.height
+            ),
+            Some(root),
+        ),
+    }
+
+    let resources_dir = options.parse.resources_dir.clone().or_else(|| {
+        (!is_url(input_path))
+            .then(|| Path::new(input_path).parent().map(Path::to_path_buf))
+            .flatten()
+    });
+    for node in document.descendants().filter(|node| node.is_element()) {
+        let name = node.tag_name().name();
+        let href = node
+            .attribute((XLINK_NS, "href"))
+            .or_else(|| node.attribute("href"))
+            .map(str::trim);
+        match href {
+            None => {}
+            Some(href) if href.starts_with('#') || href.starts_with("data:") => {}
+            Some(href) if is_url(href) => report(
+                Severity::Warning,
+                format!("<{}> refers to {}, which isn't downloaded", name, href),
+                Some(node),
+            ),
+            Some(href) if name == "image" || name == "feImage" => {
+                let path = href.strip_prefix("file://").unwrap_or(href);
+                let path = match &resources_dir {
+                    Some(dir) => dir.join(path),
+                    None => Path::new(path).to_path_buf(),
+                };
+                if !path.is_file() {
+                    report(
+                        Severity::Error,
+                        format!("Image {} doesn't exist", path.display()),
+                        Some(node),
+                    );
+                } else if options.parse.safe_mode {
+                    report(
+                        Severity::Error,
+                        format!("Safe mode blocks the image {}", href),
+                        Some(node),
+                    );
+                }
+            }
+            Some(href) => report(
+                Severity::Warning,
+                format!(
+                    "<{}> refers to {} in another file, which isn't supported",
+                    name, href
+                ),
+                Some(node),
+            ),
+        }
+
+        if node.parent_element().map(|parent| parent.tag_name().name()) == Some("filter")
+            && !SUPPORTED_FILTERS.contains(&name)
+        {
+            report(
+                Severity::Warning,
+                format!("Filter primitive <{}> isn't supported and is skipped", name),
+                Some(node),
+            );
+        }
+        for input in ["in", "in2"]
+            .iter()
+            .filter_map(|input| node.attribute(*input))
+        {
+            if UNSUPPORTED_FILTER_INPUTS.contains(&input) {
+                report(
+                    Severity::Warning,
+                    format!("Filter input {} isn't supported", input),
+                    Some(node),
+                );
+            }
+        }
+    }
+
+    let fontdb = convert::font_database(&options.fonts)?;
+    for family in &info.fonts {
+        let query_family = match family.as_str() {
+            "serif" => fontdb::Family::Serif,
+            "sans-serif" => fontdb::Family::SansSerif,
+            "monospace" => fontdb::Family::Monospace,
+            "cursive" => fontdb::Family::Cursive,
+            "fantasy" => fontdb::Family::Fantasy,
+            name => fontdb::Family::Name(name),
+        };
+        let query = fontdb::Query {
+            families: &[query_family],
+            ..Default::default()
+        };
+        if fontdb.query(&query).is_none() {
+            report(
+                Severity::Warning,
+                format!(
+                    "Font \"{}\" isn't available; text falls back to another font",
+                    family
+                ),
+                None,
+            );
+        }
+    }
+
+    let elements: usize = info.elements.values().sum();
+    if elements > MANY_ELEMENTS {
+        report(
+            Severity::Warning,
+            format!("{} elements; converting may be slow", elements),
+            None,
+        );
+    }
+
+    issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    Ok(issues)
+}
@@ -0,0 +1,137 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use svg_to_png::template;
+
+/// Size past which the log moves to `.log.1`, shifting older logs along.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Older logs kept next to the current one.
+const ROTATED_LOGS: usize = 3;
+
+/// Crates whose info messages are kept; the rest only log warnings and errors.
+const VERBOSE_TARGETS: [&str; 5] = [
+    "svg_to_png",
+    "rust_svg_converter",
+    "usvg",
+    "resvg",
+    "svg2pdf",
+];
+
+/// `logs/rust-svg-converter.log` in the app's config folder.
+pub fn path() -> Option<PathBuf> {
+    eframe::storage_dir(crate::app::APP_NAME)
+        .map(|dir| dir.join("logs").join("rust-svg-converter.log"))
+}
+
+/// Sends log messages, such as each file converted with its settings and timing and the
+/// warnings of the SVG parser, to the log file.
+pub fn init() {
+    let Some(path) = path() else {
+        return;
+    };
+    let logger = FileLogger {
+        path,
+        file: Mutex::new(None),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Writes the rotated logs and the current one, oldest first, into a single file.
+pub fn export(destination: &Path) -> std::io::Result<()> {
+    log::logger().flush();
+    let path = path().ok_or_else(|| std::io::Error::other("There is no log folder"))?;
+    let mut output = File::create(destination)?;
+    for index in (1..=ROTATED_LOGS).rev() {
+        if let Ok(data) = std::fs::read(rotated_path(&path, index)) {
+            output.write_all(&data)?;
+        }
+    }
+    match std::fs::read(&path) {
+        Ok(data) => output.write_all(&data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("log.{}", index))
+}
+
+struct FileLogger {
+    path: PathBuf,
+    /// The open log and its size, opened on the first message.
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl FileLogger {
+    fn open(&self) -> std::io::Result<(File, u64)> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+
+    fn rotate(&self) {
+        for index in (1..ROTATED_LOGS).rev() {
+            let _ = std::fs::rename(
+                rotated_path(&self.path, index),
+                rotated_path(&self.path, index + 1),
+            );
+        }
+        let _ = std::fs::rename(&self.path, rotated_path(&self.path, 1));
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+            || VERBOSE_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {} {}\n",
+            template::timestamp(SystemTime::now()),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            *file = self.open().ok();
+        }
+        if matches!(&*file, Some((_, size)) if *size >= MAX_LOG_SIZE) {
+            *file = None;
+            self.rotate();
+            *file = self.open().ok();
+        }
+        if let Some((file, size)) = file.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                *size += line.len() as u64;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some((file, _)) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::canvas::CanvasOptions;
+use crate::color::ColorOptions;
+use crate::convert::{parse_tree, ConversionOptions};
+use crate::svg_writer::{self, escape, Writer};
+use std::collections::HashSet;
+use std::ops::Range;
+use usvg::{roxmltree, Node, NodeExt, NodeKind, Paint};
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+
+/// Attributes that decide whether a `<switch>` picks an element, kept on the marker group.
+const CONDITIONS: [&str; 3] = ["systemLanguage", "requiredFeatures", "requiredExtensions"];
+
+/// Elements whose content is only drawn through `<use>`.
+const TEMPLATES: [&str; 2] = ["defs", "symbol"];
+
+/// Writes the SVG with every `<text>` replaced by its outlined paths and the rest of the
+/// markup left as written, so it renders the same without the fonts installed.
+pub fn save(
+    svg_data: &str,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if svg_writer::same_file(input_path, output_path) {
+        return Err(format!("Writing {} would overwrite the input", output_path).into());
+    }
+    std::fs::write(output_path, outline_text(svg_data, input_path, options)?)?;
+    Ok(())
+}
+
+/// How one `<text>` is found again in the parsed tree.
+struct Marker {
+    id: String,
+    /// Inside a `<clipPath>`, where groups aren't allowed, the text itself carries the id.
+    in_clip: bool,
+    /// Whether the id was added to the text rather than already on it.
+    generated: bool,
+    /// For text only drawn through `<use>`, the id of a `<use>` of the marker added to the
+    /// end of the document, since copies made by `<use>` lose their ids.
+    probe: Option<String>,
+}
+
+/// The markup with each `<text>` swapped for a group of paths. Text that can't be laid
+/// out, e.g. because no font covers it, is left as it was.
+pub fn outline_text(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let document = parse(svg_data)?;
+    let texts: Vec<roxmltree::Node> = document
+        .descendants()
+        .filter(|node| node.has_tag_name((SVG_NS, "text")))
+        .collect();
+    if texts.is_empty() {
+        return Ok(svg_data.to_string());
+    }
+    let mut taken: HashSet<String> = document
+        .descendants()
+        .filter_map(|node| node.attribute("id"))
+        .map(String::from)
+        .collect();
+
+    // Wrap each text in a group with a known id, whose children after parsing are exactly
+    // what the text turned into.
+    let mut insertions = Vec::new();
+    let mut markers = Vec::new();
+    for text in &texts {
+        let range = text.range();
+        let in_clip = text
+            .parent_element()
+            .is_some_and(|parent| parent.has_tag_name((SVG_NS, "clipPath")));
+        if in_clip {
+            if let Some(id) = text.attribute("id") {
+                markers.push(Marker {
+                    id: id.to_string(),
+                    in_clip,
+                    generated: false,
+                    probe: None,
+                });
+                continue;
+            }
+            let id = unique_id(&mut taken);
+            let position = range.start + name_length(&svg_data[range.start..]);
+            insertions.push((position, format!(" id=\"{}\"", id)));
+            markers.push(Marker {
+                id,
+                in_clip,
+                generated: true,
+                probe: None,
+            });
+        } else {
+            let id = unique_id(&mut taken);
+            let conditions: String = text
+                .attributes()
+                .filter(|attribute| {
+                    attribute.namespace().is_none() && CONDITIONS.contains(&attribute.name())
+                })
+                .map(attribute_markup)
+                .collect();
+            insertions.push((range.start, format!("<g id=\"{}\"{}>", id, conditions)));
+            insertions.push((range.end, String::from("</g>")));
+            let in_template = text.ancestors().any(|ancestor| {
+                ancestor.tag_name().namespace() == Some(SVG_NS)
+                    && TEMPLATES.contains(&ancestor.tag_name().name())
+            });
+            let probe = in_template.then(|| unique_id(&mut taken));
+            markers.push(Marker {
+                id,
+                in_clip,
+                generated: false,
+                probe,
+            });
+        }
+    }
+    let probes: String = markers
+        .iter()
+        .filter_map(|marker| {
+            Some(format!(
+                "<use xmlns=\"{}\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                 id=\"{}\" xlink:href=\"#{}\"/>",
+                SVG_NS,
+                marker.probe.as_ref()?,
+                marker.id
+            ))
+        })
+        .collect();
+    if !probes.is_empty() {
+        insertions.push((end_tag_start(svg_data, document.root_element()), probes));
+    }
+    let mut marked = svg_data.to_string();
+    // Later insertions at the same position go after earlier ones, e.g. `</g><g …>`.
+    insertions.sort_by_key(|&(position, _)| position);
+    for (position, insertion) in insertions.iter().rev() {
+        marked.insert_str(*position, insertion);
+    }
+
+    // Only text changes; cropping and recoloring belong to rendering.
+    let parse_options = ConversionOptions {
+        canvas: CanvasOptions::default(),
+        colors: ColorOptions::default(),
+        ..options.clone()
+    };
+    let rtree = parse_tree(&marked, input_path, &parse_options)?;
+
+    let mut writer = Writer::fragment(&rtree.root, taken);
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    for (text, marker) in texts.iter().zip(&markers) {
+        let mut paths = String::new();
+        match find(&rtree.root, &marker.id) {
+            Some(node) if marker.in_clip => {
+                if marker.generated {
+                    if let NodeKind::Group(ref mut group) = *node.borrow_mut() {
+                        group.id.clear();
+                    }
+                }
+                // Transforms and clip paths on the text become a group around its paths.
+                let target = match node.parent() {
+                    Some(parent) if parent.parent().is_some() => parent,
+                    _ => node,
+                };
+                writer.write(&mut paths, &target, true);
+            }
+            Some(node) => {
+                for child in node.children() {
+                    writer.write(&mut paths, &child, false);
+                }
+            }
+            // The probe's child is its copy of the marker, where the text's id is lost.
+            None => {
+                let copy = marker
+                    .probe
+                    .as_ref()
+                    .and_then(|probe| find(&rtree.root, probe))
+                    .and_then(|probe| probe.first_child());
+                for child in copy.iter().flat_map(Node::children) {
+                    writer.write(&mut paths, &child, false);
+                }
+                if let Some(id) = text.attribute("id").filter(|_| !paths.is_empty()) {
+                    paths = format!("<g id=\"{}\">{}</g>", escape(id), paths);
+                }
+            }
+        }
+        if !paths.is_empty() {
+            replacements.push((text.range(), paths));
+        }
+    }
+
+    let mut outlined = svg_data.to_string();
+    for (range, paths) in replacements.into_iter().rev() {
+        outlined.replace_range(range, &paths);
+    }
+    if !writer.defs().is_empty() {
+        let root_start = document.root_element().range().start;
+        let position = start_tag_end(&outlined, root_start);
+        outlined.insert_str(position, &format!("<defs>{}</defs>", writer.defs()));
+    }
+    Ok(outlined)
+}
+
+fn parse(svg_data: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    roxmltree::Document::parse_with_options(
+        svg_data,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// An id not yet in the document, which is then taken.
+fn unique_id(taken: &mut HashSet<String>) -> String {
+    (1..)
+        .map(|n| format!("outline-text-{}", n))
+        .find(|id| taken.insert(id.clone()))
+        .unwrap()
+}
+
+/// Length of `<name` at the start of a tag, where attributes can be inserted.
+pub fn name_length(tag: &str) -> usize {
+    tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(tag.len())
+}
+
+/// Position just after the `>` of the start tag beginning at `start`.
+fn start_tag_end(svg_data: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in svg_data[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return start + i + 1,
+            _ => {}
+        }
+    }
+    svg_data.len()
+}
+
+/// Position of the `</` that ends `element`.
+fn end_tag_start(svg_data: &str, element: roxmltree::Node) -> usize {
+    let range = element.range();
+    svg_data[range.clone()]
+        .rfind("</")
+        .map_or(range.end, |i| range.start + i)
+}
+
+fn attribute_markup(attribute: roxmltree::Attribute) -> String {
+    format!(" {}=\"{}\"", attribute.name(), escape(attribute.value()))
+}
+
+/// The first node with `id` under `root`, including inside clip paths, masks and patterns.
+fn find(root: &Node, id: &str) -> Option<Node> {
+    for node in root.descendants() {
+        if &*node.id() == id {
+            return Some(node);
+        }
+        let mut roots = Vec::new();
+        match *node.borrow() {
+            NodeKind::Group(ref group) => {
+                roots.extend(group.clip_path.iter().map(|clip| clip.root.clone()));
+                roots.extend(group.mask.iter().map(|mask| mask.root.clone()));
+            }
+            NodeKind::Path(ref path) => {
+                let paints = path
+                    .fill
+                    .iter()
+                    .map(|fill| &fill.paint)
+                    .chain(path.stroke.iter().map(|stroke| &stroke.paint));
+                for paint in paints {
+                    if let Paint::Pattern(pattern) = paint {
+                        roots.push(pattern.root.clone());
+                    }
+                }
+            }
+            NodeKind::Image(_) | NodeKind::Text(_) => {}
+        }
+        if let Some(found) = roots.iter().find_map(|root| find(root, id)) {
+            return Some(found);
+        }
+    }
+    None
+}
@@ -0,0 +1,250 @@
+use crate::convert::{render_tree, ConversionOptions};
+use crate::encode::{self, OutputFormat};
+use crate::icon::{self, render_square};
+use crate::template::input_name;
+use std::path::Path;
+use usvg::Tree;
+
+/// A set of files for one platform, written into a folder instead of a single image.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum Package {
+    /// Favicons, the Apple touch icon, web app icons and `site.webmanifest`.
+    Favicon,
+    /// `drawable-mdpi` through `drawable-xxxhdpi` folders, for an Android `res` folder.
+    Android,
+    /// An Xcode asset catalog `.imageset` with 1x, 2x and 3x images and `Contents.json`.
+    Ios,
+}
+
+impl Package {
+    pub const ALL: [Package; 3] = [Package::Favicon, Package::Android, Package::Ios];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Package::Favicon => "Favicon",
+            Package::Android => "Android drawables",
+            Package::Ios => "iOS/macOS imageset",
+        }
+    }
+
+    /// Writes the package for an SVG into `dir` and returns the written paths. Image sets
+    /// go in `dir` with an `.imageset` extension, as Xcode expects.
+    pub fn write(
+        self,
+        rtree: &Tree,
+        input_path: &str,
+        dir: &Path,
+        options: &ConversionOptions,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let dir = match self {
+            Package::Ios => dir.with_extension("imageset"),
+            _ => dir.to_path_buf(),
+        };
+        std::fs::create_dir_all(&dir)?;
+        match self {
+            Package::Favicon => write_favicon(rtree, input_path, &dir, options),
+            Package::Android => write_android(rtree, input_path, &dir, options),
+            Package::Ios => write_imageset(rtree, input_path, &dir, options),
+        }
+    }
+}
+
+/// Settings for density-based packages.
+#[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PackageOptions {
+    /// Longer side of the 1x (mdpi) image in dp or points; `None` sizes it like a normal
+    /// conversion.
+    pub base_size: Option<u32>,
+    /// Also write an unscaled 1x copy to `drawable-nodpi`.
+    pub android_nodpi: bool,
+}
+
+impl PackageOptions {
+    /// Options that render the tree at `factor` times the 1x size.
+    fn at_density(
+        &self,
+        rtree: &Tree,
+        options: &ConversionOptions,
+        factor: f32,
+    ) -> ConversionOptions {
+        let Some(base_size) = self.base_size else {
+            return options.at_density(factor);
+        };
+        let longer_side = rtree.size.width().max(rtree.size.height()) as f32;
+        ConversionOptions {
+            scale: base_size as f32 / longer_side * factor,
+            width: None,
+            height: None,
+            print_width: None,
+            print_height: None,
+            density_variants: Vec::new(),
+            ..options.clone()
+        }
+    }
+}
+
+/// Android density buckets and their scale relative to mdpi.
+const ANDROID_DENSITIES: [(&str, f32); 5] = [
+    ("mdpi", 1.0),
+    ("hdpi", 1.5),
+    ("xhdpi", 2.0),
+    ("xxhdpi", 3.0),
+    ("xxxhdpi", 4.0),
+];
+
+/// Square PNGs in a favicon package, by file name.
+const FAVICON_PNGS: [(&str, u32); 5] = [
+    ("favicon-16.png", 16),
+    ("favicon-32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("icon-192.png", 192),
+    ("icon-512.png", 512),
+];
+
+fn write_favicon(
+    rtree: &Tree,
+    input_path: &str,
+    dir: &Path,
+    options: &ConversionOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let background = options.background.unwrap_or([255, 255, 255]);
+    let png = ConversionOptions {
+        format: OutputFormat::Png,
+        ico_sizes: vec![16, 32, 48],
+        ..options.clone()
+    };
+    // iOS paints transparent touch icons black, so that one always gets a background.
+    let opaque = ConversionOptions {
+        background: Some(background),
+        ..png.clone()
+    };
+
+    let mut written = Vec::new();
+    for (file_name, size) in FAVICON_PNGS {
+        let options = if file_name == "apple-touch-icon.png" {
+            &opaque
+        } else {
+            &png
+        };
+        let path = dir.join(file_name).display().to_string();
+        encode::save(
+            &render_square(rtree, size, options)?,
+            input_path,
+            &path,
+            options,
+        )?;
+        written.push(path);
+    }
+
+    let ico_path = dir.join("favicon.ico").display().to_string();
+    icon::save_ico(rtree, &ico_path, &png)?;
+    written.push(ico_path);
+
+    let name = input_name(input_path);
+    let color = format!(
+        "#{:02x}{:02x}{:02x}",
+        background[0], background[1], background[2]
+    );
+    let manifest = serde_json::json!({
+        "name": name,
+        "short_name": name,
+        "icons": [
+            { "src": "/icon-192.png", "sizes": "192x192", "type": "image/png" },
+            { "src": "/icon-512.png", "sizes": "512x512", "type": "image/png" },
+        ],
+        "theme_color": color,
+        "background_color": color,
+        "display": "standalone",
+    });
+    let manifest_path = dir.join("site.webmanifest").display().to_string();
+    std::fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+    written.push(manifest_path);
+
+    Ok(written)
+}
+
+fn write_android(
+    rtree: &Tree,
+    input_path: &str,
+    dir: &Path,
+    options: &ConversionOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // Resource names may only hold lowercase letters, digits and underscores.
+    let name: String = input_name(input_path)
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '_') => c,
+            _ => '_',
+        })
+        .collect();
+    let file_name = format!("{}.png", name);
+    let png = ConversionOptions {
+        format: OutputFormat::Png,
+        ..options.clone()
+    };
+
+    let mut folders: Vec<(&str, f32)> = ANDROID_DENSITIES.to_vec();
+    if options.package_options.android_nodpi {
+        folders.push(("nodpi", 1.0));
+    }
+    let mut written = Vec::new();
+    for (density, factor) in folders {
+        let variant = options.package_options.at_density(rtree, &png, factor);
+        let folder = dir.join(format!("drawable-{}", density));
+        std::fs::create_dir_all(&folder)?;
+        let path = folder.join(&file_name).display().to_string();
+        encode::save(&render_tree(rtree, &variant)?, input_path, &path, &variant)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn write_imageset(
+    rtree: &Tree,
+    input_path: &str,
+    dir: &Path,
+    options: &ConversionOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let name = input_name(input_path);
+    let png = ConversionOptions {
+        format: OutputFormat::Png,
+        ..options.clone()
+    };
+
+    let mut written = Vec::new();
+    let mut images = Vec::new();
+    for factor in [1, 2, 3] {
+        let file_name = if factor == 1 {
+            format!("{}.png", name)
+        } else {
+            format!("{}@{}x.png", name, factor)
+        };
+        let variant = options
+            .package_options
+            .at_density(rtree, &png, factor as f32);
+        let path = dir.join(&file_name).display().to_string();
+        encode::save(&render_tree(rtree, &variant)?, input_path, &path, &variant)?;
+        written.push(path);
+        images.push(serde_json::json!({
+            "filename": file_name,
+            "idiom": "universal",
+            "scale": format!("{}x", factor),
+        }));
+    }
+
+    let contents = serde_json::json!({
+        "images": images,
+        "info": { "author": "xcode", "version": 1 },
+    });
+    let contents_path = dir.join("Contents.json").display().to_string();
+    std::fs::write(
+        &contents_path,
+        serde_json::to_string_pretty(&contents)? + "\n",
+    )?;
+    written.push(contents_path);
+    Ok(written)
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+/// How shapes are anti-aliased, like the SVG `shape-rendering` property.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum ShapeRendering {
+    OptimizeSpeed,
+    CrispEdges,
+    GeometricPrecision,
+}
+
+/// How text is rendered, like the SVG `text-rendering` property.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum TextRendering {
+    OptimizeSpeed,
+    OptimizeLegibility,
+    GeometricPrecision,
+}
+
+/// How embedded raster images are scaled, like the SVG `image-rendering` property.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum ImageRendering {
+    OptimizeQuality,
+    OptimizeSpeed,
+}
+
+impl ShapeRendering {
+    pub const ALL: [ShapeRendering; 3] = [
+        ShapeRendering::OptimizeSpeed,
+        ShapeRendering::CrispEdges,
+        ShapeRendering::GeometricPrecision,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapeRendering::OptimizeSpeed => "optimizeSpeed",
+            ShapeRendering::CrispEdges => "crispEdges",
+            ShapeRendering::GeometricPrecision => "geometricPrecision",
+        }
+    }
+
+    fn to_usvg(self) -> usvg::ShapeRendering {
+        match self {
+            ShapeRendering::OptimizeSpeed => usvg::ShapeRendering::OptimizeSpeed,
+            ShapeRendering::CrispEdges => usvg::ShapeRendering::CrispEdges,
+            ShapeRendering::GeometricPrecision => usvg::ShapeRendering::GeometricPrecision,
+        }
+    }
+}
+
+impl TextRendering {
+    pub const ALL: [TextRendering; 3] = [
+        TextRendering::OptimizeSpeed,
+        TextRendering::OptimizeLegibility,
+        TextRendering::GeometricPrecision,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextRendering::OptimizeSpeed => "optimizeSpeed",
+            TextRendering::OptimizeLegibility => "optimizeLegibility",
+            TextRendering::GeometricPrecision => "geometricPrecision",
+        }
+    }
+
+    fn to_usvg(self) -> usvg::TextRendering {
+        match self {
+            TextRendering::OptimizeSpeed => usvg::TextRendering::OptimizeSpeed,
+            TextRendering::OptimizeLegibility => usvg::TextRendering::OptimizeLegibility,
+            TextRendering::GeometricPrecision => usvg::TextRendering::GeometricPrecision,
+        }
+    }
+}
+
+impl ImageRendering {
+    pub const ALL: [ImageRendering; 2] = [
+        ImageRendering::OptimizeQuality,
+        ImageRendering::OptimizeSpeed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageRendering::OptimizeQuality => "optimizeQuality",
+            ImageRendering::OptimizeSpeed => "optimizeSpeed",
+        }
+    }
+
+    fn to_usvg(self) -> usvg::ImageRendering {
+        match self {
+            ImageRendering::OptimizeQuality => usvg::ImageRendering::OptimizeQuality,
+            ImageRendering::OptimizeSpeed => usvg::ImageRendering::OptimizeSpeed,
+        }
+    }
+}
+
+/// usvg settings used while parsing, for tuning a conversion beyond the defaults.
+///
+/// The rendering modes only apply where the SVG doesn't set the property itself.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Folder relative `href`s such as `images/photo.png` resolve against; `None` uses the
+    /// input file's folder.
+    pub resources_dir: Option<PathBuf>,
+    /// Refuse SVGs that reference external files or URLs, for converting untrusted input.
+    pub safe_mode: bool,
+    /// Strip scripts, event handlers and `<foreignObject>` from the markup before converting.
+    pub sanitize: bool,
+    /// Also save the sanitized markup next to the output, as `name.sanitized.svg`.
+    pub write_sanitized: bool,
+    /// Resolution for converting physical units such as `mm` and `in` inside the SVG.
+    pub dpi: f64,
+    /// Languages matched against `systemLanguage` in `<switch>` elements.
+    pub languages: Vec<String>,
+    pub shape_rendering: ShapeRendering,
+    pub text_rendering: TextRendering,
+    pub image_rendering: ImageRendering,
+    /// Size used when the SVG has neither a `viewBox` nor a width and height.
+    pub default_size: (f64, f64),
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            resources_dir: None,
+            safe_mode: false,
+            sanitize: false,
+            write_sanitized: false,
+            dpi: 96.0,
+            languages: vec![String::from("en")],
+            shape_rendering: ShapeRendering::GeometricPrecision,
+            text_rendering: TextRendering::OptimizeLegibility,
+            image_rendering: ImageRendering::OptimizeQuality,
+            default_size: (100.0, 100.0),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Sets these options on `options` for parsing the SVG at `input_path`.
+    pub fn apply(&self, options: &mut usvg::Options, input_path: &str) {
+        options.resources_dir = self.resources_dir.clone().or_else(|| {
+            // Downloads and pasted markup have no folder of their own.
+            let parent = Path::new(input_path).parent()?;
+            (!crate::convert::is_url(input_path) && !parent.as_os_str().is_empty())
+                .then(|| parent.to_path_buf())
+        });
+        options.dpi = self.dpi;
+        options.languages = self.languages.clone();
+        options.shape_rendering = self.shape_rendering.to_usvg();
+        options.text_rendering = self.text_rendering.to_usvg();
+        options.image_rendering = self.image_rendering.to_usvg();
+        if let Some(size) = usvg::Size::new(self.default_size.0, self.default_size.1) {
+            options.default_size = size;
+        }
+    }
+}
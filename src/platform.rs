@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Opens the file in the app the desktop associates with it.
+pub fn open(path: &str) -> std::io::Result<()> {
+    open_command(path).spawn()?;
+    Ok(())
+}
+
+/// Shows the file in the desktop's file manager, selected where the platform allows it.
+pub fn reveal(path: &str) -> std::io::Result<()> {
+    // File managers want absolute paths, and outputs are often relative to the working dir.
+    let path = std::path::absolute(path)?;
+    reveal_path(&path)
+}
+
+#[cfg(target_os = "macos")]
+fn open_command(path: &str) -> Command {
+    let mut command = Command::new("open");
+    command.arg(path);
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_path(path: &Path) -> std::io::Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn open_command(path: &str) -> Command {
+    let mut command = Command::new("explorer");
+    command.arg(path);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_path(path: &Path) -> std::io::Result<()> {
+    // Explorer reads `/select,` and the path as one argument.
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(path);
+    Command::new("explorer").arg(select).spawn()?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn open_command(path: &str) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(path);
+    command
+}
+
+/// Asks the file manager to select the file over D-Bus, which most Linux file managers
+/// support, and otherwise opens the folder holding it. The answer is waited for on a
+/// thread of its own so the UI doesn't stall.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_path(path: &Path) -> std::io::Result<()> {
+    let uri = file_uri(path);
+    let folder = path.parent().unwrap_or(path).to_path_buf();
+    std::thread::spawn(move || {
+        let selected = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--reply-timeout=2000",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !selected {
+            if let Err(e) = Command::new("xdg-open").arg(&folder).spawn() {
+                log::warn!("reveal failed path={:?} error={:?}", folder, e.to_string());
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A `file://` URI for an absolute path, percent-encoding everything but unreserved
+/// characters and slashes.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+use svg_to_png::convert::ConversionOptions;
+
+/// A named combination of conversion settings and output template.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Preset {
+    pub name: String,
+    /// Output path or folder, may contain template tokens.
+    pub output: String,
+    pub options: ConversionOptions,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct PresetFile {
+    #[serde(default, rename = "preset")]
+    presets: Vec<Preset>,
+}
+
+/// `presets.toml` in the app's config folder, next to its saved settings.
+pub fn presets_path() -> Option<PathBuf> {
+    eframe::storage_dir(crate::app::APP_NAME).map(|dir| dir.join("presets.toml"))
+}
+
+/// Reads the saved presets; a missing file means there are none yet.
+pub fn load_presets() -> Result<Vec<Preset>, Box<dyn std::error::Error>> {
+    let path = presets_path().ok_or("No config folder for presets")?;
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(toml::from_str::<PresetFile>(&data)?.presets),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_presets(presets: &[Preset]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = presets_path().ok_or("No config folder for presets")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = PresetFile {
+        presets: presets.to_vec(),
+    };
+    std::fs::write(path, toml::to_string_pretty(&file)?)?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::ops::Range;
+use usvg::roxmltree;
+
+/// Elements that can run code or embed arbitrary HTML.
+const UNSAFE_ELEMENTS: [&str; 2] = ["script", "foreignObject"];
+
+/// Animation elements that can set an attribute, and so could turn a link into a script.
+/// `animateMotion` and `animateTransform` only move and transform, and `discard` only
+/// removes elements, so none of those can change a link.
+const ANIMATION_ELEMENTS: [&str; 3] = ["set", "animate", "animateColor"];
+
+/// Strips scripting from SVG markup: `<script>` and `<foreignObject>` elements, `on*` event
+/// handler attributes, `javascript:` links and animations that change links. Returns the
+/// cleaned markup and the number of items removed.
+///
+/// Everything else is copied through byte for byte, so the output stays as readable as the
+/// input.
+pub fn sanitize(svg_data: &str) -> Result<(String, usize), roxmltree::Error> {
+    let document = roxmltree::Document::parse_with_options(
+        svg_data,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )?;
+
+    let mut removed: Vec<Range<usize>> = Vec::new();
+    for node in document.descendants().filter(|node| node.is_element()) {
+        let name = node.tag_name().name();
+        // `attributeName` may carry a prefix, as in `xlink:href`.
+        let animates_link = ANIMATION_ELEMENTS.contains(&name)
+            && node
+                .attribute("attributeName")
+                .is_some_and(|target| target.trim().rsplit(':').next() == Some("href"));
+        if UNSAFE_ELEMENTS.contains(&name) || animates_link {
+            removed.push(node.range());
+            continue;
+        }
+        for attribute in node.attributes() {
+            let name = attribute.name();
+            let is_handler = name.len() > 2
+                && name
+                    .get(..2)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("on"));
+            let is_script_link = name == "href" && is_script_url(attribute.value());
+            if is_handler || is_script_link {
+                if let Some(range) = attribute_range(svg_data, attribute.position()) {
+                    removed.push(range);
+                }
+            }
+        }
+    }
+
+    // Attributes and children of removed elements come after them, so the sorted ranges
+    // only need checking against the previous one.
+    removed.sort_by_key(|range| range.start);
+    let mut sanitized = String::with_capacity(svg_data.len());
+    let mut copied_to = 0;
+    let mut count = 0;
+    for range in removed {
+        if range.start < copied_to {
+            continue;
+        }
+        sanitized.push_str(&svg_data[copied_to..range.start]);
+        copied_to = range.end;
+        count += 1;
+    }
+    sanitized.push_str(&svg_data[copied_to..]);
+
+    Ok((sanitized, count))
+}
+
+/// Whether a link runs script. Browsers drop ASCII whitespace and control characters from
+/// URLs before reading the scheme, so `java\tscript:` counts too.
+fn is_script_url(url: &str) -> bool {
+    let scheme: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .take("javascript:".len())
+        .collect();
+    scheme.eq_ignore_ascii_case("javascript:")
+}
+
+/// Byte range of the attribute starting at `start`, from its name to the closing quote.
+pub fn attribute_range(svg_data: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &svg_data[start..];
+    let equals = rest.find('=')?;
+    let value = rest[equals + 1..].trim_start();
+    let quote = value.chars().next()?;
+    let value_start = rest.len() - value.len() + 1;
+    let value_len = rest[value_start..].find(quote)?;
+    Some(start..start + value_start + value_len + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">{}</svg>"#,
+            body
+        )
+    }
+
+    fn sanitized(body: &str) -> (String, usize) {
+        sanitize(&svg(body)).unwrap()
+    }
+
+    #[test]
+    fn strips_event_handlers() {
+        let (cleaned, removed) = sanitized(r#"<rect onclick="alert(1)" ONLOAD="x" opacity="1"/>"#);
+        assert_eq!(cleaned, svg(r#"<rect   opacity="1"/>"#));
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn strips_script_links() {
+        let (cleaned, removed) = sanitized(concat!(
+            r#"<a href="java&#9;script:alert(1)"/>"#,
+            r#"<a xlink:href=" JaVaScRiPt:alert(2)"/>"#,
+            r#"<a href="https://example.com/"/>"#,
+        ));
+        assert_eq!(
+            cleaned,
+            svg(r#"<a /><a /><a href="https://example.com/"/>"#)
+        );
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn strips_animations_of_links() {
+        let (cleaned, removed) = sanitized(concat!(
+            r#"<a><set attributeName="href" to="javascript:alert(1)"/>"#,
+            r#"<animate attributeName="xlink:href" values="javascript:alert(2)"/>"#,
+            r#"<animate attributeName="x" values="0;5"/></a>"#,
+        ));
+        assert_eq!(
+            cleaned,
+            svg(r#"<a><animate attributeName="x" values="0;5"/></a>"#)
+        );
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn counts_nested_removals_once() {
+        let (cleaned, removed) = sanitized(concat!(
+            r#"<foreignObject onload="x"><script>alert(1)</script></foreignObject>"#,
+            r#"<script href="javascript:alert(2)"/>"#,
+        ));
+        assert_eq!(cleaned, svg(""));
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn keeps_multibyte_attribute_names() {
+        let (cleaned, removed) = sanitized(r#"<rect 中a="1" o中="2"/>"#);
+        assert_eq!(cleaned, svg(r#"<rect 中a="1" o中="2"/>"#));
+        assert_eq!(removed, 0);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::cli::{parse_hex_color, parse_scale, report};
+use clap::ValueEnum;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use svg_to_png::convert::BatchError;
+use svg_to_png::{ConversionOptions, Converter, OutputFormat};
+
+/// Largest request body accepted, so one request can't exhaust memory.
+const MAX_BODY: usize = 32 * 1024 * 1024;
+
+/// Longest request line or header accepted, and how many headers, so headers can't
+/// exhaust memory either.
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// How long a client may stall while sending its request, and how long it may take to send
+/// all of it, so slow clients can't hold on to the workers.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_DEADLINE: Duration = Duration::from_secs(60);
+
+/// Largest image rendered, in pixels, so a query like `?scale=1000` can't exhaust memory.
+const MAX_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Connections waiting for a free worker before new ones are turned away with a 503, and
+/// how many of those may wait for their 503 before further ones are just closed.
+const MAX_QUEUED: usize = 64;
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: &'static str, message: impl Into<String>) -> Self {
+        let mut body = message.into().into_bytes();
+        body.push(b'\n');
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    fn write_to(&self, mut stream: &TcpStream) -> std::io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )?;
+        if self.status.starts_with("405") {
+            write!(stream, "Allow: POST\r\n")?;
+        }
+        write!(stream, "\r\n")?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+/// Serves `POST /convert` on `address` until the process is stopped, and returns the exit
+/// code if it can't start.
+///
+/// Each request body is converted with `options`, overridden by the query string. Requests
+/// are rendered in safe mode, so SVGs sent to the server can't read files from its disk.
+/// A fixed pool of workers, one per core, answers requests.
+pub fn run(address: &str, mut options: ConversionOptions, json: bool) -> i32 {
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            let error = BatchError::from(Box::<dyn std::error::Error>::from(e));
+            return report(
+                json,
+                &format!("Error listening on {}", address),
+                None,
+                &error,
+            );
+        }
+    };
+    if let Ok(address) = listener.local_addr() {
+        println!("Listening on http://{}/convert", address);
+    }
+
+    options.parse.safe_mode = true;
+    let options = Arc::new(options);
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(MAX_QUEUED);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    for _ in 0..workers {
+        let options = Arc::clone(&options);
+        let receiver = Arc::clone(&receiver);
+        std::thread::spawn(move || loop {
+            // The lock is only held while waiting, not while answering.
+            let stream = match receiver.lock().map(|receiver| receiver.recv()) {
+                Ok(Ok(stream)) => stream,
+                _ => return,
+            };
+            if let Err(e) = handle(&stream, &options) {
+                eprintln!("Error answering request: {}", e);
+            }
+        });
+    }
+
+    // Turned away on a thread of their own, so a client slow to read its 503 doesn't hold up
+    // accepting connections.
+    let (reject, rejected) = mpsc::sync_channel::<TcpStream>(MAX_QUEUED);
+    std::thread::spawn(move || {
+        for stream in rejected {
+            let response = Response::text("503 Service Unavailable", "The server is busy");
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            let _ = response.write_to(&stream);
+        }
+    });
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(TrySendError::Full(stream)) = sender.try_send(stream) {
+            let _ = reject.try_send(stream);
+        }
+    }
+    0
+}
+
+fn handle(stream: &TcpStream, options: &ConversionOptions) -> std::io::Result<()> {
+    let mut reader = BufReader::new(DeadlineReader {
+        stream,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    });
+    let response = match read_request(&mut reader, &mut &*stream) {
+        // A panic while converting only fails this request, and the worker stays alive.
+        Ok(request) => std::panic::catch_unwind(AssertUnwindSafe(|| respond(&request, options)))
+            .unwrap_or_else(|_| {
+                Response::text("500 Internal Server Error", "Converting the SVG failed")
+            }),
+        Err(response) => response,
+    };
+    response.write_to(stream)
+}
+
+/// Reads from the stream until the deadline, each read waiting at most [`READ_TIMEOUT`].
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left.min(READ_TIMEOUT)))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Reads a request from `reader`, telling clients that wait for it through `writer` to go
+/// on sending the body.
+fn read_request(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<Request, Response> {
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Response::text("400 Bad Request", "Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        body: Vec::new(),
+    };
+
+    let mut content_length = 0;
+    let mut expect_continue = false;
+    for headers in 0.. {
+        line.clear();
+        read_line(reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(Response::text(
+                "431 Request Header Fields Too Large",
+                format!("At most {} headers are accepted", MAX_HEADERS),
+            ));
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(Response::text("400 Bad Request", "Malformed header"));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| Response::text("400 Bad Request", "Invalid Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(Response::text(
+                "411 Length Required",
+                "Send the SVG with a Content-Length rather than chunked",
+            ));
+        } else if name.eq_ignore_ascii_case("expect") {
+            expect_continue = value.eq_ignore_ascii_case("100-continue");
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Err(Response::text(
+            "413 Payload Too Large",
+            format!("SVGs are limited to {} MB", MAX_BODY / 1024 / 1024),
+        ));
+    }
+    // curl waits for this before sending larger bodies.
+    if expect_continue && content_length > 0 {
+        writer
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .map_err(read_error)?;
+    }
+    request.body = vec![0; content_length];
+    reader.read_exact(&mut request.body).map_err(read_error)?;
+    Ok(request)
+}
+
+/// The answer to a request that couldn't be read.
+fn read_error(error: std::io::Error) -> Response {
+    match error.kind() {
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => Response::text(
+            "408 Request Timeout",
+            format!(
+                "The request has to arrive within {} seconds",
+                REQUEST_DEADLINE.as_secs()
+            ),
+        ),
+        _ => Response::text("400 Bad Request", "Malformed request"),
+    }
+}
+
+/// Reads one line of at most [`MAX_LINE`] bytes, including its line break.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<(), Response> {
+    reader.take(MAX_LINE).read_line(line).map_err(read_error)?;
+    if !line.ends_with('\n') && line.len() as u64 == MAX_LINE {
+        return Err(Response::text(
+            "431 Request Header Fields Too Large",
+            format!(
+                "Request lines and headers are limited to {} bytes",
+                MAX_LINE
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn respond(request: &Request, options: &ConversionOptions) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/convert") => convert(request, options).unwrap_or_else(|response| response),
+        (_, "/convert") => Response::text(
+            "405 Method Not Allowed",
+            "POST the SVG to /convert as the request body",
+        ),
+        _ => Response::text("404 Not Found", "Not found; POST SVGs to /convert"),
+    }
+}
+
+/// Converts the request body, with `scale`, `width`, `height`, `format` and `background`
+/// from the query string overriding `defaults`.
+fn convert(request: &Request, defaults: &ConversionOptions) -> Result<Response, Response> {
+    let mut options = defaults.clone();
+    for (name, value) in form_urlencoded::parse(request.query.as_bytes()) {
+        let applied = match &*name {
+            "scale" => parse_scale(&value).map(|scale| options.scale = scale),
+            "width" => parse_pixels(&value).map(|width| options.width = Some(width)),
+            "height" => parse_pixels(&value).map(|height| options.height = Some(height)),
+            "format" => OutputFormat::from_str(&value, true).map(|format| options.format = format),
+            "background" => parse_hex_color(&value).map(|color| options.background = Some(color)),
+            _ => Err(String::from("unknown parameter")),
+        };
+        applied.map_err(|e| Response::text("400 Bad Request", format!("{}: {}", name, e)))?;
+    }
+
+    if options.format.is_animated() || options.format == OutputFormat::PngSequence {
+        return Err(Response::text(
+            "400 Bad Request",
+            format!(
+                "{} isn't served, only still images are",
+                options.format.name()
+            ),
+        ));
+    }
+
+    let svg_data = std::str::from_utf8(&request.body)
+        .map_err(|_| Response::text("400 Bad Request", "The body must be SVG markup in UTF-8"))?;
+    let converter = Converter::new(options.clone());
+    let svg_data = converter
+        .sanitize(svg_data)
+        .map_err(|e| Response::text("422 Unprocessable Entity", e.to_string()))?;
+    let rtree = converter
+        .parse(&svg_data, "request.svg")
+        .map_err(|e| Response::text("422 Unprocessable Entity", e.to_string()))?;
+    let size = options.output_size(rtree.size.width() as f32, rtree.size.height() as f32);
+    let (width, height) = options
+        .canvas
+        .tiled_size(size)
+        .map_err(|e| Response::text("413 Payload Too Large", e))?;
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(Response::text(
+            "413 Payload Too Large",
+            format!(
+                "{}×{} is larger than the {} pixels rendered at most",
+                width, height, MAX_PIXELS
+            ),
+        ));
+    }
+    let data = converter
+        .convert_tree(&rtree, &svg_data, "request.svg")
+        .map_err(|e| Response::text("422 Unprocessable Entity", e.to_string()))?;
+    Ok(Response {
+        status: "200 OK",
+        content_type: options.format.mime_type(),
+        body: data,
+    })
+}
+
+fn parse_pixels(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(pixels) if pixels > 0 => Ok(pixels),
+        _ => Err(format!("expected a positive whole number, got `{}`", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `raw` as a request, returning it and what was written back meanwhile.
+    fn read(raw: &[u8]) -> (Result<Request, Response>, Vec<u8>) {
+        let mut written = Vec::new();
+        let request = read_request(&mut &raw[..], &mut written);
+        (request, written)
+    }
+
+    fn status(result: Result<Request, Response>) -> &'static str {
+        match result {
+            Ok(_) => "request",
+            Err(response) => response.status,
+        }
+    }
+
+    fn post(query: &str, body: &str) -> Request {
+        Request {
+            method: String::from("POST"),
+            path: String::from("/convert"),
+            query: query.to_string(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"/>"#;
+
+    #[test]
+    fn reads_request() {
+        let (request, written) =
+            read(b"POST /convert?width=8 HTTP/1.1\r\nContent-Length: 4\r\n\r\n<svg");
+        let request = request.ok().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/convert");
+        assert_eq!(request.query, "width=8");
+        assert_eq!(request.body, b"<svg");
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn limits_headers() {
+        let long = format!("POST /convert HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(9000));
+        assert_eq!(
+            status(read(long.as_bytes()).0),
+            "431 Request Header Fields Too Large"
+        );
+
+        let many = format!("POST /convert HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(101));
+        assert_eq!(
+            status(read(many.as_bytes()).0),
+            "431 Request Header Fields Too Large"
+        );
+
+        let allowed = format!("POST /convert HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(100));
+        assert_eq!(status(read(allowed.as_bytes()).0), "request");
+    }
+
+    #[test]
+    fn answers_expect_continue() {
+        let (request, written) =
+            read(b"POST /convert HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 1\r\n\r\n<");
+        assert_eq!(request.ok().unwrap().body, b"<");
+        assert_eq!(written, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn rejects_chunked_and_large_bodies() {
+        let chunked = b"POST /convert HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert_eq!(status(read(chunked).0), "411 Length Required");
+
+        let large = format!(
+            "POST /convert HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(status(read(large.as_bytes()).0), "413 Payload Too Large");
+
+        let short = b"POST /convert HTTP/1.1\r\nContent-Length: 10\r\n\r\n<svg";
+        assert_eq!(status(read(short).0), "400 Bad Request");
+    }
+
+    #[test]
+    fn rejects_bad_query_overrides() {
+        let options = ConversionOptions::default();
+        for query in [
+            "scale=abc",
+            "width=0",
+            "format=bogus",
+            "background=red",
+            "size=2",
+        ] {
+            let response = convert(&post(query, SVG), &options).err().unwrap();
+            assert_eq!(response.status, "400 Bad Request", "{}", query);
+        }
+        for query in ["format=mp4", "format=gif", "format=png-sequence"] {
+            let response = convert(&post(query, SVG), &options).err().unwrap();
+            assert_eq!(response.status, "400 Bad Request", "{}", query);
+        }
+        let response = convert(&post("scale=10000", SVG), &options).err().unwrap();
+        assert_eq!(response.status, "413 Payload Too Large");
+    }
+
+    #[test]
+    fn converts_with_overrides() {
+        let options = ConversionOptions::default();
+        let response = convert(&post("width=8&format=png", SVG), &options)
+            .ok()
+            .unwrap();
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(response.content_type, "image/png");
+        assert!(response.body.starts_with(b"\x89PNG"));
+    }
+}
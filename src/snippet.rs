@@ -0,0 +1,110 @@
+use crate::atlas::css_class;
+use crate::svg_writer::escape;
+use crate::template::input_name;
+use std::path::Path;
+
+/// Markup written next to the density variants, referencing each of them.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum Snippet {
+    /// An `<img>` tag with `srcset`, `width` and `height`.
+    Html,
+    /// A class with a `background-image: image-set(…)` and the 1x size.
+    Css,
+}
+
+impl Snippet {
+    pub const ALL: [Snippet; 2] = [Snippet::Html, Snippet::Css];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Snippet::Html => "HTML <img>",
+            Snippet::Css => "CSS background",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Snippet::Html => "html",
+            Snippet::Css => "css",
+        }
+    }
+
+    /// Writes the snippet beside the first of `variants`, given as density factors and
+    /// output paths, and returns its path. `size` is the 1x size in CSS pixels.
+    pub fn write(
+        self,
+        input_path: &str,
+        variants: &[(f32, String)],
+        size: (u32, u32),
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let (_, first_path) = variants.first().ok_or("No images to reference")?;
+        let snippet_path = Path::new(first_path)
+            .with_extension(self.extension())
+            .display()
+            .to_string();
+        let dir = Path::new(&snippet_path).parent().unwrap_or(Path::new(""));
+        let urls: Vec<(f32, String)> = variants
+            .iter()
+            .map(|(factor, path)| (*factor, relative_url(dir, path)))
+            .collect();
+        let name = input_name(input_path);
+        let snippet = match self {
+            Snippet::Html => img_tag(&name, &urls, size),
+            Snippet::Css => background_rule(&name, &urls, size),
+        };
+        std::fs::write(&snippet_path, snippet)?;
+        Ok(snippet_path)
+    }
+}
+
+fn img_tag(name: &str, urls: &[(f32, String)], (width, height): (u32, u32)) -> String {
+    let srcset: Vec<String> = urls
+        .iter()
+        .map(|(factor, url)| format!("{} {}x", url, factor))
+        .collect();
+    format!(
+        "<img src=\"{}\" srcset=\"{}\" width=\"{}\" height=\"{}\" alt=\"{}\">\n",
+        escape(&urls[0].1),
+        escape(&srcset.join(", ")),
+        width,
+        height,
+        escape(name)
+    )
+}
+
+fn background_rule(name: &str, urls: &[(f32, String)], (width, height): (u32, u32)) -> String {
+    let quote = |url: &str| format!("url(\"{}\")", url.replace('"', "\\\""));
+    let image_set: Vec<String> = urls
+        .iter()
+        .map(|(factor, url)| format!("{} {}x", quote(url), factor))
+        .collect();
+    let mut class = css_class(name);
+    // Class names can't start with a digit.
+    if !class.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        class.insert_str(0, "image-");
+    }
+    format!(
+        ".{} {{\n  width: {}px;\n  height: {}px;\n  background-image: {};\n  \
+         background-image: image-set({});\n  background-size: {}px {}px;\n  \
+         background-repeat: no-repeat;\n}}\n",
+        class,
+        width,
+        height,
+        quote(&urls[0].1),
+        image_set.join(", "),
+        width,
+        height
+    )
+}
+
+/// `path` relative to `dir` when it's inside it, with forward slashes, and with the
+/// characters that split `srcset` and `image-set` entries percent-encoded.
+fn relative_url(dir: &Path, path: &str) -> String {
+    let relative = Path::new(path).strip_prefix(dir).unwrap_or(Path::new(path));
+    relative
+        .to_string_lossy()
+        .replace('\\', "/")
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace(',', "%2C")
+}
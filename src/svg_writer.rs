@@ -0,0 +1,833 @@
+use crate::convert::ConversionOptions;
+use base64::Engine;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use usvg::{
+    Align, AspectRatio, BaseGradient, BlendMode, ClipPath, Color, FillRule, Image, ImageKind,
+    LineCap, LineJoin, Mask, Node, NodeKind, Paint, PaintOrder, Path, PathData, PathSegment,
+    Pattern, ShapeRendering, SpreadMethod, Transform, Tree, Units, ViewBox, Visibility,
+};
+
+/// Writes the parsed tree back out as a minified SVG.
+///
+/// The tree is what usvg made of the document: styles are resolved into attributes, shapes
+/// and text are paths, `use` elements are expanded and metadata, comments and unused
+/// definitions are gone. Transforms are baked into plain filled paths and identical
+/// gradients, patterns, clip paths and masks are written once. Filters are not written.
+pub fn save(
+    rtree: &Tree,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if same_file(input_path, output_path) {
+        return Err(format!("Writing {} would overwrite the input", output_path).into());
+    }
+    std::fs::write(output_path, document(rtree, options))?;
+    Ok(())
+}
+
+/// The cleaned SVG as [`save`] writes it, sized like a rendered image would be.
+pub fn document(rtree: &Tree, options: &ConversionOptions) -> String {
+    let (scale_x, scale_y) =
+        options.scale_factors(rtree.size.width() as f32, rtree.size.height() as f32);
+    let size = (
+        rtree.size.width() * scale_x as f64,
+        rtree.size.height() * scale_y as f64,
+    );
+    to_svg(rtree, size)
+}
+
+/// Whether both paths name one existing file.
+pub fn same_file(input_path: &str, output_path: &str) -> bool {
+    match (
+        std::fs::canonicalize(input_path),
+        std::fs::canonicalize(output_path),
+    ) {
+        (Ok(input), Ok(output)) => input == output,
+        _ => false,
+    }
+}
+
+/// The tree as a one-line SVG document, `width` by `height` user units.
+pub fn to_svg(rtree: &Tree, (width, height): (f64, f64)) -> String {
+    let mut writer = Writer::new(&rtree.root);
+    let mut body = String::new();
+    for child in rtree.root.children() {
+        writer.write(&mut body, &child, false);
+    }
+
+    let view_box = rtree.view_box.rect;
+    let mut svg = String::from("<svg xmlns=\"http://www.w3.org/2000/svg\"");
+    if body.contains("xlink:") || writer.defs.contains("xlink:") {
+        svg.push_str(" xmlns:xlink=\"http://www.w3.org/1999/xlink\"");
+    }
+    let _ = write!(
+        svg,
+        " width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\"{}>",
+        number(width),
+        number(height),
+        number(view_box.x()),
+        number(view_box.y()),
+        number(view_box.width()),
+        number(view_box.height()),
+        aspect_attribute(rtree.view_box.aspect)
+    );
+    if !writer.defs.is_empty() {
+        let _ = write!(svg, "<defs>{}</defs>", writer.defs);
+    }
+    svg.push_str(&body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Serializes nodes, collecting the paint servers, clip paths and masks they use as defs.
+pub struct Writer {
+    defs: String,
+    /// Ids of already written defs, by their markup without the id.
+    written: HashMap<String, String>,
+    /// Ids already on elements, which generated ids must avoid.
+    taken: HashSet<String>,
+    next_id: usize,
+    /// Whether the output goes into the document's own markup. Paths then spell out every
+    /// inheritable style, since the surrounding elements may set others, and filters refer
+    /// to the document's `<filter>` elements.
+    fragment: bool,
+}
+
+/// Where a `<clipPath>`'s content is written. Only shapes are allowed there, so groups are
+/// flattened into their paths, passing their transform and clip path down.
+#[derive(Clone, Default)]
+struct ClipContext {
+    transform: Transform,
+    clip_path: Option<String>,
+}
+
+impl Writer {
+    fn new(root: &Node) -> Self {
+        let mut taken = HashSet::new();
+        collect_ids(root, &mut taken);
+        Self {
+            defs: String::new(),
+            written: HashMap::new(),
+            taken,
+            next_id: 0,
+            fragment: false,
+        }
+    }
+
+    /// A writer for nodes placed back into the document they were parsed from. `taken`
+    /// holds the ids in that document.
+    pub fn fragment(root: &Node, mut taken: HashSet<String>) -> Self {
+        collect_ids(root, &mut taken);
+        Self {
+            taken,
+            fragment: true,
+            ..Self::new(root)
+        }
+    }
+
+    /// The definitions the written nodes refer to, without a `<defs>` around them.
+    pub fn defs(&self) -> &str {
+        &self.defs
+    }
+
+    /// Writes `node` as it is placed in its parent. Inside a `<clipPath>`, set `in_clip`.
+    pub fn write(&mut self, out: &mut String, node: &Node, in_clip: bool) {
+        let clip = in_clip.then(ClipContext::default);
+        self.write_node(out, node, clip.as_ref());
+    }
+
+    fn write_node(&mut self, out: &mut String, node: &Node, clip: Option<&ClipContext>) {
+        match *node.borrow() {
+            NodeKind::Group(ref group) => {
+                if let Some(context) = clip {
+                    let mut transform = context.transform;
+                    transform.append(&group.transform);
+                    let clip_path = match &group.clip_path {
+                        Some(clip_path) => Some(self.clip_path(clip_path)),
+                        None => context.clip_path.clone(),
+                    };
+                    let inner = ClipContext {
+                        transform,
+                        clip_path,
+                    };
+                    for child in node.children() {
+                        self.write_node(out, &child, Some(&inner));
+                    }
+                    return;
+                }
+
+                let mut attributes = String::new();
+                if !group.id.is_empty() {
+                    let _ = write!(attributes, " id=\"{}\"", escape(&group.id));
+                }
+                attributes.push_str(&transform_attribute("transform", group.transform));
+                if group.opacity.get() < 1.0 {
+                    let _ = write!(attributes, " opacity=\"{}\"", number(group.opacity.get()));
+                }
+                if let Some(clip) = &group.clip_path {
+                    let id = self.clip_path(clip);
+                    let _ = write!(attributes, " clip-path=\"url(#{})\"", id);
+                }
+                if let Some(mask) = &group.mask {
+                    let id = self.mask(mask);
+                    let _ = write!(attributes, " mask=\"url(#{})\"", id);
+                }
+                if self.fragment && !group.filters.is_empty() {
+                    let filters: Vec<String> = group
+                        .filters
+                        .iter()
+                        .map(|filter| format!("url(#{})", escape(&filter.id)))
+                        .collect();
+                    let _ = write!(attributes, " filter=\"{}\"", filters.join(" "));
+                }
+                let mut style = Vec::new();
+                if group.blend_mode != BlendMode::Normal {
+                    style.push(format!("mix-blend-mode:{}", blend_mode(group.blend_mode)));
+                }
+                if group.isolate {
+                    style.push(String::from("isolation:isolate"));
+                }
+                if !style.is_empty() {
+                    let _ = write!(attributes, " style=\"{}\"", style.join(";"));
+                }
+
+                let mut children = String::new();
+                for child in node.children() {
+                    self.write_node(&mut children, &child, None);
+                }
+                if attributes.is_empty() {
+                    // A group without attributes changes nothing, so only its children stay.
+                    out.push_str(&children);
+                } else if !children.is_empty() {
+                    let _ = write!(out, "<g{}>{}</g>", attributes, children);
+                }
+            }
+            NodeKind::Path(ref path) => self.write_path(out, path, clip),
+            NodeKind::Image(ref image) => {
+                if clip.is_none() {
+                    self.write_image(out, image);
+                }
+            }
+            // Text is converted to paths while parsing; any left had no usable font.
+            NodeKind::Text(_) => {}
+        }
+    }
+
+    fn write_path(&mut self, out: &mut String, path: &Path, clip: Option<&ClipContext>) {
+        if path.visibility != Visibility::Visible {
+            return;
+        }
+        let transform = match clip {
+            Some(context) => {
+                let mut transform = context.transform;
+                transform.append(&path.transform);
+                transform
+            }
+            None => path.transform,
+        };
+        // Solid fills look the same with the transform applied to the points, but strokes
+        // and paint servers would be distorted.
+        let bake = path.stroke.is_none()
+            && path
+                .fill
+                .as_ref()
+                .is_none_or(|fill| matches!(fill.paint, Paint::Color(_)));
+        let data = if bake {
+            path_data(&path.data, transform)
+        } else {
+            path_data(&path.data, Transform::default())
+        };
+        if data.is_empty() {
+            return;
+        }
+
+        let explicit = self.fragment;
+        out.push_str("<path");
+        if !path.id.is_empty() {
+            let _ = write!(out, " id=\"{}\"", escape(&path.id));
+        }
+        let _ = write!(out, " d=\"{}\"", data);
+        if !bake {
+            out.push_str(&transform_attribute("transform", transform));
+        }
+        if let Some(id) = clip.and_then(|context| context.clip_path.as_ref()) {
+            let _ = write!(out, " clip-path=\"url(#{})\"", id);
+        }
+
+        match &path.fill {
+            Some(fill) => {
+                if explicit || fill.paint != Paint::Color(Color::black()) {
+                    let _ = write!(out, " fill=\"{}\"", self.paint(&fill.paint));
+                }
+                if explicit || fill.opacity.get() < 1.0 {
+                    let _ = write!(out, " fill-opacity=\"{}\"", number(fill.opacity.get()));
+                }
+                if explicit || fill.rule == FillRule::EvenOdd {
+                    let rule = match fill.rule {
+                        FillRule::NonZero => "nonzero",
+                        FillRule::EvenOdd => "evenodd",
+                    };
+                    let name = if clip.is_some() {
+                        "clip-rule"
+                    } else {
+                        "fill-rule"
+                    };
+                    let _ = write!(out, " {}=\"{}\"", name, rule);
+                }
+            }
+            None => out.push_str(" fill=\"none\""),
+        }
+
+        match &path.stroke {
+            Some(stroke) => {
+                let _ = write!(out, " stroke=\"{}\"", self.paint(&stroke.paint));
+                if explicit || stroke.opacity.get() < 1.0 {
+                    let _ = write!(out, " stroke-opacity=\"{}\"", number(stroke.opacity.get()));
+                }
+                if explicit || stroke.width.get() != 1.0 {
+                    let _ = write!(out, " stroke-width=\"{}\"", number(stroke.width.get()));
+                }
+                if explicit || stroke.linecap != LineCap::Butt {
+                    let cap = match stroke.linecap {
+                        LineCap::Butt => "butt",
+                        LineCap::Round => "round",
+                        LineCap::Square => "square",
+                    };
+                    let _ = write!(out, " stroke-linecap=\"{}\"", cap);
+                }
+                if explicit || stroke.linejoin != LineJoin::Miter {
+                    let join = match stroke.linejoin {
+                        LineJoin::Miter => "miter",
+                        LineJoin::Round => "round",
+                        LineJoin::Bevel => "bevel",
+                    };
+                    let _ = write!(out, " stroke-linejoin=\"{}\"", join);
+                }
+                if explicit || stroke.miterlimit.get() != 4.0 {
+                    let _ = write!(
+                        out,
+                        " stroke-miterlimit=\"{}\"",
+                        number(stroke.miterlimit.get())
+                    );
+                }
+                match &stroke.dasharray {
+                    Some(dasharray) => {
+                        let dashes: Vec<String> =
+                            dasharray.iter().map(|&dash| number(dash)).collect();
+                        let _ = write!(out, " stroke-dasharray=\"{}\"", dashes.join(" "));
+                        if explicit || stroke.dashoffset != 0.0 {
+                            let _ = write!(
+                                out,
+                                " stroke-dashoffset=\"{}\"",
+                                number(stroke.dashoffset as f64)
+                            );
+                        }
+                    }
+                    None if explicit => out.push_str(" stroke-dasharray=\"none\""),
+                    None => {}
+                }
+            }
+            None if explicit => out.push_str(" stroke=\"none\""),
+            None => {}
+        }
+
+        match path.paint_order {
+            PaintOrder::StrokeAndFill => out.push_str(" paint-order=\"stroke\""),
+            PaintOrder::FillAndStroke if explicit => out.push_str(" paint-order=\"normal\""),
+            PaintOrder::FillAndStroke => {}
+        }
+        match path.rendering_mode {
+            ShapeRendering::GeometricPrecision if explicit => {
+                out.push_str(" shape-rendering=\"geometricPrecision\"")
+            }
+            ShapeRendering::GeometricPrecision => {}
+            ShapeRendering::CrispEdges => out.push_str(" shape-rendering=\"crispEdges\""),
+            ShapeRendering::OptimizeSpeed => out.push_str(" shape-rendering=\"optimizeSpeed\""),
+        }
+        if explicit {
+            out.push_str(" visibility=\"visible\"");
+        }
+        out.push_str("/>");
+    }
+
+    fn write_image(&mut self, out: &mut String, image: &Image) {
+        if image.visibility != Visibility::Visible {
+            return;
+        }
+        let (mime, data) = match &image.kind {
+            ImageKind::JPEG(data) => ("image/jpeg", data.to_vec()),
+            ImageKind::PNG(data) => ("image/png", data.to_vec()),
+            ImageKind::GIF(data) => ("image/gif", data.to_vec()),
+            ImageKind::SVG(tree) => (
+                "image/svg+xml",
+                to_svg(tree, (tree.size.width(), tree.size.height())).into_bytes(),
+            ),
+        };
+        let rect = image.view_box.rect;
+        out.push_str("<image");
+        if !image.id.is_empty() {
+            let _ = write!(out, " id=\"{}\"", escape(&image.id));
+        }
+        let _ = write!(
+            out,
+            " x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}{}",
+            number(rect.x()),
+            number(rect.y()),
+            number(rect.width()),
+            number(rect.height()),
+            aspect_attribute(image.view_box.aspect),
+            transform_attribute("transform", image.transform)
+        );
+        if image.rendering_mode == usvg::ImageRendering::OptimizeSpeed {
+            out.push_str(" image-rendering=\"optimizeSpeed\"");
+        }
+        let _ = write!(
+            out,
+            " xlink:href=\"data:{};base64,{}\"/>",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        );
+    }
+
+    /// A fill or stroke value, defining the paint server if it is one.
+    fn paint(&mut self, paint: &Paint) -> String {
+        let id = match paint {
+            Paint::Color(color) => return hex_color(*color),
+            Paint::LinearGradient(linear) => {
+                let mut attributes = String::new();
+                // The defaults run left to right across the bounding box.
+                if linear.base.units == Units::UserSpaceOnUse
+                    || (linear.x1, linear.y1, linear.x2, linear.y2) != (0.0, 0.0, 1.0, 0.0)
+                {
+                    let _ = write!(
+                        attributes,
+                        " x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                        number(linear.x1),
+                        number(linear.y1),
+                        number(linear.x2),
+                        number(linear.y2)
+                    );
+                }
+                attributes.push_str(&gradient_attributes(&linear.base));
+                self.define("linearGradient", &attributes, &gradient_stops(&linear.base))
+            }
+            Paint::RadialGradient(radial) => {
+                let mut attributes = String::new();
+                // The defaults fill the bounding box from its center.
+                if radial.base.units == Units::UserSpaceOnUse
+                    || (radial.cx, radial.cy, radial.r.get()) != (0.5, 0.5, 0.5)
+                {
+                    let _ = write!(
+                        attributes,
+                        " cx=\"{}\" cy=\"{}\" r=\"{}\"",
+                        number(radial.cx),
+                        number(radial.cy),
+                        number(radial.r.get())
+                    );
+                }
+                if radial.fx != radial.cx || radial.fy != radial.cy {
+                    let _ = write!(
+                        attributes,
+                        " fx=\"{}\" fy=\"{}\"",
+                        number(radial.fx),
+                        number(radial.fy)
+                    );
+                }
+                attributes.push_str(&gradient_attributes(&radial.base));
+                self.define("radialGradient", &attributes, &gradient_stops(&radial.base))
+            }
+            Paint::Pattern(pattern) => self.pattern(pattern),
+        };
+        format!("url(#{})", id)
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> String {
+        let mut attributes = rect_attributes(pattern.rect);
+        if pattern.units == Units::UserSpaceOnUse {
+            attributes.push_str(" patternUnits=\"userSpaceOnUse\"");
+        }
+        if pattern.content_units == Units::ObjectBoundingBox {
+            attributes.push_str(" patternContentUnits=\"objectBoundingBox\"");
+        }
+        attributes.push_str(&transform_attribute("patternTransform", pattern.transform));
+        if let Some(view_box) = pattern.view_box {
+            attributes.push_str(&view_box_attributes(view_box));
+        }
+        let mut children = String::new();
+        for child in pattern.root.children() {
+            self.write_node(&mut children, &child, None);
+        }
+        self.define("pattern", &attributes, &children)
+    }
+
+    fn clip_path(&mut self, clip: &ClipPath) -> String {
+        let mut attributes = String::new();
+        if clip.units == Units::ObjectBoundingBox {
+            attributes.push_str(" clipPathUnits=\"objectBoundingBox\"");
+        }
+        attributes.push_str(&transform_attribute("transform", clip.transform));
+        if let Some(nested) = &clip.clip_path {
+            let id = self.clip_path(nested);
+            let _ = write!(attributes, " clip-path=\"url(#{})\"", id);
+        }
+        let mut children = String::new();
+        for child in clip.root.children() {
+            self.write_node(&mut children, &child, Some(&ClipContext::default()));
+        }
+        self.define("clipPath", &attributes, &children)
+    }
+
+    fn mask(&mut self, mask: &Mask) -> String {
+        let mut attributes = rect_attributes(mask.rect);
+        if mask.units == Units::UserSpaceOnUse {
+            attributes.push_str(" maskUnits=\"userSpaceOnUse\"");
+        }
+        if mask.content_units == Units::ObjectBoundingBox {
+            attributes.push_str(" maskContentUnits=\"objectBoundingBox\"");
+        }
+        if let Some(nested) = &mask.mask {
+            let id = self.mask(nested);
+            let _ = write!(attributes, " mask=\"url(#{})\"", id);
+        }
+        let mut children = String::new();
+        for child in mask.root.children() {
+            self.write_node(&mut children, &child, None);
+        }
+        self.define("mask", &attributes, &children)
+    }
+
+    /// Adds a `<tag>` to the defs unless an identical one is there, and returns its id.
+    fn define(&mut self, tag: &str, attributes: &str, children: &str) -> String {
+        let markup = if children.is_empty() {
+            format!("<{}{}/>", tag, attributes)
+        } else {
+            format!("<{}{}>{}</{}>", tag, attributes, children, tag)
+        };
+        if let Some(id) = self.written.get(&markup) {
+            return id.clone();
+        }
+        let id = self.generate_id();
+        // The id goes right after the tag name.
+        let _ = write!(
+            self.defs,
+            "<{} id=\"{}\"{}",
+            tag,
+            id,
+            &markup[tag.len() + 1..]
+        );
+        self.written.insert(markup, id.clone());
+        id
+    }
+
+    /// The next of `a`, `b`, … `z`, `aa`, `ab`, … not already used by an element.
+    fn generate_id(&mut self) -> String {
+        loop {
+            let mut n = self.next_id;
+            self.next_id += 1;
+            let mut id = Vec::new();
+            loop {
+                id.push(b'a' + (n % 26) as u8);
+                n /= 26;
+                if n == 0 {
+                    break;
+                }
+                n -= 1;
+            }
+            id.reverse();
+            let id = String::from_utf8(id).unwrap();
+            if !self.taken.contains(&id) {
+                return id;
+            }
+        }
+    }
+}
+
+/// Adds the ids of every node under `root`, including inside defs, to `ids`.
+fn collect_ids(root: &Node, ids: &mut HashSet<String>) {
+    for node in root.descendants() {
+        let mut roots = Vec::new();
+        match *node.borrow() {
+            NodeKind::Group(ref group) => {
+                ids.insert(group.id.clone());
+                let mut clip = group.clip_path.clone();
+                while let Some(clip_path) = clip {
+                    roots.push(clip_path.root.clone());
+                    clip = clip_path.clip_path.clone();
+                }
+                let mut mask = group.mask.clone();
+                while let Some(nested) = mask {
+                    roots.push(nested.root.clone());
+                    mask = nested.mask.clone();
+                }
+            }
+            NodeKind::Path(ref path) => {
+                ids.insert(path.id.clone());
+                for paint in path
+                    .fill
+                    .iter()
+                    .map(|fill| &fill.paint)
+                    .chain(path.stroke.iter().map(|stroke| &stroke.paint))
+                {
+                    if let Paint::Pattern(pattern) = paint {
+                        if ids.insert(format!("pattern {}", pattern.id)) {
+                            roots.push(pattern.root.clone());
+                        }
+                    }
+                }
+            }
+            NodeKind::Image(ref image) => {
+                ids.insert(image.id.clone());
+            }
+            NodeKind::Text(ref text) => {
+                ids.insert(text.id.clone());
+            }
+        }
+        for root in roots {
+            collect_ids(&root, ids);
+        }
+    }
+}
+
+fn gradient_attributes(base: &BaseGradient) -> String {
+    let mut attributes = String::new();
+    if base.units == Units::UserSpaceOnUse {
+        attributes.push_str(" gradientUnits=\"userSpaceOnUse\"");
+    }
+    attributes.push_str(&transform_attribute("gradientTransform", base.transform));
+    match base.spread_method {
+        SpreadMethod::Pad => {}
+        SpreadMethod::Reflect => attributes.push_str(" spreadMethod=\"reflect\""),
+        SpreadMethod::Repeat => attributes.push_str(" spreadMethod=\"repeat\""),
+    }
+    attributes
+}
+
+fn gradient_stops(base: &BaseGradient) -> String {
+    let mut stops = String::new();
+    for stop in &base.stops {
+        let _ = write!(stops, "<stop offset=\"{}\"", number(stop.offset.get()));
+        if stop.color != Color::black() {
+            let _ = write!(stops, " stop-color=\"{}\"", hex_color(stop.color));
+        }
+        if stop.opacity.get() < 1.0 {
+            let _ = write!(stops, " stop-opacity=\"{}\"", number(stop.opacity.get()));
+        }
+        stops.push_str("/>");
+    }
+    stops
+}
+
+fn rect_attributes(rect: usvg::Rect) -> String {
+    format!(
+        " x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+        number(rect.x()),
+        number(rect.y()),
+        number(rect.width()),
+        number(rect.height())
+    )
+}
+
+fn view_box_attributes(view_box: ViewBox) -> String {
+    let rect = view_box.rect;
+    format!(
+        " viewBox=\"{} {} {} {}\"{}",
+        number(rect.x()),
+        number(rect.y()),
+        number(rect.width()),
+        number(rect.height()),
+        aspect_attribute(view_box.aspect)
+    )
+}
+
+/// A ` preserveAspectRatio` attribute, or nothing for the default `xMidYMid meet`.
+fn aspect_attribute(aspect: AspectRatio) -> String {
+    if aspect == AspectRatio::default() {
+        return String::new();
+    }
+    let align = match aspect.align {
+        Align::None => "none",
+        Align::XMinYMin => "xMinYMin",
+        Align::XMidYMin => "xMidYMin",
+        Align::XMaxYMin => "xMaxYMin",
+        Align::XMinYMid => "xMinYMid",
+        Align::XMidYMid => "xMidYMid",
+        Align::XMaxYMid => "xMaxYMid",
+        Align::XMinYMax => "xMinYMax",
+        Align::XMidYMax => "xMidYMax",
+        Align::XMaxYMax => "xMaxYMax",
+    };
+    format!(
+        " preserveAspectRatio=\"{}{}{}\"",
+        if aspect.defer { "defer " } else { "" },
+        align,
+        if aspect.slice { " slice" } else { "" }
+    )
+}
+
+/// A transform attribute named `name` in its shortest form, or nothing for the identity.
+fn transform_attribute(name: &str, ts: Transform) -> String {
+    if ts.is_default() {
+        return String::new();
+    }
+    let value = if ts.a == 1.0 && ts.b == 0.0 && ts.c == 0.0 && ts.d == 1.0 {
+        format!("translate({} {})", number(ts.e), number(ts.f))
+    } else if ts.b == 0.0 && ts.c == 0.0 && ts.e == 0.0 && ts.f == 0.0 {
+        format!("scale({} {})", number(ts.a), number(ts.d))
+    } else {
+        format!(
+            "matrix({} {} {} {} {} {})",
+            number(ts.a),
+            number(ts.b),
+            number(ts.c),
+            number(ts.d),
+            number(ts.e),
+            number(ts.f)
+        )
+    };
+    format!(" {}=\"{}\"", name, value)
+}
+
+fn blend_mode(mode: BlendMode) -> &'static str {
+    match mode {
+        BlendMode::Normal => "normal",
+        BlendMode::Multiply => "multiply",
+        BlendMode::Screen => "screen",
+        BlendMode::Overlay => "overlay",
+        BlendMode::Darken => "darken",
+        BlendMode::Lighten => "lighten",
+        BlendMode::ColorDodge => "color-dodge",
+        BlendMode::ColorBurn => "color-burn",
+        BlendMode::HardLight => "hard-light",
+        BlendMode::SoftLight => "soft-light",
+        BlendMode::Difference => "difference",
+        BlendMode::Exclusion => "exclusion",
+        BlendMode::Hue => "hue",
+        BlendMode::Saturation => "saturation",
+        BlendMode::Color => "color",
+        BlendMode::Luminosity => "luminosity",
+    }
+}
+
+/// `#rrggbb`, or `#rgb` when each channel's digits repeat.
+fn hex_color(color: Color) -> String {
+    let channels = [color.red, color.green, color.blue];
+    if channels.iter().all(|channel| channel >> 4 == channel & 0xf) {
+        format!(
+            "#{:x}{:x}{:x}",
+            color.red & 0xf,
+            color.green & 0xf,
+            color.blue & 0xf
+        )
+    } else {
+        format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+    }
+}
+
+/// Path data after applying `transform`, in absolute commands with repeated commands,
+/// separators and leading zeros left out.
+pub fn path_data(data: &PathData, transform: Transform) -> String {
+    let mut out = String::new();
+    let mut previous = ' ';
+    // The current point and subpath start, as written, to spot horizontal and vertical lines.
+    let mut current = (String::new(), String::new());
+    let mut start = current.clone();
+    let mut has_dot = false;
+    let point = |x: f64, y: f64| {
+        let (x, y) = transform.apply(x, y);
+        (short_number(x), short_number(y))
+    };
+
+    for segment in data.segments() {
+        let (command, numbers) = match segment {
+            PathSegment::MoveTo { x, y } => {
+                current = point(x, y);
+                start = current.clone();
+                ('M', vec![current.0.clone(), current.1.clone()])
+            }
+            PathSegment::LineTo { x, y } => {
+                let (x, y) = point(x, y);
+                let line = if y == current.1 {
+                    ('H', vec![x.clone()])
+                } else if x == current.0 {
+                    ('V', vec![y.clone()])
+                } else {
+                    ('L', vec![x.clone(), y.clone()])
+                };
+                current = (x, y);
+                line
+            }
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (x1, y1) = point(x1, y1);
+                let (x2, y2) = point(x2, y2);
+                current = point(x, y);
+                (
+                    'C',
+                    vec![x1, y1, x2, y2, current.0.clone(), current.1.clone()],
+                )
+            }
+            PathSegment::ClosePath => {
+                current = start.clone();
+                ('Z', Vec::new())
+            }
+        };
+
+        // Coordinates after a moveto are implicit linetos, and repeats need no letter.
+        let implicit = command != 'M'
+            && command != 'Z'
+            && (command == previous || (command == 'L' && previous == 'M'));
+        if !implicit {
+            out.push(command);
+        }
+        for (i, value) in numbers.iter().enumerate() {
+            // A sign or a second decimal point already ends the previous number.
+            let separate = (i > 0 || implicit)
+                && !value.starts_with('-')
+                && !(value.starts_with('.') && has_dot);
+            if separate {
+                out.push(' ');
+            }
+            out.push_str(value);
+            has_dot = value.contains('.');
+        }
+        previous = command;
+    }
+    out
+}
+
+/// Like [`number`], without the leading zero of fractions.
+fn short_number(value: f64) -> String {
+    let formatted = number(value);
+    if let Some(fraction) = formatted.strip_prefix("0.") {
+        format!(".{}", fraction)
+    } else if let Some(fraction) = formatted.strip_prefix("-0.") {
+        format!("-.{}", fraction)
+    } else {
+        formatted
+    }
+}
+
+/// Formats a coordinate with at most three decimals and no trailing zeros.
+pub fn number(value: f64) -> String {
+    let formatted = format!("{:.3}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => String::from("0"),
+        _ => trimmed.to_string(),
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
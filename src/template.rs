@@ -0,0 +1,84 @@
+use crate::convert::{is_url, ConversionOptions};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tokens recognized in output paths, shown as a hint in the UI and CLI help.
+pub const TOKENS: &str = "{name} {id} {scale} {width} {height} {date} {ext}";
+
+pub fn has_tokens(template: &str) -> bool {
+    [
+        "{name}", "{id}", "{scale}", "{width}", "{height}", "{date}", "{ext}",
+    ]
+    .iter()
+    .any(|token| template.contains(token))
+}
+
+/// Expands output path tokens for one rendered file.
+pub fn expand(
+    template: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+    (width, height): (u32, u32),
+    scale: f32,
+) -> String {
+    template
+        .replace("{name}", &input_name(input_path))
+        .replace(
+            "{id}",
+            options.canvas.element.as_deref().unwrap_or_default(),
+        )
+        .replace("{scale}", &format!("{}", (scale * 100.0).round() / 100.0))
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+        .replace("{date}", &timestamp(SystemTime::now())[..10])
+        .replace("{ext}", options.format.extension())
+}
+
+/// The input's file name without its extension, which `{name}` expands to.
+pub fn input_name(input_path: &str) -> String {
+    // A URL's query string and fragment aren't part of its file name.
+    let input_path = if is_url(input_path) {
+        input_path.split(['?', '#']).next().unwrap_or(input_path)
+    } else {
+        input_path
+    };
+    Path::new(input_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("output"))
+}
+
+/// A time in UTC as `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let days = (seconds / 86_400) as i64;
+    let second_of_day = seconds % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    )
+}
@@ -0,0 +1,431 @@
+use crate::svg_writer::number;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Extensions of the raster images that can be traced.
+pub const RASTER_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "bmp", "tif", "tiff"];
+
+/// Outline points closer than this to the straight line between their neighbours are
+/// dropped, so pixel staircases become sloped lines.
+const TOLERANCE: f64 = 1.0;
+
+/// How a raster image is split into shapes.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum TraceMode {
+    /// One black shape covering the pixels darker than the threshold.
+    Bilevel,
+    /// A shape for each color of a reduced palette, stacked from the largest down.
+    Color,
+}
+
+impl TraceMode {
+    pub const ALL: [TraceMode; 2] = [TraceMode::Bilevel, TraceMode::Color];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceMode::Bilevel => "Black and white",
+            TraceMode::Color => "Color",
+        }
+    }
+}
+
+/// Settings for tracing a raster image into an SVG.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TraceOptions {
+    pub mode: TraceMode,
+    /// Brightness, 0-255, below which a pixel is traced in bilevel mode. Transparent
+    /// pixels count as white.
+    pub threshold: u8,
+    /// Palette size in color mode.
+    pub colors: u16,
+    /// Shapes and holes of at most this many pixels are dropped as noise.
+    pub speckle: u32,
+    /// How readily corners are rounded into curves, from 0 (never) to 1.34 (always).
+    pub smoothness: f32,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self {
+            mode: TraceMode::Bilevel,
+            threshold: 128,
+            colors: 8,
+            speckle: 2,
+            smoothness: 1.0,
+        }
+    }
+}
+
+pub fn is_raster_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        RASTER_EXTENSIONS
+            .iter()
+            .any(|raster| ext.eq_ignore_ascii_case(raster))
+    })
+}
+
+/// Traces the image at `input_path` into `output_path`.
+pub fn save(
+    input_path: &str,
+    output_path: &str,
+    options: &TraceOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(input_path)?.into_rgba8();
+    std::fs::write(output_path, trace(&image, options))?;
+    Ok(())
+}
+
+/// SVG markup with the image's shapes as filled paths, the size of the image in pixels.
+pub fn trace(image: &RgbaImage, options: &TraceOptions) -> String {
+    let (width, height) = image.dimensions();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\">\n",
+        width, height
+    );
+    let mut add_layer = |color: [u8; 3], mask: &[bool]| {
+        let d = path_data(mask, width as usize, height as usize, options);
+        if !d.is_empty() {
+            let _ = writeln!(
+                svg,
+                "<path fill=\"#{:02x}{:02x}{:02x}\" fill-rule=\"evenodd\" d=\"{}\"/>",
+                color[0], color[1], color[2], d
+            );
+        }
+    };
+
+    match options.mode {
+        TraceMode::Bilevel => {
+            let threshold = options.threshold as f32;
+            let mask: Vec<bool> = image
+                .pixels()
+                .map(|pixel| {
+                    let [red, green, blue, alpha] = pixel.0.map(f32::from);
+                    let luma = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+                    let over_white = luma * alpha / 255.0 + (255.0 - alpha);
+                    over_white < threshold
+                })
+                .collect();
+            add_layer([0, 0, 0], &mask);
+        }
+        TraceMode::Color => {
+            let rgba = image.as_raw();
+            let opaque: Vec<bool> = rgba.chunks_exact(4).map(|pixel| pixel[3] >= 128).collect();
+            let (palette, indices) = palette(image, options.colors.clamp(2, 256) as usize);
+            let mut counts = vec![0usize; palette.len()];
+            for (&index, _) in indices.iter().zip(&opaque).filter(|(_, &opaque)| opaque) {
+                counts[index as usize] += 1;
+            }
+            let mut order: Vec<usize> = (0..palette.len()).filter(|&i| counts[i] > 0).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(counts[i]));
+            let mut rank = vec![0; palette.len()];
+            for (position, &index) in order.iter().enumerate() {
+                rank[index] = position;
+            }
+            // Each layer also covers the smaller ones drawn over it, so anti-aliased edges
+            // between colors can't leave gaps.
+            for (position, &index) in order.iter().enumerate() {
+                let mask: Vec<bool> = indices
+                    .iter()
+                    .zip(&opaque)
+                    .map(|(&i, &opaque)| opaque && rank[i as usize] >= position)
+                    .collect();
+                add_layer(palette[index], &mask);
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A palette of at most `colors` entries and the nearest entry to each pixel. When enough
+/// opaque pixels match all their neighbours, only those choose the palette, so the blends
+/// along anti-aliased edges don't take entries from the actual colors.
+fn palette(image: &RgbaImage, colors: usize) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let (width, height) = image.dimensions();
+    let (mut flat, mut opaque) = (HashMap::new(), HashMap::new());
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel[3] < 128 {
+            continue;
+        }
+        let color = [pixel[0], pixel[1], pixel[2]];
+        *opaque.entry(color).or_insert(0) += 1;
+        let uniform = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (x as i64 + dx, y as i64 + dy)))
+            .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < width as i64 && ny < height as i64)
+            .all(|(nx, ny)| image.get_pixel(nx as u32, ny as u32) == pixel);
+        if uniform {
+            *flat.entry(color).or_insert(0) += 1;
+        }
+    }
+    let count = |histogram: &HashMap<[u8; 3], u64>| histogram.values().sum::<u64>();
+    let sample = if count(&flat) >= count(&opaque) / 10 {
+        flat
+    } else {
+        opaque
+    };
+    let palette = median_cut(sample.into_iter().collect(), colors);
+
+    let distance = |a: [u8; 3], b: [u8; 3]| -> u32 {
+        a.iter()
+            .zip(b)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+    let mut nearest = HashMap::new();
+    let indices = image
+        .pixels()
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2]];
+            *nearest.entry(color).or_insert_with(|| {
+                (0..palette.len())
+                    .min_by_key(|&i| distance(palette[i], color))
+                    .unwrap_or(0) as u8
+            })
+        })
+        .collect();
+    (palette, indices)
+}
+
+/// Reduces the colors, given with their pixel counts, to at most `colors` by repeatedly
+/// splitting the group with the widest channel at its median pixel, and averages each
+/// group. Images with few enough colors keep them exactly.
+fn median_cut(histogram: Vec<([u8; 3], u64)>, colors: usize) -> Vec<[u8; 3]> {
+    let range = |group: &[([u8; 3], u64)], channel: usize| {
+        let values = group.iter().map(|(color, _)| color[channel]);
+        values.clone().max().unwrap_or(0) - values.min().unwrap_or(0)
+    };
+    let widest = |group: &[([u8; 3], u64)]| {
+        (0..3)
+            .map(|channel| (range(group, channel), channel))
+            .max()
+            .unwrap()
+    };
+
+    let mut groups = vec![histogram];
+    groups.retain(|group| !group.is_empty());
+    while groups.len() < colors {
+        let Some((index, (_, channel))) = groups
+            .iter()
+            .map(|group| widest(group))
+            .enumerate()
+            .filter(|(_, (range, _))| *range > 0)
+            .max_by_key(|(_, (range, _))| *range)
+        else {
+            break;
+        };
+        let mut group = groups.swap_remove(index);
+        group.sort_by_key(|(color, _)| color[channel]);
+        let half = group.iter().map(|(_, count)| count).sum::<u64>() / 2;
+        let mut seen = 0;
+        let split = group
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen > half
+            })
+            .unwrap_or(0)
+            .clamp(1, group.len() - 1);
+        let rest = group.split_off(split);
+        groups.push(group);
+        groups.push(rest);
+    }
+
+    groups
+        .iter()
+        .map(|group| {
+            let total = group.iter().map(|(_, count)| count).sum::<u64>().max(1);
+            let mut sum = [0u64; 3];
+            for (color, count) in group {
+                for channel in 0..3 {
+                    sum[channel] += color[channel] as u64 * count;
+                }
+            }
+            sum.map(|sum| ((sum + total / 2) / total) as u8)
+        })
+        .collect()
+}
+
+/// Path data outlining the pixels set in `mask`, holes included under the even-odd rule.
+fn path_data(mask: &[bool], width: usize, height: usize, options: &TraceOptions) -> String {
+    let mut d = String::new();
+    for contour in contours(mask, width, height) {
+        if area(&contour).abs() <= options.speckle as f64 {
+            continue;
+        }
+        let polygon = simplify(&contour, TOLERANCE);
+        if polygon.len() >= 3 {
+            write_curve(&mut d, &polygon, options.smoothness as f64);
+        }
+    }
+    d
+}
+
+/// Closed outlines along the pixel edges, with the set pixels on their right, as the grid
+/// points where they turn.
+fn contours(mask: &[bool], width: usize, height: usize) -> Vec<Vec<(f64, f64)>> {
+    // Right, down, left and up; turning right adds one.
+    const STEPS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let stride = width + 1;
+    let inside = |x: isize, y: isize| {
+        x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height && {
+            mask[y as usize * width + x as usize]
+        }
+    };
+
+    // The directions of the edges leaving each grid point, as bit flags.
+    let mut edges = vec![0u8; stride * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            if !mask[y * width + x] {
+                continue;
+            }
+            let (ix, iy) = (x as isize, y as isize);
+            if !inside(ix, iy - 1) {
+                edges[y * stride + x] |= 1 << 0;
+            }
+            if !inside(ix + 1, iy) {
+                edges[y * stride + x + 1] |= 1 << 1;
+            }
+            if !inside(ix, iy + 1) {
+                edges[(y + 1) * stride + x + 1] |= 1 << 2;
+            }
+            if !inside(ix - 1, iy) {
+                edges[(y + 1) * stride + x] |= 1 << 3;
+            }
+        }
+    }
+
+    let mut contours = Vec::new();
+    for start in 0..edges.len() {
+        while edges[start] != 0 {
+            let first_direction = edges[start].trailing_zeros() as usize;
+            let mut direction = first_direction;
+            let mut point = start;
+            let mut corners = Vec::new();
+            loop {
+                edges[point] &= !(1 << direction);
+                let (dx, dy) = STEPS[direction];
+                point = (point as isize + dy * stride as isize + dx) as usize;
+                // Where two pixels only touch at a corner, turning left joins them.
+                let next = [(direction + 3) % 4, direction, (direction + 1) % 4]
+                    .into_iter()
+                    .find(|&next| edges[point] & (1 << next) != 0);
+                let turned = next.map_or(direction != first_direction, |next| next != direction);
+                if turned {
+                    corners.push(((point % stride) as f64, (point / stride) as f64));
+                }
+                match next {
+                    Some(next) => direction = next,
+                    None => break,
+                }
+            }
+            contours.push(corners);
+        }
+    }
+    contours
+}
+
+/// Signed area of a closed polygon.
+fn area(points: &[(f64, f64)]) -> f64 {
+    let mut sum = 0.0;
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        sum += x0 * y1 - x1 * y0;
+    }
+    sum / 2.0
+}
+
+/// The polygon left after dropping the points of a closed outline within `tolerance` of
+/// the line between the points kept around them (Douglas–Peucker).
+fn simplify(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    let count = points.len();
+    if count <= 4 {
+        return points.to_vec();
+    }
+    // The loop is split in two at the point farthest from the first.
+    let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+    let farthest = (1..count)
+        .max_by(|&a, &b| distance(points[0], points[a]).total_cmp(&distance(points[0], points[b])))
+        .unwrap();
+    let mut keep = vec![false; count];
+    keep[0] = true;
+    keep[farthest] = true;
+    let mut spans = vec![(0, farthest), (farthest, count)];
+    while let Some((first, last)) = spans.pop() {
+        let (a, b) = (points[first], points[last % count]);
+        let length = distance(a, b);
+        let mut worst = (0.0, first);
+        for (i, &p) in points.iter().enumerate().take(last).skip(first + 1) {
+            let offset = if length == 0.0 {
+                distance(a, p)
+            } else {
+                ((b.0 - a.0) * (a.1 - p.1) - (a.0 - p.0) * (b.1 - a.1)).abs() / length
+            };
+            if offset > worst.0 {
+                worst = (offset, i);
+            }
+        }
+        if worst.0 > tolerance {
+            keep[worst.1] = true;
+            spans.push((first, worst.1));
+            spans.push((worst.1, last));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&point, keep)| keep.then_some(point))
+        .collect()
+}
+
+/// Appends the polygon as a closed path running through the middle of each side, with
+/// each vertex either kept as a corner or rounded into a Bézier curve the way potrace
+/// does, depending on how sharp it is compared with `alpha_max`.
+fn write_curve(d: &mut String, polygon: &[(f64, f64)], alpha_max: f64) {
+    let count = polygon.len();
+    let lerp =
+        |a: (f64, f64), b: (f64, f64), t: f64| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+    let middle = |i: usize| lerp(polygon[i % count], polygon[(i + 1) % count], 0.5);
+    let point = |(x, y): (f64, f64)| format!("{} {}", number(x), number(y));
+
+    let _ = write!(d, "M{}", point(middle(count - 1)));
+    for j in 0..count {
+        let (a, b, c) = (
+            polygon[(j + count - 1) % count],
+            polygon[j],
+            polygon[(j + 1) % count],
+        );
+        // How far the vertex sticks out past the line joining its neighbours, measured
+        // against that line's length along the nearest axis.
+        let sign = |v: f64| (v > 0.0) as i8 as f64 - (v < 0.0) as i8 as f64;
+        let (ox, oy) = (-sign(c.1 - a.1), sign(c.0 - a.0));
+        let denominator = oy * (c.0 - a.0) - ox * (c.1 - a.1);
+        let alpha = if denominator == 0.0 {
+            4.0 / 3.0
+        } else {
+            let cross = (b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1);
+            let ratio = (cross / denominator).abs();
+            let alpha = if ratio > 1.0 { 1.0 - 1.0 / ratio } else { 0.0 };
+            alpha / 0.75
+        };
+        let end = middle(j);
+        if alpha >= alpha_max {
+            let _ = write!(d, "L{}L{}", point(b), point(end));
+        } else {
+            let t = 0.5 + 0.5 * alpha.clamp(0.55, 1.0);
+            let _ = write!(
+                d,
+                "C{} {} {}",
+                point(lerp(a, b, t)),
+                point(lerp(c, b, t)),
+                point(end)
+            );
+        }
+    }
+    d.push('Z');
+}
@@ -0,0 +1,203 @@
+use crate::app::{show_preview, MAX_PREVIEW_SIDE};
+use eframe::egui;
+use resvg::tiny_skia::Pixmap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use svg_to_png::convert::{parse_tree, render_tree, ConversionOptions};
+use svg_to_png::trace::{self, TraceMode, TraceOptions, RASTER_EXTENSIONS};
+
+/// The image to SVG tab, which traces a raster image into vector shapes.
+///
+/// Tracing runs on a thread whenever the input or settings change, so dragging a slider
+/// doesn't stall the UI on large images.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TraceTab {
+    #[serde(skip)]
+    input_path: String,
+    output_path: String,
+    options: TraceOptions,
+    /// The traced markup and its rendered preview.
+    #[serde(skip)]
+    traced: Option<(String, egui::TextureHandle)>,
+    #[serde(skip)]
+    traced_key: Option<(String, TraceOptions)>,
+    #[serde(skip)]
+    tracing: Option<Receiver<Result<(String, Pixmap), String>>>,
+}
+
+impl Default for TraceTab {
+    fn default() -> Self {
+        Self {
+            input_path: String::new(),
+            output_path: String::from("traced.svg"),
+            options: TraceOptions::default(),
+            traced: None,
+            traced_key: None,
+            tracing: None,
+        }
+    }
+}
+
+impl TraceTab {
+    /// Uses `path` as the input, writing the SVG next to it.
+    pub fn set_input(&mut self, path: &Path) {
+        self.input_path = path.display().to_string();
+        self.output_path = path.with_extension("svg").display().to_string();
+    }
+
+    /// Starts tracing when the input or settings change and picks up the result.
+    pub fn update(&mut self, ctx: &egui::Context, status_message: &mut String) {
+        let key = (self.input_path.clone(), self.options.clone());
+        if self.tracing.is_none() && self.traced_key.as_ref() != Some(&key) {
+            self.traced_key = Some(key);
+            if self.input_path.is_empty() {
+                self.traced = None;
+                return;
+            }
+            let (sender, tracing) = mpsc::channel();
+            let (input_path, options) = (self.input_path.clone(), self.options.clone());
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                let _ =
+                    sender.send(trace_preview(&input_path, &options).map_err(|e| e.to_string()));
+                ctx.request_repaint();
+            });
+            self.tracing = Some(tracing);
+        }
+
+        let Some(result) = self.tracing.as_ref().and_then(|t| t.try_recv().ok()) else {
+            return;
+        };
+        self.tracing = None;
+        match result {
+            Ok((markup, pixmap)) => {
+                let image = egui::ColorImage::from_rgba_premultiplied(
+                    [pixmap.width() as usize, pixmap.height() as usize],
+                    pixmap.data(),
+                );
+                let texture =
+                    ctx.load_texture("trace_preview", image, egui::TextureOptions::LINEAR);
+                self.traced = Some((markup, texture));
+            }
+            Err(e) => {
+                self.traced = None;
+                *status_message = format!("Error tracing {}: {}", self.input_path, e);
+            }
+        }
+    }
+
+    pub fn show_preview(&self, ui: &mut egui::Ui) {
+        show_preview(ui, self.traced.as_ref().map(|(_, texture)| texture));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, status_message: &mut String) {
+        ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+            ui.heading("Image to SVG");
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                ui.label("Input image:");
+                ui.text_edit_singleline(&mut self.input_path);
+                if ui.button("Browse").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Images", &RASTER_EXTENSIONS)
+                        .pick_file()
+                    {
+                        self.set_input(&path);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Output SVG:");
+                ui.text_edit_singleline(&mut self.output_path);
+                if ui.button("Browse").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("SVG files", &["svg"])
+                        .save_file()
+                    {
+                        self.output_path = path.display().to_string();
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let options = &mut self.options;
+                ui.label("Mode:");
+                egui::ComboBox::from_id_source("trace_mode")
+                    .selected_text(options.mode.name())
+                    .show_ui(ui, |ui| {
+                        for mode in TraceMode::ALL {
+                            ui.selectable_value(&mut options.mode, mode, mode.name());
+                        }
+                    });
+                match options.mode {
+                    TraceMode::Bilevel => {
+                        ui.label("Threshold:");
+                        ui.add(egui::Slider::new(&mut options.threshold, 0..=255))
+                            .on_hover_text("Pixels darker than this are traced");
+                    }
+                    TraceMode::Color => {
+                        ui.label("Colors:");
+                        ui.add(egui::DragValue::new(&mut options.colors).clamp_range(2..=256));
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                let options = &mut self.options;
+                ui.label("Drop speckles up to:");
+                ui.add(
+                    egui::DragValue::new(&mut options.speckle)
+                        .clamp_range(0..=10_000)
+                        .suffix(" px"),
+                );
+                ui.label("Smoothness:");
+                ui.add(egui::Slider::new(&mut options.smoothness, 0.0..=1.34))
+                    .on_hover_text("0 keeps every corner sharp; higher values round more of them");
+            });
+
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                let ready = self.tracing.is_none() && self.traced.is_some();
+                if ui
+                    .add_enabled(ready, egui::Button::new("Save SVG"))
+                    .clicked()
+                {
+                    if let Some((markup, _)) = &self.traced {
+                        *status_message = match std::fs::write(&self.output_path, markup) {
+                            Ok(()) => format!("Traced {} to {}", self.input_path, self.output_path),
+                            Err(e) => format!("Error: {}", e),
+                        };
+                    }
+                }
+                if self.tracing.is_some() {
+                    ui.spinner();
+                }
+            });
+
+            ui.add_space(5.0);
+            ui.label(status_message.as_str());
+        });
+    }
+}
+
+/// Traces the image and renders the result, fitted to the preview size.
+fn trace_preview(
+    input_path: &str,
+    options: &TraceOptions,
+) -> Result<(String, Pixmap), Box<dyn std::error::Error>> {
+    let image = image::open(input_path)?.into_rgba8();
+    let markup = trace::trace(&image, options);
+
+    let (width, height) = image.dimensions();
+    let fit = (MAX_PREVIEW_SIDE as f32 / width.max(height) as f32).min(1.0);
+    let preview_options = ConversionOptions {
+        width: Some(((width as f32 * fit).round() as u32).max(1)),
+        height: Some(((height as f32 * fit).round() as u32).max(1)),
+        ..ConversionOptions::default()
+    };
+    let rtree = parse_tree(&markup, input_path, &preview_options)?;
+    let pixmap = render_tree(&rtree, &preview_options)?;
+    Ok((markup, pixmap))
+}
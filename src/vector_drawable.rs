@@ -0,0 +1,342 @@
+use crate::convert::ConversionOptions;
+use crate::svg_writer::{escape, number};
+use std::fmt::Write;
+use usvg::{
+    BaseGradient, FillRule, LineCap, LineJoin, Node, NodeExt, NodeKind, Paint, PathData,
+    PathSegment, SpreadMethod, Transform, Tree, Units, Visibility,
+};
+
+/// Writes the tree as an Android VectorDrawable, sized in dp from the output size.
+///
+/// VectorDrawables only hold paths, so transforms are baked into the path data, group
+/// opacity is folded into each path's alpha and gradients use `aapt:attr`. Images, filters
+/// and masks are left out, and patterns are drawn flat gray.
+pub fn save(
+    rtree: &Tree,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(output_path, to_xml(rtree, options))?;
+    Ok(())
+}
+
+/// The VectorDrawable XML written by [`save`].
+pub fn to_xml(rtree: &Tree, options: &ConversionOptions) -> String {
+    let (width, height) =
+        options.output_size(rtree.size.width() as f32, rtree.size.height() as f32);
+    let view_box = rtree.view_box.rect;
+
+    let mut body = String::new();
+    let viewport = Transform::new_translate(-view_box.x(), -view_box.y());
+    for child in rtree.root.children() {
+        write_node(&mut body, &child, viewport, 1.0, 1);
+    }
+
+    let mut xml =
+        String::from("<vector xmlns:android=\"http://schemas.android.com/apk/res/android\"\n");
+    if body.contains("<aapt:attr") {
+        xml.push_str("    xmlns:aapt=\"http://schemas.android.com/aapt\"\n");
+    }
+    let _ = write!(
+        xml,
+        "    android:width=\"{}dp\"\n    android:height=\"{}dp\"\n    \
+         android:viewportWidth=\"{}\"\n    android:viewportHeight=\"{}\">\n",
+        width,
+        height,
+        number(view_box.width()),
+        number(view_box.height())
+    );
+    xml.push_str(&body);
+    xml.push_str("</vector>\n");
+    xml
+}
+
+/// Writes `node` at `depth` levels of indentation. `viewport` maps user space to the
+/// drawable's viewport and `opacity` is the product of the enclosing groups' opacities.
+fn write_node(xml: &mut String, node: &Node, viewport: Transform, opacity: f64, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let mut transform = viewport;
+    transform.append(&node.abs_transform());
+
+    match *node.borrow() {
+        NodeKind::Group(ref group) => {
+            let _ = write!(xml, "{}<group", indent);
+            if !group.id.is_empty() {
+                let _ = write!(xml, " android:name=\"{}\"", escape(&group.id));
+            }
+            xml.push_str(">\n");
+
+            // Clip paths apply in the group's user space; bounding-box units are skipped.
+            if let Some(clip) = group
+                .clip_path
+                .as_ref()
+                .filter(|clip| clip.units == Units::UserSpaceOnUse)
+            {
+                let mut clip_transform = transform;
+                clip_transform.append(&clip.transform);
+                let mut data = String::new();
+                for child in clip.root.descendants() {
+                    if let NodeKind::Path(ref path) = *child.borrow() {
+                        let mut path_transform = clip_transform;
+                        path_transform.append(&child.abs_transform());
+                        data.push_str(&path_data(&path.data, path_transform));
+                    }
+                }
+                if !data.is_empty() {
+                    let _ = writeln!(
+                        xml,
+                        "{}    <clip-path android:pathData=\"{}\" />",
+                        indent,
+                        data.trim()
+                    );
+                }
+            }
+
+            let opacity = opacity * group.opacity.get();
+            for child in node.children() {
+                write_node(xml, &child, viewport, opacity, depth + 1);
+            }
+            let _ = writeln!(xml, "{}</group>", indent);
+        }
+        NodeKind::Path(ref path) => {
+            if path.visibility != Visibility::Visible {
+                return;
+            }
+            let data = path_data(&path.data, transform);
+            if data.is_empty() {
+                return;
+            }
+
+            let _ = write!(xml, "{}<path", indent);
+            let attribute_indent = format!("\n{}    ", indent);
+            if !path.id.is_empty() {
+                let _ = write!(
+                    xml,
+                    "{}android:name=\"{}\"",
+                    attribute_indent,
+                    escape(&path.id)
+                );
+            }
+            let _ = write!(
+                xml,
+                "{}android:pathData=\"{}\"",
+                attribute_indent,
+                data.trim()
+            );
+
+            let mut gradients = Vec::new();
+            if let Some(fill) = &path.fill {
+                match solid_color(&fill.paint) {
+                    Some(color) => {
+                        let _ = write!(xml, "{}android:fillColor=\"{}\"", attribute_indent, color);
+                    }
+                    None => gradients.push(("android:fillColor", &fill.paint)),
+                }
+                let alpha = opacity * fill.opacity.get();
+                if alpha < 1.0 {
+                    let _ = write!(
+                        xml,
+                        "{}android:fillAlpha=\"{}\"",
+                        attribute_indent,
+                        number(alpha)
+                    );
+                }
+                if fill.rule == FillRule::EvenOdd {
+                    let _ = write!(xml, "{}android:fillType=\"evenOdd\"", attribute_indent);
+                }
+            }
+            if let Some(stroke) = &path.stroke {
+                match solid_color(&stroke.paint) {
+                    Some(color) => {
+                        let _ =
+                            write!(xml, "{}android:strokeColor=\"{}\"", attribute_indent, color);
+                    }
+                    None => gradients.push(("android:strokeColor", &stroke.paint)),
+                }
+                let alpha = opacity * stroke.opacity.get();
+                if alpha < 1.0 {
+                    let _ = write!(
+                        xml,
+                        "{}android:strokeAlpha=\"{}\"",
+                        attribute_indent,
+                        number(alpha)
+                    );
+                }
+                let _ = write!(
+                    xml,
+                    "{}android:strokeWidth=\"{}\"",
+                    attribute_indent,
+                    number(stroke.width.get() * scale(transform))
+                );
+                let cap = match stroke.linecap {
+                    LineCap::Butt => "butt",
+                    LineCap::Round => "round",
+                    LineCap::Square => "square",
+                };
+                let join = match stroke.linejoin {
+                    LineJoin::Miter => "miter",
+                    LineJoin::Round => "round",
+                    LineJoin::Bevel => "bevel",
+                };
+                let _ = write!(
+                    xml,
+                    "{0}android:strokeLineCap=\"{1}\"{0}android:strokeLineJoin=\"{2}\"",
+                    attribute_indent, cap, join
+                );
+                if stroke.linejoin == LineJoin::Miter {
+                    let _ = write!(
+                        xml,
+                        "{}android:strokeMiterLimit=\"{}\"",
+                        attribute_indent,
+                        number(stroke.miterlimit.get())
+                    );
+                }
+            }
+
+            // Gradients need the object's bounding box for `objectBoundingBox` units.
+            let gradients: Vec<(&str, String)> = gradients
+                .into_iter()
+                .filter_map(|(name, paint)| {
+                    Some((name, gradient(paint, &path.data, transform, depth + 2)?))
+                })
+                .collect();
+            if gradients.is_empty() {
+                xml.push_str(" />\n");
+            } else {
+                xml.push_str(">\n");
+                for (name, gradient) in gradients {
+                    let _ = writeln!(xml, "{}    <aapt:attr name=\"{}\">", indent, name);
+                    xml.push_str(&gradient);
+                    let _ = writeln!(xml, "{}    </aapt:attr>", indent);
+                }
+                let _ = writeln!(xml, "{}</path>", indent);
+            }
+        }
+        NodeKind::Image(_) | NodeKind::Text(_) => {}
+    }
+}
+
+/// Path data in absolute commands after applying `transform`, with a trailing space.
+fn path_data(data: &PathData, transform: Transform) -> String {
+    let mut out = String::new();
+    let point = |x: f64, y: f64| {
+        let (x, y) = transform.apply(x, y);
+        format!("{},{}", number(x), number(y))
+    };
+    for segment in data.segments() {
+        let _ = match segment {
+            PathSegment::MoveTo { x, y } => write!(out, "M{} ", point(x, y)),
+            PathSegment::LineTo { x, y } => write!(out, "L{} ", point(x, y)),
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => write!(out, "C{} {} {} ", point(x1, y1), point(x2, y2), point(x, y)),
+            PathSegment::ClosePath => write!(out, "Z "),
+        };
+    }
+    out
+}
+
+fn solid_color(paint: &Paint) -> Option<String> {
+    match paint {
+        Paint::Color(color) => Some(format!(
+            "#{:02X}{:02X}{:02X}",
+            color.red, color.green, color.blue
+        )),
+        // VectorDrawables have no patterns; a gray stand-in keeps the shape visible.
+        Paint::Pattern(_) => Some(String::from("#808080")),
+        Paint::LinearGradient(_) | Paint::RadialGradient(_) => None,
+    }
+}
+
+/// An `aapt:attr` `<gradient>` element for a gradient paint on `data`.
+fn gradient(paint: &Paint, data: &PathData, transform: Transform, depth: usize) -> Option<String> {
+    let (base, attributes): (&BaseGradient, Vec<(&str, f64)>) = match paint {
+        Paint::LinearGradient(linear) => {
+            let transform = gradient_transform(&linear.base, data, transform)?;
+            let (start_x, start_y) = transform.apply(linear.x1, linear.y1);
+            let (end_x, end_y) = transform.apply(linear.x2, linear.y2);
+            (
+                &linear.base,
+                vec![
+                    ("startX", start_x),
+                    ("startY", start_y),
+                    ("endX", end_x),
+                    ("endY", end_y),
+                ],
+            )
+        }
+        Paint::RadialGradient(radial) => {
+            let transform = gradient_transform(&radial.base, data, transform)?;
+            let (center_x, center_y) = transform.apply(radial.cx, radial.cy);
+            (
+                &radial.base,
+                vec![
+                    ("centerX", center_x),
+                    ("centerY", center_y),
+                    ("gradientRadius", radial.r.get() * scale(transform)),
+                ],
+            )
+        }
+        Paint::Color(_) | Paint::Pattern(_) => return None,
+    };
+
+    let indent = "    ".repeat(depth);
+    let mut xml = format!(
+        "{}<gradient android:type=\"{}\"",
+        indent,
+        if matches!(paint, Paint::LinearGradient(_)) {
+            "linear"
+        } else {
+            "radial"
+        }
+    );
+    for (name, value) in attributes {
+        let _ = write!(xml, " android:{}=\"{}\"", name, number(value));
+    }
+    let tile_mode = match base.spread_method {
+        SpreadMethod::Pad => "clamp",
+        SpreadMethod::Reflect => "mirror",
+        SpreadMethod::Repeat => "repeat",
+    };
+    let _ = writeln!(xml, " android:tileMode=\"{}\">", tile_mode);
+    for stop in &base.stops {
+        let _ = writeln!(
+            xml,
+            "{}    <item android:offset=\"{}\" android:color=\"#{:02X}{:02X}{:02X}{:02X}\" />",
+            indent,
+            number(stop.offset.get()),
+            (stop.opacity.get() * 255.0).round() as u8,
+            stop.color.red,
+            stop.color.green,
+            stop.color.blue
+        );
+    }
+    let _ = writeln!(xml, "{}</gradient>", indent);
+    Some(xml)
+}
+
+/// Maps gradient coordinates to the viewport, through the object's bounding box if needed.
+fn gradient_transform(
+    base: &BaseGradient,
+    data: &PathData,
+    mut transform: Transform,
+) -> Option<Transform> {
+    if base.units == Units::ObjectBoundingBox {
+        let bbox = data.bbox()?.to_rect()?;
+        transform.append(&Transform::from_bbox(bbox));
+    }
+    transform.append(&base.transform);
+    Some(transform)
+}
+
+/// Average scale of a transform, for widths and radii.
+fn scale(transform: Transform) -> f64 {
+    (transform.a * transform.d - transform.b * transform.c)
+        .abs()
+        .sqrt()
+}
@@ -0,0 +1,120 @@
+use crate::animation;
+use crate::convert::ConversionOptions;
+use crate::encode::{self, OutputFormat};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Numbers the temporary files, so encodes running at once don't share one.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Renders the animation in `svg_data` and streams the frames to `ffmpeg`, which encodes
+/// them as `options.format` into `output_path`.
+pub fn save(
+    svg_data: &str,
+    input_path: &str,
+    output_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ffmpeg: Option<Child> = None;
+    let rendered = animation::render_frames(svg_data, input_path, options, check_cancel, |frame| {
+        let child = match &mut ffmpeg {
+            Some(child) => child,
+            None => ffmpeg.insert(spawn(frame.width(), frame.height(), output_path, options)?),
+        };
+        let pixels = match options.format {
+            // MP4 players don't show alpha, so frames are flattened like JPEG output.
+            OutputFormat::Mp4 => {
+                encode::flatten(&frame, options.background.unwrap_or([255, 255, 255]))
+            }
+            _ => encode::unpremultiply(&frame),
+        };
+        // A closed pipe means ffmpeg gave up; its own message is reported below.
+        match child.stdin.as_mut().unwrap().write_all(&pixels) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+            result => Ok(result?),
+        }
+    });
+
+    let Some(mut child) = ffmpeg else {
+        return rendered;
+    };
+    drop(child.stdin.take());
+    let output = child.wait_with_output()?;
+    if rendered.is_err() || !output.status.success() {
+        let _ = std::fs::remove_file(output_path);
+    }
+    rendered?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed: {}", message.trim()).into());
+    }
+    Ok(())
+}
+
+/// Encodes the animation through a temporary file, since MP4 can't be streamed out
+/// without fragmenting it.
+pub fn encode(
+    svg_data: &str,
+    input_path: &str,
+    options: &ConversionOptions,
+    check_cancel: &dyn Fn() -> Result<(), &'static str>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!(
+        "rust-svg-converter-{}-{}.{}",
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed),
+        options.format.extension()
+    ));
+    let path = path.display().to_string();
+    let saved = save(svg_data, input_path, &path, options, check_cancel);
+    let data = saved.and_then(|()| Ok(std::fs::read(&path)?));
+    let _ = std::fs::remove_file(&path);
+    data
+}
+
+/// Starts ffmpeg reading raw `width`×`height` frames from stdin.
+fn spawn(
+    width: u32,
+    height: u32,
+    output_path: &str,
+    options: &ConversionOptions,
+) -> Result<Child, Box<dyn std::error::Error>> {
+    let (input_format, codec) = match options.format {
+        OutputFormat::Mp4 => ("rgb24", ["-c:v", "libx264", "-pix_fmt", "yuv420p"]),
+        OutputFormat::Webm => ("rgba", ["-c:v", "libvpx-vp9", "-pix_fmt", "yuva420p"]),
+        format => return Err(format!("{} output isn't a video", format.name()).into()),
+    };
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-f", "rawvideo", "-pix_fmt", input_format])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-framerate", &options.animation.fps.to_string()])
+        .args(["-i", "-"])
+        .args(codec);
+    if options.format == OutputFormat::Mp4 {
+        // H.264 in 4:2:0 needs even dimensions.
+        command.args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"]);
+    }
+    match options.animation.bitrate {
+        Some(kbps) => command.args(["-b:v", &format!("{}k", kbps)]),
+        // VP9 otherwise targets a low fixed bitrate; constant quality suits graphics better.
+        None if options.format == OutputFormat::Webm => command.args(["-crf", "32", "-b:v", "0"]),
+        None => &mut command,
+    };
+    command
+        .arg(Path::new(output_path))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    command.spawn().map_err(|e| {
+        if e.kind() == ErrorKind::NotFound {
+            "ffmpeg was not found on PATH; install it to export MP4 and WebM".into()
+        } else {
+            e.into()
+        }
+    })
+}
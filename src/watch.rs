@@ -0,0 +1,55 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// Watches a single file and reports when it has been written.
+///
+/// The parent directory is watched rather than the file itself, because many editors save by
+/// replacing the file, which would otherwise end the watch.
+pub struct FileWatcher {
+    path: String,
+    changes: Receiver<()>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str, on_change: impl Fn() + Send + 'static) -> notify::Result<Self> {
+        let file = PathBuf::from(path);
+        let file_name = file.file_name().map(|name| name.to_os_string());
+        let (sender, changes) = mpsc::channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                let touches_file = event.paths.iter().any(|changed| {
+                    changed.file_name().map(|name| name.to_os_string()) == file_name
+                });
+                if touches_file && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                {
+                    let _ = sender.send(());
+                    on_change();
+                }
+            })?;
+
+        let directory = match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path: path.to_string(),
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns whether the file changed since the last call, coalescing bursts of events.
+    pub fn changed(&self) -> bool {
+        self.changes.try_iter().count() > 0
+    }
+}
@@ -0,0 +1,187 @@
+use eframe::egui;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use svg_to_png::atlas::{build_atlas, AtlasOptions};
+use svg_to_png::convert::{
+    convert_file, convert_svg, BatchError, BatchJob, BatchResult, ConversionOptions, StageTimings,
+};
+
+pub enum WorkerEvent {
+    /// The item at this index started converting.
+    ItemStarted(usize),
+    /// The item at this index finished converting, taking this long in each stage.
+    ItemDone(usize, BatchResult, StageTimings),
+    /// Every item finished, or the batch could not start at all.
+    Finished(Result<(), String>),
+}
+
+/// Jobs of a running batch not yet picked up by a thread.
+struct PendingJobs {
+    jobs: VecDeque<(usize, BatchJob)>,
+    /// Threads still taking jobs; once none are left, added jobs would never run.
+    threads: usize,
+}
+
+impl PendingJobs {
+    /// The next job, or `None` after which the calling thread stops.
+    fn next(pending: &Mutex<PendingJobs>) -> Option<(usize, BatchJob)> {
+        let mut pending = pending.lock().unwrap();
+        let job = pending.jobs.pop_front();
+        if job.is_none() {
+            pending.threads -= 1;
+        }
+        job
+    }
+}
+
+/// Runs conversions on a background thread so the UI stays responsive.
+pub struct Worker {
+    events: Receiver<WorkerEvent>,
+    cancel: Arc<AtomicBool>,
+    /// Set for batches, which can take more jobs while they run.
+    pending: Option<Arc<Mutex<PendingJobs>>>,
+}
+
+impl Worker {
+    /// Converts the jobs on `threads` threads, reporting each under its position in `jobs`.
+    pub fn spawn(jobs: Vec<BatchJob>, threads: usize, ctx: egui::Context) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build();
+        let pending = Arc::new(Mutex::new(PendingJobs {
+            jobs: jobs.into_iter().enumerate().collect(),
+            threads: pool.as_ref().map_or(0, |pool| pool.current_num_threads()),
+        }));
+
+        let shared = Arc::clone(&pending);
+        let mut worker = Self::run(ctx, move |cancel, send| {
+            let pool = match pool {
+                Ok(pool) => pool,
+                Err(e) => return send(WorkerEvent::Finished(Err(e.to_string()))),
+            };
+            pool.scope(|scope| {
+                for _ in 0..pool.current_num_threads() {
+                    scope.spawn(|_| {
+                        while let Some((index, job)) = PendingJobs::next(&shared) {
+                            send(WorkerEvent::ItemStarted(index));
+                            let mut timings = StageTimings::default();
+                            let result = convert_file(
+                                &job.input,
+                                &job.output_template,
+                                &job.options,
+                                cancel,
+                                &mut timings,
+                            )
+                            .map_err(BatchError::from);
+                            send(WorkerEvent::ItemDone(index, result, timings));
+                        }
+                    });
+                }
+            });
+            send(WorkerEvent::Finished(Ok(())));
+        });
+        worker.pending = Some(pending);
+        worker
+    }
+
+    /// Adds a job to a running batch, reported under `index`. Returns `false` if the batch
+    /// has already wound down or this worker isn't running one.
+    pub fn add_job(&self, index: usize, job: BatchJob) -> bool {
+        let Some(pending) = &self.pending else {
+            return false;
+        };
+        let mut pending = pending.lock().unwrap();
+        if pending.threads == 0 {
+            return false;
+        }
+        pending.jobs.push_back((index, job));
+        true
+    }
+
+    /// Converts SVG markup held in memory as a single item named `input_name`.
+    pub fn spawn_markup(
+        svg_data: String,
+        input_name: String,
+        output_template: String,
+        options: ConversionOptions,
+        ctx: egui::Context,
+    ) -> Self {
+        Self::run(ctx, move |cancel, send| {
+            send(WorkerEvent::ItemStarted(0));
+            let mut timings = StageTimings::default();
+            let result = convert_svg(
+                &svg_data,
+                &input_name,
+                &output_template,
+                &options,
+                cancel,
+                &mut timings,
+            )
+            .map_err(BatchError::from);
+            send(WorkerEvent::ItemDone(0, result, timings));
+            send(WorkerEvent::Finished(Ok(())));
+        })
+    }
+
+    /// Packs every input into one sprite sheet, reported as a single item.
+    pub fn spawn_atlas(
+        inputs: Vec<String>,
+        output_path: String,
+        options: ConversionOptions,
+        atlas: AtlasOptions,
+        ctx: egui::Context,
+    ) -> Self {
+        Self::run(ctx, move |cancel, send| {
+            send(WorkerEvent::ItemStarted(0));
+            let started = Instant::now();
+            let result = build_atlas(&inputs, &output_path, &options, &atlas, cancel)
+                .map_err(BatchError::from);
+            // The sheet is rendered and encoded in one go.
+            let timings = StageTimings {
+                render: started.elapsed(),
+                total: started.elapsed(),
+                ..StageTimings::default()
+            };
+            send(WorkerEvent::ItemDone(0, result, timings));
+            send(WorkerEvent::Finished(Ok(())));
+        })
+    }
+
+    fn run(
+        ctx: egui::Context,
+        work: impl FnOnce(&AtomicBool, &(dyn Fn(WorkerEvent) + Sync)) + Send + 'static,
+    ) -> Self {
+        let (sender, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let flag = Arc::clone(&cancel);
+        thread::spawn(move || {
+            work(&flag, &|event| {
+                let _ = sender.send(event);
+                ctx.request_repaint();
+            });
+        });
+
+        Self {
+            events,
+            cancel,
+            pending: None,
+        }
+    }
+
+    /// Asks the worker to stop; items already rendering finish their current stage first.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Events received since the last poll.
+    pub fn poll(&self) -> Vec<WorkerEvent> {
+        self.events.try_iter().collect()
+    }
+}